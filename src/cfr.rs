@@ -1,5 +1,5 @@
 use rand::distributions::Distribution;
-use crate::game::{Game, Player, Infoset};
+use crate::game::{Game, UndoGame, CloneUndo, IllegalMove, Player, Infoset};
 use crate::regret;
 
 pub struct CounterFactualRegret {
@@ -47,8 +47,17 @@ impl CounterFactualRegret {
         };
     }
 
-    pub fn search<T>(&mut self, mut game: T, depth: u32) -> Option<f32>
+    pub fn search<T>(&mut self, game: T, depth: u32) -> Option<f32>
         where T: Game + Clone
+    {
+        self.search_in_place(&mut CloneUndo(game), depth)
+    }
+
+    /// Searches by applying and undoing turns on a single game
+    ///
+    /// The game is restored to its original state before this returns
+    pub fn search_in_place<T>(&mut self, game: &mut T, depth: u32) -> Option<f32>
+        where T: UndoGame
     {
         if self.verbose {
             println!("---------------");
@@ -66,61 +75,94 @@ impl CounterFactualRegret {
             }
         }
 
+        //at simultaneous turns, the opponent's action is sampled without knowing ours
+        if let Some(actions) = game.get_simultaneous_turn() {
+            let on_player = self.on_player;
+            let other_action = self.sample_action(game, on_player.other(), on_player.other().lens(&actions).as_slice())?;
+            return self.search_own_turn(game, depth, on_player, on_player.lens(&actions).as_slice(), |game, action| {
+                game.apply_simultaneous_turn(on_player.pair(action, &other_action))
+            });
+        }
+
         let (player, actions) = game.get_turn();
-        let infoset = game.get_infoset(player);
         if player == self.on_player {
-            let probs = self.get_iter_strategy(player, &infoset, actions.len())?;
-
-            let mut rewards = vec![];
-            for action in actions.into_iter() {
-                let mut subgame = game.clone();
-                subgame.take_turn(player, &action);
-                let reward = self.search(subgame, depth+1);
-                let reward = reward?;
-                rewards.push(reward);
-            }
+            self.search_own_turn(game, depth, player, &actions, |game, action| game.apply_turn(player, action))
+        } else {
+            let action = self.sample_action(game, player, &actions)?;
+            let undo = game.apply_turn(player, &action)
+                .expect("Failed to take legal action");
+            let reward = self.search_in_place(game, depth+1);
+            game.undo_turn(undo);
 
-            let expected_value: f32 = probs.iter().zip(rewards.iter())
-                .map(|(p, r)| p * r)
-                .sum();
-            let regrets = rewards.into_iter().map(|r| r - expected_value).collect();
+            reward
+        }
+    }
 
-            self.regret_handler
-                .as_mut()
-                .expect("Tried to search in a stategy-only cfr instance")
-                .send_delta(player, infoset.hash, regrets, self.iteration / 2 + 1)
-                .expect("Failed to send regret delta");
+    /// Searches every action of the player being updated and sends their regrets
+    ///
+    /// `apply` takes the turn for the given action
+    fn search_own_turn<T, F>(&mut self, game: &mut T, depth: u32, player: Player, actions: &[T::Action], apply: F) -> Option<f32>
+        where T: UndoGame,
+              F: Fn(&mut T, &T::Action) -> Result<T::Undo, IllegalMove<T::Action>>
+    {
+        let (infoset, action_ids) = game.get_canonical_action_ids(player, actions);
+        let probs = self.get_iter_strategy(player, &infoset, &action_ids)?;
+
+        let mut rewards = vec![];
+        for action in actions.iter() {
+            let undo = apply(game, action)
+                .expect("Failed to take legal action");
+            let reward = self.search_in_place(game, depth+1);
+            game.undo_turn(undo);
+            let reward = reward?;
+            rewards.push(reward);
+        }
 
-            Some(expected_value)
+        let expected_value: f32 = probs.iter().zip(rewards.iter())
+            .map(|(p, r)| p * r)
+            .sum();
+        let regrets = rewards.into_iter().map(|r| r - expected_value).collect();
 
-        } else {
-            let probs = self.get_iter_strategy(player, &infoset, actions.len())?;
-            let sampler = rand::distributions::WeightedIndex::new(&probs).unwrap();
+        self.regret_handler
+            .as_mut()
+            .expect("Tried to search in a stategy-only cfr instance")
+            .send_delta(player, infoset.hash, regret::ActionValues::new(action_ids, regrets), self.iteration / 2 + 1)
+            .expect("Failed to send regret delta");
 
-            self.strat_handler.send_delta(player, infoset.hash, probs, self.iteration)
-                .expect("Failed to update average strategy");
+        Some(expected_value)
+    }
 
-            let action_index = sampler.sample(&mut rand::thread_rng());
-            let action = &actions[action_index];
-            game.take_turn(player, action);
+    /// Samples an action for the player who isn't being updated and adds their strategy to the average
+    fn sample_action<T: Game>(&mut self, game: &T, player: Player, actions: &[T::Action]) -> Option<T::Action> {
+        let (infoset, action_ids) = game.get_canonical_action_ids(player, actions);
+        let probs = self.get_iter_strategy(player, &infoset, &action_ids)?;
+        let sampler = rand::distributions::WeightedIndex::new(&probs).unwrap();
 
-            self.search(game, depth+1)
-        }
+        self.strat_handler.send_delta(player, infoset.hash, regret::ActionValues::new(action_ids, probs), self.iteration)
+            .expect("Failed to update average strategy");
+
+        let action_index = sampler.sample(&mut rand::thread_rng());
+        Some(actions[action_index].clone())
     }
 
-    pub fn get_avg_strategy(&self, player: Player, infoset: &Infoset, num_actions: usize) -> Option<Vec<f32>> {
-        CounterFactualRegret::regret_match(&self.strat_handler, player, infoset, num_actions)
+    /// Gets the player's average strategy over the given actions, in the same order as the actions
+    ///
+    /// Strategies are stored for canonical infosets, so symmetric infosets share one
+    pub fn get_avg_strategy<T: Game>(&self, game: &T, player: Player, actions: &[T::Action]) -> Option<Vec<f32>> {
+        let (infoset, action_ids) = game.get_canonical_action_ids(player, actions);
+        CounterFactualRegret::regret_match(&self.strat_handler, player, &infoset, &action_ids)
     }
 
-    fn get_iter_strategy(&mut self, player: Player, infoset: &Infoset, num_actions: usize) -> Option<Vec<f32>> {
+    fn get_iter_strategy(&mut self, player: Player, infoset: &Infoset, action_ids: &[usize]) -> Option<Vec<f32>> {
         let regret_handler = self.regret_handler
             .as_mut()
             .expect("Tried to get iter stategy in a strategy-only cfr instance");
-        CounterFactualRegret::regret_match(&regret_handler, player, infoset, num_actions)
+        CounterFactualRegret::regret_match(&regret_handler, player, infoset, action_ids)
     }
 
-    fn regret_match(regret_handler: &Box<dyn regret::RegretHandler>, player: Player, infoset: &Infoset, num_actions: usize) -> Option<Vec<f32>>
+    fn regret_match(regret_handler: &Box<dyn regret::RegretHandler>, player: Player, infoset: &Infoset, action_ids: &[usize]) -> Option<Vec<f32>>
     {
+        let num_actions = action_ids.len();
         //no need to get the regrets if the probs is always 1
         if num_actions == 1 {
             return Some(vec![1.0]);
@@ -135,7 +177,7 @@ impl CounterFactualRegret {
         };
 
         let regrets = match regrets {
-            Some(regret) => regret,
+            Some(regret) => regret.get(action_ids),
             None => vec![0.0; num_actions],
         };
 
@@ -161,4 +203,49 @@ impl CounterFactualRegret {
         }
     }
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use crate::game::KuhnPoker;
+    use crate::regret::{HashRegretProvider, RegretProvider, RegretConfig};
+
+    /// P1's expected reward when both players use their average strategies
+    fn avg_strategy_value<T: Game + Clone>(cfr: &CounterFactualRegret, game: &T) -> f32 {
+        if let Some(reward) = game.get_reward() {
+            return reward;
+        }
+        let (player, actions) = game.get_turn();
+        let probs = cfr.get_avg_strategy(game, player, &actions).unwrap();
+        actions.iter().zip(probs.iter()).map(|(action, prob)| {
+            let mut subgame = game.clone();
+            subgame.take_turn(player, action).unwrap();
+            prob * avg_strategy_value(cfr, &subgame)
+        }).sum()
+    }
+
+    #[test]
+    fn converges_on_kuhn_poker() {
+        let mut regret_config = RegretConfig { alpha: 1.5, beta: 0.0, gamma: 2.0, is_strategy: false };
+        let mut regret_provider = HashRegretProvider::new();
+        regret_provider.set_config(&regret_config);
+        regret_config.is_strategy = true;
+        let mut strategy_provider = HashRegretProvider::new();
+        strategy_provider.set_config(&regret_config);
+
+        let mut cfr = CounterFactualRegret::new(regret_provider.get_handler(), strategy_provider.get_handler());
+        thread::spawn(move || regret_provider.run());
+        thread::spawn(move || strategy_provider.run());
+
+        for iteration in 0..20_000 {
+            cfr.set_iteration(iteration);
+            cfr.search(KuhnPoker::new(), 0).unwrap();
+        }
+
+        let value: f32 = KuhnPoker::all_deals().iter()
+            .map(|(game, prob)| prob * avg_strategy_value(&cfr, game))
+            .sum();
+        assert!((value + 1.0 / 18.0).abs() < 0.01, "P1's value {} is too far from -1/18", value);
+    }
+}
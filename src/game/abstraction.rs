@@ -0,0 +1,425 @@
+use std::fmt;
+use std::sync::Arc;
+use rand::distributions::Distribution;
+
+use crate::game::{Game, UndoGame, IllegalMove, Player, Infoset, Observation, PublicState};
+
+/// Maps a game's infosets to coarser abstract infosets, so similar infosets share a strategy
+///
+/// The abstract infoset must only depend on what the player can see.
+/// Abstractions that forget earlier decisions lose perfect recall, which CFR tolerates but can't guarantee convergence with
+pub trait InfosetAbstraction<G: Game>: Send + Sync {
+    fn get_abstract_infoset(&self, game: &G, player: Player) -> Infoset;
+}
+
+impl<G: Game, F: Fn(&G, Player) -> Infoset + Send + Sync> InfosetAbstraction<G> for F {
+    fn get_abstract_infoset(&self, game: &G, player: Player) -> Infoset {
+        self(game, player)
+    }
+}
+
+/// Plays the game as normal, but players only see abstract infosets
+///
+/// Actions aren't abstracted, so a strategy for an abstract infoset is already a strategy over the real actions.
+/// Anything that trains or plays on this game uses the abstraction without knowing about it
+#[derive(Clone)]
+pub struct Abstracted<G: Game> {
+    game: G,
+    abstraction: Arc<dyn InfosetAbstraction<G>>,
+}
+
+impl<G: Game> Abstracted<G> {
+    pub fn new(game: G, abstraction: impl InfosetAbstraction<G> + 'static) -> Abstracted<G> {
+        Abstracted {
+            game,
+            abstraction: Arc::new(abstraction),
+        }
+    }
+}
+
+impl<G: Game + Clone> Game for Abstracted<G> {
+    type Action = G::Action;
+
+    fn get_turn(&self) -> (Player, Vec<G::Action>) {
+        self.game.get_turn()
+    }
+
+    fn get_simultaneous_turn(&self) -> Option<(Vec<G::Action>, Vec<G::Action>)> {
+        self.game.get_simultaneous_turn()
+    }
+
+    fn take_turn(&mut self, player: Player, action: &G::Action) -> Result<(), IllegalMove<G::Action>> {
+        self.game.take_turn(player, action)
+    }
+
+    fn take_simultaneous_turn(&mut self, actions: (&G::Action, &G::Action)) -> Result<(), IllegalMove<G::Action>> {
+        self.game.take_simultaneous_turn(actions)
+    }
+
+    fn take_turn_outcomes(&self, player: Player, action: &G::Action) -> Result<Vec<(Self, f32)>, IllegalMove<G::Action>> {
+        let outcomes = self.game.take_turn_outcomes(player, action)?;
+        Ok(outcomes.into_iter().map(|(game, prob)| (Abstracted { game, abstraction: self.abstraction.clone() }, prob)).collect())
+    }
+
+    fn take_simultaneous_turn_outcomes(&self, actions: (&G::Action, &G::Action)) -> Result<Vec<(Self, f32)>, IllegalMove<G::Action>> {
+        let outcomes = self.game.take_simultaneous_turn_outcomes(actions)?;
+        Ok(outcomes.into_iter().map(|(game, prob)| (Abstracted { game, abstraction: self.abstraction.clone() }, prob)).collect())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        self.game.num_action_ids()
+    }
+
+    fn action_id(&self, action: &G::Action) -> usize {
+        self.game.action_id(action)
+    }
+
+    fn parse_action(&self, action: &str) -> Option<G::Action> {
+        self.game.parse_action(action)
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        self.game.get_reward()
+    }
+
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        self.game.get_utility_bounds()
+    }
+
+    fn get_infoset(&self, player: Player) -> Infoset {
+        self.abstraction.get_abstract_infoset(&self.game, player)
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        self.game.get_public_observations()
+    }
+
+    fn get_private_observation(&self, player: Player) -> Observation {
+        self.game.get_private_observation(player)
+    }
+
+    fn get_public_state(&self) -> PublicState {
+        self.game.get_public_state()
+    }
+
+    fn get_summary_string(&self, player: Player) -> String {
+        self.game.get_summary_string(player)
+    }
+}
+
+impl<G: UndoGame + Clone> UndoGame for Abstracted<G> {
+    type Undo = G::Undo;
+
+    fn apply_turn(&mut self, player: Player, action: &G::Action) -> Result<G::Undo, IllegalMove<G::Action>> {
+        self.game.apply_turn(player, action)
+    }
+
+    fn apply_simultaneous_turn(&mut self, actions: (&G::Action, &G::Action)) -> Result<G::Undo, IllegalMove<G::Action>> {
+        self.game.apply_simultaneous_turn(actions)
+    }
+
+    fn undo_turn(&mut self, undo: G::Undo) {
+        self.game.undo_turn(undo)
+    }
+}
+
+impl<G: Game> fmt::Display for Abstracted<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        self.game.fmt(f)
+    }
+}
+
+/// Restricts which actions players can pick, so games with many similar actions are smaller to train on
+pub trait ActionAbstraction<G: Game>: Send + Sync {
+    /// The actions the player keeps out of all their legal actions, which can't be empty if `actions` isn't
+    fn restrict(&self, game: &G, player: Player, actions: Vec<G::Action>) -> Vec<G::Action>;
+
+    /// Maps an action that isn't one of the kept actions onto them, with the probability of each
+    ///
+    /// The action was legal in the real game, but might not be in this game if earlier actions were translated.
+    /// If there's nothing to map it onto, players go back to seeing the real game's infosets
+    fn translate(&self, game: &G, player: Player, action: &G::Action, kept: &[G::Action]) -> Vec<(G::Action, f32)>;
+}
+
+/// Pseudo-harmonic action translation from Ganzfried and Sandholm
+///
+/// Gives the probability of mapping `x` onto the smaller size `below` rather than the larger size `above`
+pub fn pseudo_harmonic(below: f32, above: f32, x: f32) -> f32 {
+    ((above - x) * (1.0 + below)) / ((above - below) * (1.0 + x))
+}
+
+/// Actions with the probability of taking each one
+type Translations<A> = Vec<(A, f32)>;
+
+/// Only lets players pick the actions kept by an action abstraction
+///
+/// Actions that are legal but weren't kept, like an opponent's bet size that wasn't trained on,
+/// are still taken in the real game, but players see the infosets of a translated game
+/// where the action was taken as one of the kept actions, so their strategies stay on the abstract tree.
+/// Chance in the translated game is sampled separately from the real game
+#[derive(Clone)]
+pub struct ActionAbstracted<G: Game> {
+    game: G,
+    /// The game as if every action that wasn't kept had been taken as its translation,
+    /// or None while every action so far was kept
+    translated: Option<G>,
+    abstraction: Arc<dyn ActionAbstraction<G>>,
+}
+
+impl<G: Game + Clone> ActionAbstracted<G> {
+    pub fn new(game: G, abstraction: impl ActionAbstraction<G> + 'static) -> ActionAbstracted<G> {
+        ActionAbstracted {
+            game,
+            translated: None,
+            abstraction: Arc::new(abstraction),
+        }
+    }
+
+    /// The real game underneath the abstraction
+    pub fn get_game(&self) -> &G {
+        &self.game
+    }
+
+    /// The game that players see their infosets in
+    fn view(&self) -> &G {
+        self.translated.as_ref().unwrap_or(&self.game)
+    }
+
+    /// Distribution over the kept actions in the translated game that the action is taken as
+    ///
+    /// Empty if the player can't move in the translated game, so it can't follow the real game any more
+    fn translate(&self, player: Player, action: &G::Action) -> Translations<G::Action> {
+        let view = self.view();
+        let actions = match view.get_simultaneous_turn() {
+            Some(actions) => player.lens(&actions).clone(),
+            None => match view.get_turn() {
+                (turn, actions) if turn == player => actions,
+                _ => vec![],
+            },
+        };
+        if actions.is_empty() {
+            return vec![];
+        }
+        let kept = self.abstraction.restrict(view, player, actions);
+        let action_id = view.action_id(action);
+        if kept.iter().any(|a| view.action_id(a) == action_id) {
+            vec![(action.clone(), 1.0)]
+        } else {
+            self.abstraction.translate(view, player, action, &kept)
+        }
+    }
+
+    /// Translations of the action, or None if the game stays on the abstract tree
+    fn turn_translations(&self, player: Player, action: &G::Action) -> Option<Translations<G::Action>> {
+        let translations = self.translate(player, action);
+        if self.translated.is_none() && self.is_kept(action, &translations) {
+            return None;
+        }
+        Some(translations)
+    }
+
+    /// Translations of both players' actions, or None if the game stays on the abstract tree
+    fn simultaneous_translations(&self, actions: (&G::Action, &G::Action)) -> Option<Translations<(G::Action, G::Action)>> {
+        let translations1 = self.translate(Player::P1, actions.0);
+        let translations2 = self.translate(Player::P2, actions.1);
+        if self.translated.is_none() && self.is_kept(actions.0, &translations1) && self.is_kept(actions.1, &translations2) {
+            return None;
+        }
+        Some(translations1.into_iter()
+            .flat_map(|(action1, prob1)| translations2.iter()
+                .map(move |(action2, prob2)| ((action1.clone(), action2.clone()), prob1 * prob2)))
+            .collect())
+    }
+
+    /// Whether the action is always taken as itself
+    fn is_kept(&self, action: &G::Action, translations: &[(G::Action, f32)]) -> bool {
+        translations.len() == 1 && self.game.action_id(&translations[0].0) == self.game.action_id(action)
+    }
+
+    /// The translated game after taking a sampled translation, or None if it's the real game
+    fn translated_after<T>(&self, translations: Option<Translations<T>>, take: impl Fn(&mut G, &T) -> Result<(), IllegalMove<G::Action>>) -> Option<G> {
+        let mut translations = translations?;
+        if translations.is_empty() {
+            return None;
+        }
+        let probs: Vec<f32> = translations.iter().map(|(_, prob)| *prob).collect();
+        let sampler = rand::distributions::WeightedIndex::new(&probs).unwrap();
+        let translation = translations.swap_remove(sampler.sample(&mut rand::thread_rng())).0;
+        let mut translated = self.view().clone();
+        take(&mut translated, &translation).ok()?;
+        Some(translated)
+    }
+
+    /// Every translated game after each translation and each way chance could play out in it
+    fn translated_outcomes<T>(&self, translations: Option<Translations<T>>, take: impl Fn(&G, &T) -> Result<Vec<(G, f32)>, IllegalMove<G::Action>>) -> Vec<(Option<G>, f32)> {
+        let translations = translations.unwrap_or_default();
+        if translations.is_empty() {
+            return vec![(None, 1.0)];
+        }
+        let mut outcomes = vec![];
+        for (translation, prob) in translations {
+            match take(self.view(), &translation) {
+                Ok(games) => outcomes.extend(games.into_iter().map(|(game, p)| (Some(game), p * prob))),
+                Err(_) => outcomes.push((None, prob)),
+            }
+        }
+        outcomes
+    }
+
+    /// Pairs up every real outcome with every translated outcome
+    fn combine_outcomes(&self, outcomes: Vec<(G, f32)>, translated: Vec<(Option<G>, f32)>) -> Vec<(Self, f32)> {
+        let mut all_outcomes = vec![];
+        for (game, prob) in outcomes {
+            for (translated, translated_prob) in translated.iter() {
+                all_outcomes.push((ActionAbstracted {
+                    game: game.clone(),
+                    translated: translated.clone(),
+                    abstraction: self.abstraction.clone(),
+                }, prob * translated_prob));
+            }
+        }
+        all_outcomes
+    }
+}
+
+impl<G: Game + Clone> Game for ActionAbstracted<G> {
+    type Action = G::Action;
+
+    fn get_turn(&self) -> (Player, Vec<G::Action>) {
+        let (player, actions) = self.game.get_turn();
+        (player, self.abstraction.restrict(&self.game, player, actions))
+    }
+
+    fn get_simultaneous_turn(&self) -> Option<(Vec<G::Action>, Vec<G::Action>)> {
+        let (actions1, actions2) = self.game.get_simultaneous_turn()?;
+        Some((
+            self.abstraction.restrict(&self.game, Player::P1, actions1),
+            self.abstraction.restrict(&self.game, Player::P2, actions2),
+        ))
+    }
+
+    fn take_turn(&mut self, player: Player, action: &G::Action) -> Result<(), IllegalMove<G::Action>> {
+        let translations = self.turn_translations(player, action);
+        let translated = self.translated_after(translations, |game, a| game.take_turn(player, a));
+        self.game.take_turn(player, action)?;
+        self.translated = translated;
+        Ok(())
+    }
+
+    fn take_simultaneous_turn(&mut self, actions: (&G::Action, &G::Action)) -> Result<(), IllegalMove<G::Action>> {
+        let translations = self.simultaneous_translations(actions);
+        let translated = self.translated_after(translations, |game, (a, b)| game.take_simultaneous_turn((a, b)));
+        self.game.take_simultaneous_turn(actions)?;
+        self.translated = translated;
+        Ok(())
+    }
+
+    /// Each translation of the action is a separate outcome
+    fn take_turn_outcomes(&self, player: Player, action: &G::Action) -> Result<Vec<(Self, f32)>, IllegalMove<G::Action>> {
+        let outcomes = self.game.take_turn_outcomes(player, action)?;
+        let translations = self.turn_translations(player, action);
+        let translated = self.translated_outcomes(translations, |game, a| game.take_turn_outcomes(player, a));
+        Ok(self.combine_outcomes(outcomes, translated))
+    }
+
+    fn take_simultaneous_turn_outcomes(&self, actions: (&G::Action, &G::Action)) -> Result<Vec<(Self, f32)>, IllegalMove<G::Action>> {
+        let outcomes = self.game.take_simultaneous_turn_outcomes(actions)?;
+        let translations = self.simultaneous_translations(actions);
+        let translated = self.translated_outcomes(translations, |game, (a, b)| game.take_simultaneous_turn_outcomes((a, b)));
+        Ok(self.combine_outcomes(outcomes, translated))
+    }
+
+    fn num_action_ids(&self) -> usize {
+        self.game.num_action_ids()
+    }
+
+    fn action_id(&self, action: &G::Action) -> usize {
+        self.game.action_id(action)
+    }
+
+    fn parse_action(&self, action: &str) -> Option<G::Action> {
+        self.game.parse_action(action)
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        self.game.get_reward()
+    }
+
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        self.game.get_utility_bounds()
+    }
+
+    fn get_infoset(&self, player: Player) -> Infoset {
+        self.view().get_infoset(player)
+    }
+
+    fn get_canonical_infoset(&self, player: Player) -> (Infoset, Option<Vec<usize>>) {
+        self.view().get_canonical_infoset(player)
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        self.view().get_public_observations()
+    }
+
+    fn get_private_observation(&self, player: Player) -> Observation {
+        self.view().get_private_observation(player)
+    }
+
+    fn get_public_state(&self) -> PublicState {
+        self.view().get_public_state()
+    }
+
+    fn get_summary_string(&self, player: Player) -> String {
+        self.game.get_summary_string(player)
+    }
+}
+
+impl<G: UndoGame + Clone> UndoGame for ActionAbstracted<G> {
+    //the translated game before the turn is put back as it was
+    type Undo = (G::Undo, Option<G>);
+
+    fn apply_turn(&mut self, player: Player, action: &G::Action) -> Result<Self::Undo, IllegalMove<G::Action>> {
+        let translations = self.turn_translations(player, action);
+        let translated = self.translated_after(translations, |game, a| game.take_turn(player, a));
+        let undo = self.game.apply_turn(player, action)?;
+        Ok((undo, std::mem::replace(&mut self.translated, translated)))
+    }
+
+    fn apply_simultaneous_turn(&mut self, actions: (&G::Action, &G::Action)) -> Result<Self::Undo, IllegalMove<G::Action>> {
+        let translations = self.simultaneous_translations(actions);
+        let translated = self.translated_after(translations, |game, (a, b)| game.take_simultaneous_turn((a, b)));
+        let undo = self.game.apply_simultaneous_turn(actions)?;
+        Ok((undo, std::mem::replace(&mut self.translated, translated)))
+    }
+
+    fn undo_turn(&mut self, undo: Self::Undo) {
+        self.game.undo_turn(undo.0);
+        self.translated = undo.1;
+    }
+}
+
+impl<G: Game> fmt::Display for ActionAbstracted<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        self.game.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MatrixGame;
+
+    #[test]
+    fn uses_abstract_infosets() {
+        let game = Abstracted::new(MatrixGame::new_rock_paper_scissors(), |_: &MatrixGame, player| Infoset::new(player as u8));
+        assert_eq!(game.get_infoset(Player::P2).hash, Infoset::new(Player::P2 as u8).hash);
+        assert_eq!(game.game.get_infoset(Player::P2).hash, Infoset::new(0).hash);
+    }
+
+    #[test]
+    fn translates_pseudo_harmonically() {
+        assert_eq!(pseudo_harmonic(1.0, 3.0, 1.0), 1.0);
+        assert_eq!(pseudo_harmonic(1.0, 3.0, 3.0), 0.0);
+        assert!((pseudo_harmonic(1.0, 3.0, 2.0) - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
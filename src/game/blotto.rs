@@ -0,0 +1,277 @@
+//https://en.wikipedia.org/wiki/Blotto_game
+
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::game::{Game, UndoGame, IllegalMove, IllegalMoveReason, Player, Infoset, Observation};
+use crate::game::{GameRecord, RecordedTurn, RecordGame, RecordError};
+
+/// Soldiers sent to each battlefield
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub struct Allocation(pub Vec<u32>);
+
+/// Most soldiers each player can have
+pub const MAX_SOLDIERS: u32 = 30;
+/// Most battlefields there can be
+pub const MAX_BATTLEFIELDS: usize = 10;
+/// Most ways to split the soldiers, since every one is an action that gets listed and stored
+pub const MAX_ALLOCATIONS: usize = 5000;
+
+/// Ways to split the soldiers between the battlefields, which is soldiers + battlefields - 1 choose battlefields - 1
+pub fn num_allocations(soldiers: u32, battlefields: usize) -> usize {
+    let soldiers = soldiers as usize;
+    (1..battlefields).fold(1, |count, i| count * (soldiers + i) / i)
+}
+
+/// Whether a game with this many soldiers and battlefields is small enough to play
+pub fn is_playable(soldiers: u32, battlefields: usize) -> bool {
+    (1..=MAX_SOLDIERS).contains(&soldiers) && (1..=MAX_BATTLEFIELDS).contains(&battlefields)
+        && num_allocations(soldiers, battlefields) <= MAX_ALLOCATIONS
+}
+
+/// Every way to split the soldiers between the battlefields, in the order of their action ids
+fn all_allocations(soldiers: u32, battlefields: usize) -> Vec<Allocation> {
+    let mut allocations = Vec::with_capacity(num_allocations(soldiers, battlefields));
+    add_allocations(soldiers, battlefields, &mut Vec::with_capacity(battlefields), &mut allocations);
+    allocations
+}
+
+/// Adds every way to finish the partial allocation, sending fewer soldiers to earlier battlefields first
+fn add_allocations(soldiers: u32, battlefields: usize, partial: &mut Vec<u32>, allocations: &mut Vec<Allocation>) {
+    if battlefields == 1 {
+        partial.push(soldiers);
+        allocations.push(Allocation(partial.clone()));
+        partial.pop();
+        return;
+    }
+    for sent in 0..=soldiers {
+        partial.push(sent);
+        add_allocations(soldiers - sent, battlefields - 1, partial, allocations);
+        partial.pop();
+    }
+}
+
+/// Colonel Blotto, where both players split their soldiers between the battlefields at once
+///
+/// Sending more soldiers to a battlefield wins it, and whoever wins more battlefields wins the game
+#[derive(Clone, Debug)]
+pub struct Blotto {
+    soldiers: u32,
+    battlefields: usize,
+    /// Every legal allocation, which is only built once and shared between copies of the game
+    choices: Arc<Vec<Allocation>>,
+    allocations: Option<(Allocation, Allocation)>,
+}
+
+impl Blotto {
+    /// Panics unless `is_playable(soldiers, battlefields)`
+    pub fn new(soldiers: u32, battlefields: usize) -> Blotto {
+        assert!(is_playable(soldiers, battlefields), "Blotto with {} soldiers on {} battlefields is too big", soldiers, battlefields);
+        Blotto {
+            soldiers,
+            battlefields,
+            choices: Arc::new(all_allocations(soldiers, battlefields)),
+            allocations: None,
+        }
+    }
+
+    fn is_valid(&self, allocation: &Allocation) -> bool {
+        allocation.0.len() == self.battlefields && allocation.0.iter().sum::<u32>() == self.soldiers
+    }
+}
+
+impl Game for Blotto {
+    type Action = Allocation;
+
+    fn get_turn(&self) -> (Player, Vec<Allocation>) {
+        match self.get_simultaneous_turn() {
+            Some((allocations, _)) => (Player::P1, allocations),
+            None => (Player::P1, vec![]),
+        }
+    }
+
+    fn get_simultaneous_turn(&self) -> Option<(Vec<Allocation>, Vec<Allocation>)> {
+        if self.allocations.is_some() {
+            return None;
+        }
+        Some((self.choices.to_vec(), self.choices.to_vec()))
+    }
+
+    fn take_turn(&mut self, player: Player, action: &Allocation) -> Result<(), IllegalMove<Allocation>> {
+        let reason = if self.allocations.is_some() {
+            IllegalMoveReason::GameOver
+        } else {
+            IllegalMoveReason::InvalidAction("both players move at once")
+        };
+        Err(IllegalMove::new(player, action, reason))
+    }
+
+    fn take_simultaneous_turn(&mut self, actions: (&Allocation, &Allocation)) -> Result<(), IllegalMove<Allocation>> {
+        if self.allocations.is_some() {
+            return Err(IllegalMove::new(Player::P1, actions.0, IllegalMoveReason::GameOver));
+        }
+        for player in [Player::P1, Player::P2].iter() {
+            let allocation = *player.lens(&actions);
+            if !self.is_valid(allocation) {
+                return Err(IllegalMove::new(*player, allocation, IllegalMoveReason::InvalidAction("soldiers don't match the battlefields")));
+            }
+        }
+
+        self.allocations = Some((actions.0.clone(), actions.1.clone()));
+        Ok(())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        self.choices.len()
+    }
+
+    fn action_id(&self, action: &Allocation) -> usize {
+        //count the allocations that come first, which send fewer soldiers to an earlier battlefield
+        let mut id = 0;
+        let mut soldiers = self.soldiers;
+        for (i, sent) in action.0.iter().enumerate().take(self.battlefields - 1) {
+            id += (0..*sent).map(|fewer| num_allocations(soldiers - fewer, self.battlefields - i - 1)).sum::<usize>();
+            soldiers -= sent;
+        }
+        id
+    }
+
+    fn parse_action(&self, action: &str) -> Option<Allocation> {
+        let allocation = action.split('/')
+            .map(|soldiers| soldiers.parse().ok())
+            .collect::<Option<_>>()
+            .map(Allocation)?;
+        Some(allocation).filter(|allocation| self.is_valid(allocation))
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        let (allocation1, allocation2) = self.allocations.as_ref()?;
+        let won = allocation1.0.iter().zip(allocation2.0.iter()).filter(|(a, b)| a > b).count();
+        let lost = allocation1.0.iter().zip(allocation2.0.iter()).filter(|(a, b)| a < b).count();
+        if won > lost {
+            Some(1.0)
+        } else if lost > won {
+            Some(-1.0)
+        } else {
+            Some(0.0)
+        }
+    }
+
+    fn get_infoset(&self, _player: Player) -> Infoset {
+        //nothing is visible before both players move
+        Infoset::new(0)
+    }
+
+    fn get_private_observation(&self, _player: Player) -> Observation {
+        //nothing is private either, since the game ends as soon as the allocations are seen
+        Observation::new(0)
+    }
+}
+
+impl UndoGame for Blotto {
+    //the only turn is the simultaneous one
+    type Undo = ();
+
+    fn apply_turn(&mut self, player: Player, action: &Allocation) -> Result<(), IllegalMove<Allocation>> {
+        self.take_turn(player, action)
+    }
+
+    fn apply_simultaneous_turn(&mut self, actions: (&Allocation, &Allocation)) -> Result<(), IllegalMove<Allocation>> {
+        self.take_simultaneous_turn(actions)
+    }
+
+    fn undo_turn(&mut self, _undo: ()) {
+        self.allocations = None;
+    }
+}
+
+impl RecordGame for Blotto {
+    fn get_record(&self) -> GameRecord {
+        let mut record = GameRecord::new("blotto");
+        record.add_setup("soldiers", self.soldiers);
+        record.add_setup("battlefields", self.battlefields);
+        if let Some((allocation1, allocation2)) = &self.allocations {
+            record.turns.push(RecordedTurn::new(Player::P1, allocation1));
+            record.turns.push(RecordedTurn::new(Player::P2, allocation2));
+        }
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<Blotto, RecordError> {
+        record.check_game("blotto")?;
+        let soldiers = record.get_setup("soldiers")?;
+        let battlefields = record.get_setup("battlefields")?;
+        if !is_playable(soldiers, battlefields) {
+            return Err(RecordError::BadSetup { key: "battlefields", value: battlefields.to_string() });
+        }
+        let mut game = Blotto::new(soldiers, battlefields);
+        record.replay(&mut game)?;
+        Ok(game)
+    }
+}
+
+impl Display for Allocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let soldiers: Vec<String> = self.0.iter().map(|soldiers| soldiers.to_string()).collect();
+        write!(f, "{}", soldiers.join("/"))
+    }
+}
+
+impl Display for Blotto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} soldiers on {} battlefields", self.soldiers, self.battlefields)?;
+        if let Some((allocation1, allocation2)) = &self.allocations {
+            writeln!(f, "P1 sent {}, P2 sent {}", allocation1, allocation2)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_allocations_by_id() {
+        let game = Blotto::new(4, 3);
+        let (allocations, _) = game.get_simultaneous_turn().unwrap();
+        assert_eq!(allocations.len(), 15);
+        assert_eq!(game.num_action_ids(), 15);
+        assert_eq!(allocations[0], Allocation(vec![0, 0, 4]));
+        assert_eq!(allocations[14], Allocation(vec![4, 0, 0]));
+        assert_eq!(game.action_ids(&allocations), (0..15).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn wins_more_battlefields() {
+        let mut game = Blotto::new(6, 3);
+        assert!(game.take_simultaneous_turn((&Allocation(vec![3, 3]), &Allocation(vec![2, 2, 2]))).is_err());
+        assert!(game.take_simultaneous_turn((&Allocation(vec![3, 3, 3]), &Allocation(vec![2, 2, 2]))).is_err());
+
+        //P1 wins two battlefields and loses one
+        game.take_simultaneous_turn((&Allocation(vec![3, 3, 0]), &Allocation(vec![2, 2, 2]))).unwrap();
+        assert_eq!(game.get_reward(), Some(1.0));
+
+        game.undo_turn(());
+        //a tied battlefield goes to nobody
+        game.take_simultaneous_turn((&Allocation(vec![4, 2, 0]), &Allocation(vec![2, 2, 2]))).unwrap();
+        assert_eq!(game.get_reward(), Some(0.0));
+    }
+
+    #[test]
+    fn parses_allocations() {
+        let game = Blotto::new(5, 3);
+        assert_eq!(game.parse_action("1/0/4"), Some(Allocation(vec![1, 0, 4])));
+        assert_eq!(game.parse_action("1/1/4"), None);
+        assert_eq!(game.parse_action("5/0"), None);
+        assert_eq!(game.parse_action(&Allocation(vec![2, 2, 1]).to_string()), Some(Allocation(vec![2, 2, 1])));
+    }
+
+    #[test]
+    fn limits_allocations() {
+        assert_eq!(num_allocations(30, 10), 211915132);
+        assert!(!is_playable(30, 10));
+        assert!(is_playable(30, 3));
+        assert!(!is_playable(0, 3));
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+
+use crate::game::{Game, UndoGame, IllegalMove, Player, Infoset, Observation, PublicState};
+
+/// Lets any cloneable game be used where an `UndoGame` is expected
+///
+/// This saves a copy of the whole game for each turn, so it's no faster than cloning,
+/// but it means the solvers only need an in-place implementation
+#[derive(Clone, Debug)]
+pub struct CloneUndo<G>(pub G);
+
+impl<G: Game + Clone> Game for CloneUndo<G> {
+    type Action = G::Action;
+
+    fn get_turn(&self) -> (Player, Vec<G::Action>) {
+        self.0.get_turn()
+    }
+
+    fn get_simultaneous_turn(&self) -> Option<(Vec<G::Action>, Vec<G::Action>)> {
+        self.0.get_simultaneous_turn()
+    }
+
+    fn take_turn(&mut self, player: Player, action: &G::Action) -> Result<(), IllegalMove<G::Action>> {
+        self.0.take_turn(player, action)
+    }
+
+    fn take_simultaneous_turn(&mut self, actions: (&G::Action, &G::Action)) -> Result<(), IllegalMove<G::Action>> {
+        self.0.take_simultaneous_turn(actions)
+    }
+
+    fn take_turn_outcomes(&self, player: Player, action: &G::Action) -> Result<Vec<(Self, f32)>, IllegalMove<G::Action>> {
+        let outcomes = self.0.take_turn_outcomes(player, action)?;
+        Ok(outcomes.into_iter().map(|(game, prob)| (CloneUndo(game), prob)).collect())
+    }
+
+    fn take_simultaneous_turn_outcomes(&self, actions: (&G::Action, &G::Action)) -> Result<Vec<(Self, f32)>, IllegalMove<G::Action>> {
+        let outcomes = self.0.take_simultaneous_turn_outcomes(actions)?;
+        Ok(outcomes.into_iter().map(|(game, prob)| (CloneUndo(game), prob)).collect())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        self.0.num_action_ids()
+    }
+
+    fn action_id(&self, action: &G::Action) -> usize {
+        self.0.action_id(action)
+    }
+
+    fn parse_action(&self, action: &str) -> Option<G::Action> {
+        self.0.parse_action(action)
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        self.0.get_reward()
+    }
+
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        self.0.get_utility_bounds()
+    }
+
+    fn get_infoset(&self, player: Player) -> Infoset {
+        self.0.get_infoset(player)
+    }
+
+    fn get_canonical_infoset(&self, player: Player) -> (Infoset, Option<Vec<usize>>) {
+        self.0.get_canonical_infoset(player)
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        self.0.get_public_observations()
+    }
+
+    fn get_private_observation(&self, player: Player) -> Observation {
+        self.0.get_private_observation(player)
+    }
+
+    fn get_public_state(&self) -> PublicState {
+        self.0.get_public_state()
+    }
+
+    fn get_summary_string(&self, player: Player) -> String {
+        self.0.get_summary_string(player)
+    }
+}
+
+impl<G: Game + Clone> UndoGame for CloneUndo<G> {
+    type Undo = G;
+
+    fn apply_turn(&mut self, player: Player, action: &G::Action) -> Result<G, IllegalMove<G::Action>> {
+        let old_game = self.0.clone();
+        self.0.take_turn(player, action)?;
+        Ok(old_game)
+    }
+
+    fn apply_simultaneous_turn(&mut self, actions: (&G::Action, &G::Action)) -> Result<G, IllegalMove<G::Action>> {
+        let old_game = self.0.clone();
+        self.0.take_simultaneous_turn(actions)?;
+        Ok(old_game)
+    }
+
+    fn undo_turn(&mut self, undo: G) {
+        self.0 = undo;
+    }
+}
+
+impl<G: fmt::Display> fmt::Display for CloneUndo<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
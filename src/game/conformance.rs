@@ -0,0 +1,458 @@
+//! Checks that a `Game` implementation follows the rules the solvers rely on
+//!
+//! Small games can be checked exhaustively, larger ones with random playouts.
+//! Infosets are compared across every history that is visited, so the checks
+//! get stronger the more of the game is covered.
+
+use std::collections::HashMap;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::game::{Game, TensorGame, RecordGame, GameRecord, Player, Sequential};
+
+/// An earlier decision of a player, as (infoset hash, action)
+type Decision = (u64, String);
+
+/// Each player's value, like how the rest of the code uses tuples for P1 and P2
+type PerPlayer<T> = (T, T);
+
+struct Checker {
+    /// Whether to check for perfect recall, which abstractions can give up
+    check_recall: bool,
+    /// Utility bounds of the first history, which every other history has to match
+    utility_bounds: Option<(f32, f32)>,
+    /// Each player's earlier decisions that lead to each of their infosets
+    recall: PerPlayer<HashMap<u64, Vec<Decision>>>,
+    /// Actions available at each infoset
+    infoset_actions: PerPlayer<HashMap<u64, Vec<String>>>,
+    /// Canonical action ids available at each canonical infoset
+    canonical_actions: PerPlayer<HashMap<u64, Vec<usize>>>,
+    /// Infoset for each public state and private observation
+    observed_infosets: PerPlayer<HashMap<(u64, u64), u64>>,
+}
+
+impl Checker {
+    fn new(check_recall: bool) -> Checker {
+        Checker {
+            check_recall,
+            utility_bounds: None,
+            recall: (HashMap::new(), HashMap::new()),
+            infoset_actions: (HashMap::new(), HashMap::new()),
+            canonical_actions: (HashMap::new(), HashMap::new()),
+            observed_infosets: (HashMap::new(), HashMap::new()),
+        }
+    }
+
+    /// Checks everything about a single history
+    ///
+    /// Returns the player to move and their actions, or None if the game is over
+    fn check_node<G: Game + Clone>(&mut self, game: &G, decisions: &PerPlayer<Vec<Decision>>) -> Option<(Player, Vec<G::Action>)> {
+        let (player, actions) = game.get_turn();
+
+        //rewards get scaled by the bounds, so they can't change during the game
+        let bounds = game.get_utility_bounds();
+        assert!(bounds.0 < bounds.1, "Utility bounds {:?} are empty\n{}", bounds, game);
+        let seen_bounds = *self.utility_bounds.get_or_insert(bounds);
+        assert_eq!(seen_bounds, bounds, "Utility bounds changed during the game\n{}", game);
+
+        //histories that only differ in the opponent's private information must share an infoset
+        let public_state = game.get_public_state().hash;
+        let infosets = (game.get_infoset(Player::P1).hash, game.get_infoset(Player::P2).hash);
+        for p in [Player::P1, Player::P2].iter() {
+            let key = (public_state, game.get_private_observation(*p).hash);
+            let infoset = *p.lens(&infosets);
+            let seen_infoset = *p.lens_mut(&mut self.observed_infosets).entry(key).or_insert(infoset);
+            assert_eq!(seen_infoset, infoset,
+                "{}'s infoset isn't determined by the public state and their private observation\n{}", p, game);
+        }
+
+        if let Some(reward) = game.get_reward() {
+            assert!(actions.is_empty(), "Terminal state has actions {:?}\n{}", actions, game);
+            let (min, max) = bounds;
+            assert!(min <= reward && reward <= max, "Reward {} is outside of [{}, {}]\n{}", reward, min, max, game);
+            return None;
+        }
+        assert!(!actions.is_empty(), "Unfinished game has no actions\n{}", game);
+
+        //action ids have to tell the actions apart, and every action has to parse back from its string
+        let mut action_ids = vec![];
+        for action in actions.iter() {
+            let id = game.action_id(action);
+            assert!(id < game.num_action_ids(), "Action {:?} has id {} past the number of ids\n{}", action, id, game);
+            action_ids.push(id);
+
+            let parsed = game.parse_action(&action.to_string());
+            assert_eq!(format!("{:?}", parsed), format!("{:?}", Some(action)), "Action {:?} doesn't parse from its string\n{}", action, game);
+        }
+        action_ids.sort();
+        action_ids.dedup();
+        assert_eq!(action_ids.len(), actions.len(), "Actions {:?} share action ids\n{}", actions, game);
+
+        let infoset = *player.lens(&infosets);
+
+        //every history in an infoset must have the same actions
+        let action_strings: Vec<String> = actions.iter().map(|a| format!("{:?}", a)).collect();
+        let seen_actions = player.lens_mut(&mut self.infoset_actions).entry(infoset)
+            .or_insert_with(|| action_strings.clone());
+        assert_eq!(*seen_actions, action_strings, "{}'s infoset has different actions in different histories\n{}", player, game);
+
+        //symmetric infosets share a strategy, so they need the same canonical actions
+        let (canonical_infoset, mut canonical_ids) = game.get_canonical_action_ids(player, &actions);
+        assert!(canonical_ids.iter().all(|id| *id < game.num_action_ids()), "Canonical action ids {:?} are past the number of ids\n{}", canonical_ids, game);
+        canonical_ids.sort();
+        canonical_ids.dedup();
+        assert_eq!(canonical_ids.len(), actions.len(), "Actions {:?} share canonical action ids\n{}", actions, game);
+        let seen_ids = player.lens_mut(&mut self.canonical_actions).entry(canonical_infoset.hash)
+            .or_insert_with(|| canonical_ids.clone());
+        assert_eq!(*seen_ids, canonical_ids, "{}'s canonical infoset has different actions in different histories\n{}", player, game);
+
+        //perfect recall means every history in an infoset has the same earlier decisions
+        if self.check_recall {
+            let own_decisions = player.lens(decisions);
+            let seen_decisions = player.lens_mut(&mut self.recall).entry(infoset)
+                .or_insert_with(|| own_decisions.clone());
+            assert_eq!(seen_decisions, own_decisions, "{}'s infoset doesn't have perfect recall\n{}", player, game);
+        }
+
+        Some((player, actions))
+    }
+
+    fn take_turn<G: Game + Clone>(game: &G, player: Player, action: &G::Action) -> G {
+        let mut subgame = game.clone();
+        subgame.take_turn(player, action)
+            .unwrap_or_else(|err| panic!("Legal action was rejected: {}\n{}", err, game));
+        subgame
+    }
+
+    fn explore<G: Game + Clone>(&mut self, game: &G, decisions: &mut PerPlayer<Vec<Decision>>) -> usize {
+        let (player, actions) = match self.check_node(game, decisions) {
+            Some(turn) => turn,
+            None => return 1,
+        };
+        let infoset = game.get_infoset(player).hash;
+
+        let mut num_histories = 1;
+        for action in actions.iter() {
+            //chance inside of the turn is followed through every outcome
+            let outcomes = game.take_turn_outcomes(player, action)
+                .unwrap_or_else(|err| panic!("Legal action was rejected: {}\n{}", err, game));
+            let total: f32 = outcomes.iter().map(|(_, prob)| prob).sum();
+            assert!(outcomes.iter().all(|(_, prob)| *prob > 0.0) && (total - 1.0).abs() < 1e-4,
+                "Outcomes of {:?} have probabilities adding up to {}\n{}", action, total, game);
+            player.lens_mut(decisions).push((infoset, format!("{:?}", action)));
+            for (subgame, _) in outcomes.iter() {
+                num_histories += self.explore(subgame, decisions);
+            }
+            player.lens_mut(decisions).pop();
+        }
+        num_histories
+    }
+
+    fn play_out<G: Game + Clone>(&mut self, mut game: G) {
+        let mut rng = rand::thread_rng();
+        let mut decisions = (vec![], vec![]);
+        while let Some((player, actions)) = self.check_node(&game, &decisions) {
+            let infoset = game.get_infoset(player).hash;
+            let subgames: Vec<G> = actions.iter()
+                .map(|action| Checker::take_turn(&game, player, action))
+                .collect();
+            let index = (0..actions.len()).collect::<Vec<usize>>()
+                .choose(&mut rng).copied().unwrap();
+
+            player.lens_mut(&mut decisions).push((infoset, format!("{:?}", actions[index])));
+            game = subgames.into_iter().nth(index).unwrap();
+        }
+    }
+}
+
+/// Takes a random turn, returning false if the game is already over
+fn take_random_turn<G: Game>(game: &mut G, rng: &mut impl Rng) -> bool {
+    if let Some((p1_actions, p2_actions)) = game.get_simultaneous_turn() {
+        let actions = (p1_actions.choose(rng), p2_actions.choose(rng));
+        if let (Some(p1_action), Some(p2_action)) = actions {
+            game.take_simultaneous_turn((p1_action, p2_action)).expect("Failed to take legal actions");
+            return true;
+        }
+        panic!("Simultaneous turn is missing actions\n{}", game);
+    }
+
+    let (player, actions) = game.get_turn();
+    match actions.choose(rng) {
+        Some(action) => {
+            game.take_turn(player, action).expect("Failed to take legal action");
+            true
+        },
+        None => false,
+    }
+}
+
+/// Visits every history reachable from the given starting games
+///
+/// Chance inside of a turn is followed through every outcome of `take_turn_outcomes`,
+/// but chance before the first turn has to be given as every possible starting game.
+/// Simultaneous turns are split up with `Sequential`, so they're checked like any other turn.
+///
+/// Returns the number of histories visited
+pub fn check_exhaustive<G: Game + Clone>(roots: Vec<G>) -> usize {
+    let mut checker = Checker::new(true);
+    roots.into_iter()
+        .map(|game| checker.explore(&Sequential::new(game), &mut (vec![], vec![])))
+        .sum()
+}
+
+/// Plays the given number of random games, checking every state along the way
+pub fn check_random_playouts<G: Game + Clone>(new_game: impl Fn() -> G, num_playouts: usize) {
+    let mut checker = Checker::new(true);
+    for _ in 0..num_playouts {
+        checker.play_out(Sequential::new(new_game()));
+    }
+}
+
+/// Same as `check_random_playouts`, but for abstracted games that don't have perfect recall
+pub fn check_abstraction<G: Game + Clone>(new_game: impl Fn() -> G, num_playouts: usize) {
+    let mut checker = Checker::new(false);
+    for _ in 0..num_playouts {
+        checker.play_out(Sequential::new(new_game()));
+    }
+}
+
+/// Plays the given number of random games, checking that the infoset tensors and action masks
+/// keep the same size and that the mask matches the legal actions
+pub fn check_tensors<G: TensorGame>(new_game: impl Fn() -> G, num_playouts: usize) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..num_playouts {
+        let mut game = new_game();
+        let tensor_size = game.tensor_size();
+        let num_actions = game.num_action_ids();
+        loop {
+            for p in [Player::P1, Player::P2].iter() {
+                let tensor = game.get_infoset_tensor(*p);
+                assert_eq!(tensor.len(), tensor_size, "{}'s tensor has the wrong size\n{}", p, game);
+                assert!(tensor.iter().all(|x| x.is_finite()), "{}'s tensor isn't finite {:?}\n{}", p, tensor, game);
+            }
+
+            let mask = game.get_legal_action_mask();
+            let (_, actions) = game.get_turn();
+            assert_eq!(mask.len(), num_actions, "Action mask has the wrong size\n{}", game);
+            assert_eq!(mask.iter().filter(|&&legal| legal).count(), actions.len(),
+                "Action mask doesn't match the legal actions {:?}\n{}", actions, game);
+
+            if !take_random_turn(&mut game, &mut rng) {
+                break;
+            }
+        }
+    }
+}
+
+/// Plays the given number of random games, checking that every position can be
+/// written as a record, read back, and restored to the same position
+pub fn check_records<G: RecordGame>(new_game: impl Fn() -> G, num_playouts: usize) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..num_playouts {
+        let mut game = new_game();
+        loop {
+            let text = game.get_record().to_string();
+            let record = GameRecord::parse(&text)
+                .unwrap_or_else(|err| panic!("Failed to parse record {}: {}\n{}", text, err, game));
+            let restored = G::from_record(&record)
+                .unwrap_or_else(|err| panic!("Failed to restore record {}: {}\n{}", text, err, game));
+
+            assert_eq!(restored.get_record(), record, "Restored game has a different record\n{}", game);
+            assert_eq!(restored.to_string(), game.to_string(), "Restored game is different from {}", text);
+            assert_eq!(restored.get_reward(), game.get_reward(), "Restored game has a different reward\n{}", game);
+            for p in [Player::P1, Player::P2].iter() {
+                assert_eq!(restored.get_infoset(*p).hash, game.get_infoset(*p).hash,
+                    "Restored game has a different infoset for {}\n{}", p, game);
+            }
+
+            assert_eq!(format!("{:?}", restored.get_turn()), format!("{:?}", game.get_turn()),
+                "Restored game has a different turn\n{}", game);
+            assert_eq!(format!("{:?}", restored.get_simultaneous_turn()), format!("{:?}", game.get_simultaneous_turn()),
+                "Restored game has a different simultaneous turn\n{}", game);
+
+            if !take_random_turn(&mut game, &mut rng) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::*;
+    use crate::game::registry::GAMES;
+
+    /// Checks random playouts of whichever game it's given
+    struct RandomPlayouts(usize);
+
+    impl GameVisitor for RandomPlayouts {
+        type Output = ();
+
+        fn visit<G, F>(self, new_game: F)
+            where G: UndoGame + Clone + 'static,
+                  F: Fn() -> G + Clone + Send + Sync + 'static
+        {
+            check_random_playouts(new_game, self.0);
+        }
+    }
+
+    fn check_spec(spec: &str, num_playouts: usize) {
+        build_game(spec, RandomPlayouts(num_playouts))
+            .unwrap_or_else(|err| panic!("Failed to build {}: {}", spec, err));
+    }
+
+    #[test]
+    fn registered_games() {
+        for game in GAMES.iter() {
+            check_spec(game.name, 100);
+        }
+    }
+
+    #[test]
+    fn tictactoe() {
+        //every history of the usual board, counting the empty board
+        assert_eq!(check_exhaustive(vec![TicTacToe::new_mnk(3, 3, 3)]), 549946);
+        check_spec("tictactoe(width=5,height=4,win=4)", 200);
+        check_spec("tictactoe(width=4,height=1,win=2)", 100);
+    }
+
+    #[test]
+    fn phantom_tictactoe() {
+        check_random_playouts(PhantomTicTacToe::new, 2000);
+    }
+
+    #[test]
+    fn one_card_poker() {
+        let deals = OneCardPoker::all_deals(&OneCardPokerRules::default()).into_iter().map(|(game, _)| game).collect();
+        check_exhaustive(deals);
+        check_random_playouts(|| OneCardPoker::with_rules(OneCardPokerRules::default()), 100);
+        let rules = OneCardPokerRules { num_cards: 4, ante: 2, bet_sizes: vec![1, 3], max_raises: 3 };
+        check_exhaustive(OneCardPoker::all_deals(&rules).into_iter().map(|(game, _)| game).collect());
+        check_spec("ocp(cards=20, buckets=5, ante=3, bets=2/4/8, raises=2)", 100);
+    }
+
+    #[test]
+    fn kuhn_poker() {
+        let deals = KuhnPoker::all_deals().into_iter().map(|(game, _)| game).collect();
+        assert_eq!(check_exhaustive(deals), 6 * 9);
+    }
+
+    #[test]
+    fn leduc_poker() {
+        let deals = LeducPoker::all_deals().into_iter().map(|(game, _)| game).collect();
+        check_exhaustive(deals);
+    }
+
+    #[test]
+    fn liars_dice() {
+        let rolls = LiarsDice::all_rolls(1, 3).into_iter().map(|(game, _)| game).collect();
+        check_exhaustive(rolls);
+        check_spec("liars_dice(dice=2,faces=6)", 200);
+    }
+
+    #[test]
+    fn goofspiel() {
+        let orders = vec![vec![1, 2, 3], vec![2, 3, 1], vec![3, 1, 2]];
+        check_exhaustive(orders.into_iter().map(Goofspiel::manual_new).collect());
+        check_spec("goofspiel(cards=6,order=random)", 200);
+    }
+
+    #[test]
+    fn blotto() {
+        check_exhaustive(vec![Blotto::new(4, 3)]);
+        check_exhaustive(vec![Blotto::new(3, 1)]);
+        check_spec("blotto(soldiers=6,battlefields=4)", 50);
+    }
+
+    #[test]
+    fn skulls() {
+        let rules = SkullsRules { num_skulls: 0, num_flowers: 1, ..SkullsRules::default() };
+        check_exhaustive(vec![Skulls::with_rules(rules, 0), Skulls::with_rules(rules, 1)]);
+        //flipping an opponent's skull loses a random card, so this goes through chance
+        let rules = SkullsRules { num_skulls: 1, num_flowers: 1, points_to_win: 1, ..SkullsRules::default() };
+        check_exhaustive(vec![Skulls::with_rules(rules, 0)]);
+        //bigger games take a while to explore in debug builds
+        check_spec("skulls(first=p1,skulls=1,flowers=1)", 1000);
+        check_spec("skulls(first=p2,skulls=1,flowers=2)", 200);
+        check_spec("skulls(first=p1,skulls=2,flowers=2)", 200);
+        check_spec("skulls(points=3,own_skull=random,rng=off)", 100);
+        check_spec("skulls(own_skull=random,opponent_skull=flowers)", 100);
+        check_spec("skulls(first=p2,skulls=1,flowers=2,bidding=simultaneous,own_skull=random)", 100);
+
+        //P2's seats each only know their own cards, so P2 doesn't have perfect recall
+        let rules = SkullsRules { num_players: 3, num_skulls: 1, num_flowers: 1, ..SkullsRules::default() };
+        check_abstraction(|| Skulls::with_rules(rules, 1), 100);
+        let rules = SkullsRules { num_players: 4, num_flowers: 2, simultaneous_bidding: true, ..SkullsRules::default() };
+        check_abstraction(|| Skulls::with_rules(rules, 2), 100);
+        let rules = SkullsRules { num_players: 5, num_flowers: 2, ..SkullsRules::default() };
+        check_abstraction(|| Abstracted::new(Skulls::with_rules(rules, 3), RoundSummary), 100);
+    }
+
+    #[test]
+    fn tensors() {
+        check_tensors(TicTacToe::new, 200);
+        check_tensors(|| TicTacToe::new_mnk(4, 5, 3), 100);
+        check_tensors(PhantomTicTacToe::new, 200);
+        check_tensors(|| OneCardPoker::with_rules(OneCardPokerRules::default()), 200);
+        check_tensors(|| OneCardPoker::with_rules(OneCardPokerRules { num_cards: 6, ante: 1, bet_sizes: vec![1, 2], max_raises: 3 }), 200);
+        check_tensors(KuhnPoker::new, 100);
+        check_tensors(LeducPoker::new, 200);
+        check_tensors(|| LiarsDice::new(2, 6), 200);
+        check_tensors(|| Goofspiel::new(5, false), 100);
+        check_tensors(|| Skulls::with_rules(SkullsRules::default(), 0), 200);
+        check_tensors(|| Skulls::manual_new(Player::P2, 2, 2), 200);
+        check_tensors(|| Skulls::with_rules(SkullsRules { num_players: 4, num_flowers: 2, ..SkullsRules::default() }, 3), 200);
+    }
+
+    #[test]
+    fn records() {
+        check_records(TicTacToe::new, 100);
+        check_records(|| TicTacToe::new_mnk(5, 4, 4), 50);
+        check_records(PhantomTicTacToe::new, 100);
+        check_records(|| OneCardPoker::with_rules(OneCardPokerRules::default()), 100);
+        check_records(|| OneCardPoker::with_rules(OneCardPokerRules { num_cards: 7, ante: 2, bet_sizes: vec![2, 5], max_raises: 2 }), 100);
+        check_records(KuhnPoker::new, 50);
+        check_records(LeducPoker::new, 100);
+        check_records(|| LiarsDice::new(2, 6), 100);
+        check_records(|| Goofspiel::new(5, false), 50);
+        check_records(|| Skulls::with_rules(SkullsRules::default(), 0), 100);
+        check_records(|| Skulls::manual_new(Player::P2, 2, 1), 100);
+        check_records(|| Skulls::with_rules(SkullsRules { num_players: 3, num_flowers: 1, simultaneous_bidding: true, ..SkullsRules::default() }, 1), 100);
+        check_records(MatrixGame::new_rock_paper_scissors, 10);
+        check_records(|| MatrixGame::new(2, vec![1.0, 0.9, -0.7, 1.0]), 10);
+        check_records(|| MatrixGame::new_rectangular((3, 2), vec![1.0, 0.0, -1.0, 2.0, 0.5, -3.0]), 10);
+        check_records(|| Blotto::new(5, 3), 50);
+        check_records(DoubleMatrixGame::new_rock_paper_scissors, 10);
+        check_records(|| DoubleMatrixGame::new(2, vec![1.0, 0.9, -0.7, 1.0]), 10);
+    }
+
+    #[test]
+    fn abstractions() {
+        //card buckets keep the betting history, so they keep perfect recall
+        let deals = OneCardPoker::all_deals(&OneCardPokerRules::default()).into_iter()
+            .map(|(game, _)| Abstracted::new(game, CardBuckets { num_buckets: 4 }))
+            .collect();
+        check_exhaustive(deals);
+        check_abstraction(|| Abstracted::new(Skulls::with_rules(SkullsRules::default(), 0), RoundSummary), 200);
+        check_random_playouts(|| ActionAbstracted::new(Skulls::with_rules(SkullsRules::default(), 0), BidSizes { amounts: vec![2, 4] }), 200);
+    }
+
+    #[test]
+    fn matches() {
+        let config = MatchConfig { rounds: 2, ..MatchConfig::default() };
+        check_exhaustive(vec![Match::new(MatrixGame::new_rock_paper_scissors, config)]);
+        let config = MatchConfig { rounds: 10, stack: Some(4.0), ..MatchConfig::default() };
+        check_random_playouts(|| Match::new(|| OneCardPoker::with_rules(OneCardPokerRules::default()), config.clone()), 100);
+        check_spec("match(skulls(first=p1,skulls=1,flowers=1), stop_when_decided=true)", 100);
+    }
+
+    #[test]
+    fn matrix_games() {
+        check_exhaustive(vec![MatrixGame::new_rock_paper_scissors()]);
+        check_exhaustive(vec![MatrixGame::new(2, vec![1.0, 0.9, -0.7, 1.0])]);
+        check_exhaustive(vec![MatrixGame::new_rectangular((1, 4), vec![1.0, 0.0, -1.0, 2.0])]);
+        check_exhaustive(vec![DoubleMatrixGame::new_rock_paper_scissors()]);
+        check_exhaustive(vec![DoubleMatrixGame::new(2, vec![1.0, 0.9, -0.7, 1.0])]);
+    }
+}
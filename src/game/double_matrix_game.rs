@@ -1,7 +1,8 @@
 use std::fmt::{Display, Formatter};
 
 use crate::game;
-use crate::game::{Player};
+use crate::game::Game;
+use crate::game::{Player, IllegalMove, GameRecord, RecordGame, RecordError};
 use crate::game::matrix_game::{Move, MatrixGame};
 
 #[derive(Debug, Clone)]
@@ -37,6 +38,18 @@ impl DoubleMatrixGame {
         }
     }
 
+    fn in_first_game(&self) -> bool {
+        self.games.0.get_reward().is_none()
+    }
+
+    fn current_game(&self) -> &MatrixGame {
+        if self.in_first_game() {
+            &self.games.0
+        } else {
+            &self.games.1
+        }
+    }
+
     pub fn new_rock_paper_scissors() -> DoubleMatrixGame {
         DoubleMatrixGame {
             games: (
@@ -52,22 +65,45 @@ impl game::Game for DoubleMatrixGame {
     type Action = Move;
 
     fn get_turn(&self) -> (Player, Vec<Move>) {
-        if self.games.0.get_reward().is_none() {
-            self.games.0.get_turn()
+        self.current_game().get_turn()
+    }
+
+    fn get_simultaneous_turn(&self) -> Option<(Vec<Move>, Vec<Move>)> {
+        self.current_game().get_simultaneous_turn()
+    }
+
+    fn take_turn(&mut self, player: Player, action: &Move) -> Result<(), IllegalMove<Move>> {
+        //matrix games only have simultaneous turns, so this is always an error
+        if self.in_first_game() {
+            self.games.0.take_turn(player, action)
         } else {
-            self.games.1.get_turn()
+            self.games.1.take_turn(player, action)
         }
     }
 
-    fn take_turn(&mut self, player: Player, action: &Move) {
-        if self.games.0.get_reward().is_none() {
-            self.games.0.take_turn(player, action);
-            *player.lens_mut(&mut self.game1_moves) = Some(*action);
+    fn take_simultaneous_turn(&mut self, actions: (&Move, &Move)) -> Result<(), IllegalMove<Move>> {
+        if self.in_first_game() {
+            self.games.0.take_simultaneous_turn(actions)?;
+            self.game1_moves = (Some(*actions.0), Some(*actions.1));
+            Ok(())
         } else {
-            self.games.1.take_turn(player, action);
+            self.games.1.take_simultaneous_turn(actions)
         }
     }
 
+    //both games have the same moves
+    fn num_action_ids(&self) -> usize {
+        self.games.0.num_action_ids()
+    }
+
+    fn action_id(&self, action: &Move) -> usize {
+        self.games.0.action_id(action)
+    }
+
+    fn parse_action(&self, action: &str) -> Option<Move> {
+        self.games.0.parse_action(action)
+    }
+
     fn get_reward(&self) -> Option<f32> {
         match (self.games.0.get_reward(), self.games.1.get_reward()) {
             (Some(r1), Some(r2)) => Some((r1 + r2) / 2.0),
@@ -75,10 +111,74 @@ impl game::Game for DoubleMatrixGame {
         }
     }
 
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        let (bounds1, bounds2) = (self.games.0.get_utility_bounds(), self.games.1.get_utility_bounds());
+        ((bounds1.0 + bounds2.0) / 2.0, (bounds1.1 + bounds2.1) / 2.0)
+    }
+
     fn get_infoset(&self, player: Player) -> game::Infoset {
         //only state is whether we've made the first move and what that first move was
         game::Infoset::new(*player.lens(&self.game1_moves))
     }
+
+    /// Nothing is public, since neither player sees the other's moves
+    fn get_private_observation(&self, player: Player) -> game::Observation {
+        game::Observation::new(*player.lens(&self.game1_moves))
+    }
+}
+
+impl RecordGame for DoubleMatrixGame {
+    fn get_record(&self) -> GameRecord {
+        let mut record = GameRecord::new("double_matrix");
+        record.add_setup("moves", self.games.0.num_action_ids());
+        record.add_setup("matrix1", self.games.0.matrix_string());
+        record.add_setup("matrix2", self.games.1.matrix_string());
+        record.turns = [self.games.0.get_record().turns, self.games.1.get_record().turns].concat();
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<DoubleMatrixGame, RecordError> {
+        record.check_game("double_matrix")?;
+        let num_moves = record.get_setup("moves")?;
+        let parse_matrix = |key| {
+            let matrix: String = record.get_setup(key)?;
+            MatrixGame::from_matrix_string((num_moves, num_moves), &matrix)
+                .ok_or(RecordError::BadSetup { key, value: matrix })
+        };
+
+        let mut game = DoubleMatrixGame {
+            games: (parse_matrix("matrix1")?, parse_matrix("matrix2")?),
+            game1_moves: (None, None),
+        };
+        record.replay(&mut game)?;
+        Ok(game)
+    }
+}
+
+impl game::UndoGame for DoubleMatrixGame {
+    /// Whether the turn was in the first game
+    type Undo = bool;
+
+    fn apply_turn(&mut self, player: Player, action: &Move) -> Result<bool, IllegalMove<Move>> {
+        let in_first_game = self.in_first_game();
+        game::Game::take_turn(self, player, action)?;
+        Ok(in_first_game)
+    }
+
+    fn apply_simultaneous_turn(&mut self, actions: (&Move, &Move)) -> Result<bool, IllegalMove<Move>> {
+        let in_first_game = self.in_first_game();
+        game::Game::take_simultaneous_turn(self, actions)?;
+        Ok(in_first_game)
+    }
+
+    fn undo_turn(&mut self, in_first_game: bool) {
+        if in_first_game {
+            self.games.0.undo_turn(());
+            self.game1_moves = (None, None);
+        } else {
+            self.games.1.undo_turn(());
+        }
+    }
 }
 
 impl Display for DoubleMatrixGame {
@@ -1,5 +1,7 @@
+use std::error;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 use fasthash::{MetroHasher};
 
 #[derive(PartialEq, Copy, Clone, Debug, Hash)]
@@ -36,6 +38,14 @@ impl Player {
         }
     }
 
+    /// Makes a 2-tuple with this player's value in their place and the other player's in theirs
+    pub fn pair<T>(self, own: T, other: T) -> (T, T) {
+        match self {
+            Player::P1 => (own, other),
+            Player::P2 => (other, own),
+        }
+    }
+
     /// Returns P1 if the given player matches the current player, P2 otherwise
     /// 
     /// This is for when you want a player to see themself as P1
@@ -58,50 +68,295 @@ impl fmt::Display for Player {
     }
 }
 
+impl FromStr for Player {
+    type Err = String;
+
+    /// Parses P1 or P2, ignoring case
+    fn from_str(s: &str) -> Result<Player, String> {
+        match s.to_lowercase().as_str() {
+            "p1" => Ok(Player::P1),
+            "p2" => Ok(Player::P2),
+            _ => Err(format!("'{}' isn't a player", s)),
+        }
+    }
+}
+
 pub struct Infoset {
     pub hash: u64,
 }
 
 impl Infoset {
     pub fn new<T: Hash>(infoset: T) -> Infoset {
-        let mut hasher: MetroHasher = Default::default();
-        infoset.hash(&mut hasher);
-        let hash = hasher.finish();
-
         Infoset {
-            hash,
+            hash: metro_hash(infoset),
+        }
+    }
+}
+
+/// Something that happened in the game, as seen by one or both players
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub struct Observation {
+    pub hash: u64,
+}
+
+impl Observation {
+    pub fn new<T: Hash>(observation: T) -> Observation {
+        Observation {
+            hash: metro_hash(observation),
+        }
+    }
+}
+
+/// Hash of everything both players have observed
+///
+/// Every history in a public state has the same public observations,
+/// so infosets can be grouped by their public state
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub struct PublicState {
+    pub hash: u64,
+}
+
+impl PublicState {
+    pub fn new<T: Hash>(public_state: T) -> PublicState {
+        PublicState {
+            hash: metro_hash(public_state),
+        }
+    }
+}
+
+fn metro_hash<T: Hash>(value: T) -> u64 {
+    let mut hasher: MetroHasher = Default::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Why a turn couldn't be taken
+#[derive(PartialEq, Clone, Debug)]
+pub enum IllegalMoveReason {
+    /// The game is already over
+    GameOver,
+    /// It's another player's turn
+    WrongPlayer { expected: Player },
+    /// The action isn't allowed in the current state, with an explanation
+    InvalidAction(&'static str),
+}
+
+impl fmt::Display for IllegalMoveReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IllegalMoveReason::GameOver => write!(f, "the game is over"),
+            IllegalMoveReason::WrongPlayer { expected } => write!(f, "it is {}'s turn", expected),
+            IllegalMoveReason::InvalidAction(reason) => write!(f, "{}", reason),
         }
     }
 }
 
+/// A rejected turn, with who tried to move, what they tried, and why it wasn't allowed
+#[derive(PartialEq, Clone, Debug)]
+pub struct IllegalMove<A> {
+    pub player: Player,
+    pub action: A,
+    pub reason: IllegalMoveReason,
+}
+
+impl<A: Clone> IllegalMove<A> {
+    pub fn new(player: Player, action: &A, reason: IllegalMoveReason) -> IllegalMove<A> {
+        IllegalMove {
+            player,
+            action: action.clone(),
+            reason,
+        }
+    }
+}
+
+impl<A: fmt::Display> fmt::Display for IllegalMove<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} can't take action {}: {}", self.player, self.action, self.reason)
+    }
+}
+
+impl<A: fmt::Display + fmt::Debug> error::Error for IllegalMove<A> {}
+
+/// Each player's legal actions at a simultaneous turn, as (P1's actions, P2's actions)
+pub type SimultaneousActions<A> = (Vec<A>, Vec<A>);
+
+const NO_SIMULTANEOUS_TURNS: IllegalMoveReason = IllegalMoveReason::InvalidAction("there are no simultaneous turns in this game");
+
 /// 2 player zero sum game
 ///
 /// Game is over when get_reward returns Some(reward) for player 1
 pub trait Game: fmt::Display {
 
-    type Action: fmt::Display + fmt::Debug;
+    type Action: fmt::Display + fmt::Debug + Clone;
 
     /// Returns player to move and all legal actions
+    ///
+    /// At simultaneous turns this returns P1 and their actions, but the turn can only be taken with `take_simultaneous_turn`
     fn get_turn(&self) -> (Player, Vec<Self::Action>);
 
+    /// Returns each player's legal actions if both players move at once, or None for a normal turn
+    ///
+    /// Algorithms that need players to alternate can wrap the game in `Sequential`.
+    /// By default players always alternate
+    fn get_simultaneous_turn(&self) -> Option<SimultaneousActions<Self::Action>> {
+        None
+    }
+
+    /// Both players take their action at once, without seeing the other's
+    ///
+    /// The actions are given as (P1's action, P2's action)
+    fn take_simultaneous_turn(&mut self, actions: (&Self::Action, &Self::Action)) -> Result<(), IllegalMove<Self::Action>> {
+        Err(IllegalMove::new(Player::P1, actions.0, NO_SIMULTANEOUS_TURNS))
+    }
+
+    /// Same as `take_turn_outcomes`, but for simultaneous turns
+    fn take_simultaneous_turn_outcomes(&self, actions: (&Self::Action, &Self::Action)) -> Result<Vec<(Self, f32)>, IllegalMove<Self::Action>>
+        where Self: Sized + Clone
+    {
+        let mut game = self.clone();
+        game.take_simultaneous_turn(actions)?;
+        Ok(vec![(game, 1.0)])
+    }
+
     /// The given player does the given action for their turn
-    /// # Panics
-    /// This may panic if the player cannot move or the action is invalid
-    fn take_turn(&mut self, player: Player, action: &Self::Action);
+    ///
+    /// If the player cannot move or the action is invalid, the game is left unchanged
+    /// and the reason is returned
+    fn take_turn(&mut self, player: Player, action: &Self::Action) -> Result<(), IllegalMove<Self::Action>>;
+
+    /// Takes the turn once for each way chance could play out, returning each resulting game and its probability
+    ///
+    /// Games that sample chance inside of `take_turn` should override this so the whole tree can be enumerated.
+    /// By default there is no chance, so this just takes the turn
+    fn take_turn_outcomes(&self, player: Player, action: &Self::Action) -> Result<Vec<(Self, f32)>, IllegalMove<Self::Action>>
+        where Self: Sized + Clone
+    {
+        let mut game = self.clone();
+        game.take_turn(player, action)?;
+        Ok(vec![(game, 1.0)])
+    }
+
+    /// Number of action ids in this game, so every action id is less than this
+    fn num_action_ids(&self) -> usize;
+
+    /// Stable id for an action
+    ///
+    /// Unlike an action's position in `get_turn`, this doesn't depend on the order actions are listed in,
+    /// so it's what stored regrets and strategies refer to actions by
+    fn action_id(&self, action: &Self::Action) -> usize;
+
+    /// Parses an action from the string it's displayed as
+    fn parse_action(&self, action: &str) -> Option<Self::Action>;
+
+    /// Returns the id of each of the given actions
+    fn action_ids(&self, actions: &[Self::Action]) -> Vec<usize> {
+        actions.iter().map(|action| self.action_id(action)).collect()
+    }
 
     /// Returns None if the game is not over
     /// 
     /// Otherwise returns the reward for Player 1
     fn get_reward(&self) -> Option<f32>;
 
+    /// Lowest and highest reward Player 1 can get, so solvers can scale rewards
+    ///
+    /// By default rewards are between -1 and 1
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        (-1.0, 1.0)
+    }
+
     /// Returns a player's infoset as a vector of hashes
     /// 
     /// Earlier parts of the infoset should come first, so an early infoset
     /// is a prefix of a later infoset
     fn get_infoset(&self, player: Player) -> Infoset;
 
+    /// Returns one infoset shared by every infoset that's the same up to a symmetry, like a rotated board
+    ///
+    /// Also returns the canonical id of each action id, or None if the ids don't change,
+    /// so strategies stored for the canonical infoset can be mapped back onto this one.
+    /// By default every infoset is its own canonical infoset
+    fn get_canonical_infoset(&self, player: Player) -> (Infoset, Option<Vec<usize>>) {
+        (self.get_infoset(player), None)
+    }
+
+    /// Returns the player's canonical infoset and the canonical id of each of the given actions
+    fn get_canonical_action_ids(&self, player: Player, actions: &[Self::Action]) -> (Infoset, Vec<usize>) {
+        let (infoset, permutation) = self.get_canonical_infoset(player);
+        let action_ids = match permutation {
+            Some(permutation) => actions.iter().map(|action| permutation[self.action_id(action)]).collect(),
+            None => self.action_ids(actions),
+        };
+        (infoset, action_ids)
+    }
+
+    /// Returns what both players have observed, oldest first
+    ///
+    /// By default nothing is public
+    fn get_public_observations(&self) -> Vec<Observation> {
+        vec![]
+    }
+
+    /// Returns what only the given player has observed
+    ///
+    /// Together with the public observations, this should determine the player's infoset.
+    /// By default the whole infoset is private
+    fn get_private_observation(&self, player: Player) -> Observation {
+        Observation {
+            hash: self.get_infoset(player).hash,
+        }
+    }
+
+    /// Returns the hash of the public observations
+    fn get_public_state(&self) -> PublicState {
+        PublicState::new(self.get_public_observations())
+    }
+
     /// Returns a human-readable summary of the game for the given player
     fn get_summary_string(&self, _player: Player) -> String {
         String::from("Player summary not available")
     }
-}
\ No newline at end of file
+}
+
+/// Game that can undo turns in place
+///
+/// Solvers use this to avoid cloning the whole game for every action they explore
+pub trait UndoGame: Game {
+
+    /// Whatever is needed to restore the game to how it was before a turn
+    type Undo;
+
+    /// Same as `take_turn`, but returns a token that undoes the turn
+    fn apply_turn(&mut self, player: Player, action: &Self::Action) -> Result<Self::Undo, IllegalMove<Self::Action>>;
+
+    /// Same as `take_simultaneous_turn`, but returns a token that undoes the turn
+    fn apply_simultaneous_turn(&mut self, actions: (&Self::Action, &Self::Action)) -> Result<Self::Undo, IllegalMove<Self::Action>> {
+        Err(IllegalMove::new(Player::P1, actions.0, NO_SIMULTANEOUS_TURNS))
+    }
+
+    /// Restores the game to how it was before the turn that returned the token
+    ///
+    /// Tokens must be undone in the reverse order that they were returned
+    fn undo_turn(&mut self, undo: Self::Undo);
+}
+
+/// Game that can describe what a player knows as a fixed-size vector, for function approximation
+//nothing trains on tensors until there's a function approximation solver, so only the conformance tests use this for now
+#[allow(dead_code)]
+pub trait TensorGame: Game {
+
+    /// Length of every infoset tensor for this game
+    fn tensor_size(&self) -> usize;
+
+    /// Encodes the given player's infoset as `tensor_size()` features
+    fn get_infoset_tensor(&self, player: Player) -> Vec<f32>;
+
+    /// Returns whether each action id is legal for the player to move
+    fn get_legal_action_mask(&self) -> Vec<bool> {
+        let mut mask = vec![false; self.num_action_ids()];
+        for action in self.get_turn().1.iter() {
+            mask[self.action_id(action)] = true;
+        }
+        mask
+    }
+}
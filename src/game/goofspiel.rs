@@ -0,0 +1,309 @@
+//https://en.wikipedia.org/wiki/Goofspiel
+
+use rand::seq::SliceRandom;
+use std::fmt::{Display, Formatter};
+
+use crate::game::{Game, UndoGame, TensorGame, IllegalMove, IllegalMoveReason, Player, Infoset, Observation};
+use crate::game::{GameRecord, RecordedTurn, RecordGame, RecordError};
+
+/// A card worth its number, from 1 up to the number of cards
+pub type Card = u8;
+
+/// Goofspiel, where both players bid on each prize at once
+///
+/// Each player has a hand of cards from 1 to N, and the prizes are another set of cards from 1 to N.
+/// The higher bid wins the prize's value in points, and on a tie the prize is thrown away.
+/// Every bid and prize is public, and whoever has more points at the end wins
+#[derive(Clone, Debug)]
+pub struct Goofspiel {
+    /// Prizes in the order they're revealed
+    prizes: Vec<Card>,
+    /// Cards each player has left to bid, in ascending order
+    hands: (Vec<Card>, Vec<Card>),
+    /// Both players' bids for each prize so far
+    bids: Vec<(Card, Card)>,
+    points: (u32, u32),
+}
+
+impl Goofspiel {
+    /// Prizes go up from 1 if `ascending`, otherwise they're shuffled
+    pub fn new(num_cards: u8, ascending: bool) -> Goofspiel {
+        let mut prizes: Vec<Card> = (1..=num_cards).collect();
+        if !ascending {
+            prizes.shuffle(&mut rand::thread_rng());
+        }
+        Goofspiel::manual_new(prizes)
+    }
+
+    /// Prizes are revealed in the given order, which has to use every card from 1 to N once
+    pub fn manual_new(prizes: Vec<Card>) -> Goofspiel {
+        let hand: Vec<Card> = (1..=prizes.len() as Card).collect();
+        Goofspiel {
+            prizes,
+            hands: (hand.clone(), hand),
+            bids: vec![],
+            points: (0, 0),
+        }
+    }
+
+    fn num_cards(&self) -> usize {
+        self.prizes.len()
+    }
+
+    fn is_over(&self) -> bool {
+        self.bids.len() == self.num_cards()
+    }
+
+    /// Points each player gets from a round
+    fn round_points(prize: Card, bids: (Card, Card)) -> (u32, u32) {
+        if bids.0 > bids.1 {
+            (prize as u32, 0)
+        } else if bids.1 > bids.0 {
+            (0, prize as u32)
+        } else {
+            (0, 0)
+        }
+    }
+}
+
+impl Game for Goofspiel {
+    type Action = Card;
+
+    fn get_turn(&self) -> (Player, Vec<Card>) {
+        match self.get_simultaneous_turn() {
+            Some((cards, _)) => (Player::P1, cards),
+            None => (Player::P1, vec![]),
+        }
+    }
+
+    fn get_simultaneous_turn(&self) -> Option<(Vec<Card>, Vec<Card>)> {
+        if self.is_over() {
+            None
+        } else {
+            Some(self.hands.clone())
+        }
+    }
+
+    fn take_turn(&mut self, player: Player, action: &Card) -> Result<(), IllegalMove<Card>> {
+        let reason = if self.is_over() {
+            IllegalMoveReason::GameOver
+        } else {
+            IllegalMoveReason::InvalidAction("both players bid at once")
+        };
+        Err(IllegalMove::new(player, action, reason))
+    }
+
+    fn take_simultaneous_turn(&mut self, actions: (&Card, &Card)) -> Result<(), IllegalMove<Card>> {
+        if self.is_over() {
+            return Err(IllegalMove::new(Player::P1, actions.0, IllegalMoveReason::GameOver));
+        }
+        for player in [Player::P1, Player::P2].iter() {
+            let card = *player.lens(&actions);
+            if !player.lens(&self.hands).contains(card) {
+                return Err(IllegalMove::new(*player, card, IllegalMoveReason::InvalidAction("card isn't in hand")));
+            }
+        }
+
+        let bids = (*actions.0, *actions.1);
+        for player in [Player::P1, Player::P2].iter() {
+            let card = *player.lens(&bids);
+            player.lens_mut(&mut self.hands).retain(|c| *c != card);
+        }
+        let points = Goofspiel::round_points(self.prizes[self.bids.len()], bids);
+        self.points = (self.points.0 + points.0, self.points.1 + points.1);
+        self.bids.push(bids);
+        Ok(())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        self.num_cards()
+    }
+
+    fn action_id(&self, action: &Card) -> usize {
+        *action as usize - 1
+    }
+
+    fn parse_action(&self, action: &str) -> Option<Card> {
+        action.parse().ok().filter(|&card| 1 <= card && card as usize <= self.num_cards())
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        if !self.is_over() {
+            return None;
+        }
+        if self.points.0 > self.points.1 {
+            Some(1.0)
+        } else if self.points.1 > self.points.0 {
+            Some(-1.0)
+        } else {
+            Some(0.0)
+        }
+    }
+
+    fn get_infoset(&self, _player: Player) -> Infoset {
+        //every bid and revealed prize is public, and the current prize is already showing
+        let revealed = (self.bids.len() + 1).min(self.num_cards());
+        Infoset::new((self.prizes[..revealed].to_vec(), self.bids.clone()))
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        let mut observations = vec![];
+        for (prize, bids) in self.prizes.iter().zip(self.bids.iter()) {
+            observations.push(Observation::new(prize));
+            observations.push(Observation::new(bids));
+        }
+        if let Some(prize) = self.prizes.get(self.bids.len()) {
+            observations.push(Observation::new(prize));
+        }
+        observations
+    }
+
+    fn get_private_observation(&self, _player: Player) -> Observation {
+        //nothing is private
+        Observation::new(0)
+    }
+}
+
+impl UndoGame for Goofspiel {
+    //the bids already have everything we need
+    type Undo = ();
+
+    fn apply_turn(&mut self, player: Player, action: &Card) -> Result<(), IllegalMove<Card>> {
+        self.take_turn(player, action)
+    }
+
+    fn apply_simultaneous_turn(&mut self, actions: (&Card, &Card)) -> Result<(), IllegalMove<Card>> {
+        self.take_simultaneous_turn(actions)
+    }
+
+    fn undo_turn(&mut self, _undo: ()) {
+        let bids = self.bids.pop().expect("Tried to undo with no bids");
+        for player in [Player::P1, Player::P2].iter() {
+            let card = *player.lens(&bids);
+            let hand = player.lens_mut(&mut self.hands);
+            let index = hand.iter().position(|c| *c > card).unwrap_or(hand.len());
+            hand.insert(index, card);
+        }
+        let points = Goofspiel::round_points(self.prizes[self.bids.len()], bids);
+        self.points = (self.points.0 - points.0, self.points.1 - points.1);
+    }
+}
+
+impl TensorGame for Goofspiel {
+    fn tensor_size(&self) -> usize {
+        //one-hot current prize, then the cards left in each player's hand and each player's points
+        3 * self.num_cards() + 2
+    }
+
+    fn get_infoset_tensor(&self, player: Player) -> Vec<f32> {
+        let num_cards = self.num_cards();
+        let mut tensor = vec![0.0; self.tensor_size()];
+        if let Some(prize) = self.prizes.get(self.bids.len()) {
+            tensor[*prize as usize - 1] = 1.0;
+        }
+        let (own, other) = (player.lens(&self.hands), player.other().lens(&self.hands));
+        for card in own.iter() {
+            tensor[num_cards + *card as usize - 1] = 1.0;
+        }
+        for card in other.iter() {
+            tensor[2 * num_cards + *card as usize - 1] = 1.0;
+        }
+        tensor[3 * num_cards] = *player.lens(&self.points) as f32;
+        tensor[3 * num_cards + 1] = *player.other().lens(&self.points) as f32;
+        tensor
+    }
+}
+
+impl RecordGame for Goofspiel {
+    fn get_record(&self) -> GameRecord {
+        let mut record = GameRecord::new("goofspiel");
+        record.add_setup("prizes", self.prizes.iter().map(|prize| prize.to_string()).collect::<Vec<String>>().join("/"));
+        for (bid1, bid2) in self.bids.iter() {
+            record.turns.push(RecordedTurn::new(Player::P1, bid1));
+            record.turns.push(RecordedTurn::new(Player::P2, bid2));
+        }
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<Goofspiel, RecordError> {
+        record.check_game("goofspiel")?;
+        let prizes_string: String = record.get_setup("prizes")?;
+        let bad_prizes = || RecordError::BadSetup { key: "prizes", value: prizes_string.clone() };
+        let prizes: Vec<Card> = prizes_string.split('/')
+            .map(|prize| prize.parse().ok())
+            .collect::<Option<_>>()
+            .ok_or_else(bad_prizes)?;
+        let mut sorted = prizes.clone();
+        sorted.sort();
+        if sorted != (1..=prizes.len() as Card).collect::<Vec<Card>>() {
+            return Err(bad_prizes());
+        }
+
+        let mut game = Goofspiel::manual_new(prizes);
+        record.replay(&mut game)?;
+        Ok(game)
+    }
+}
+
+impl Display for Goofspiel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (prize, (bid1, bid2)) in self.prizes.iter().zip(self.bids.iter()) {
+            writeln!(f, "Prize {}: P1 bid {}, P2 bid {}", prize, bid1, bid2)?;
+        }
+        if let Some(prize) = self.prizes.get(self.bids.len()) {
+            writeln!(f, "Prize {} is up", prize)?;
+        }
+        writeln!(f, "Points: {} to {}", self.points.0, self.points.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_rounds() {
+        let mut game = Goofspiel::manual_new(vec![2, 3, 1]);
+        assert_eq!(game.get_simultaneous_turn(), Some((vec![1, 2, 3], vec![1, 2, 3])));
+        assert!(game.take_turn(Player::P1, &1).is_err());
+
+        game.take_simultaneous_turn((&3, &1)).unwrap();
+        assert_eq!(game.points, (2, 0));
+        assert!(game.take_simultaneous_turn((&3, &2)).is_err());
+
+        //ties throw the prize away
+        game.take_simultaneous_turn((&2, &2)).unwrap();
+        assert_eq!(game.points, (2, 0));
+        game.take_simultaneous_turn((&1, &3)).unwrap();
+        assert_eq!(game.points, (2, 1));
+        assert_eq!(game.get_reward(), Some(1.0));
+        assert_eq!(game.get_simultaneous_turn(), None);
+    }
+
+    #[test]
+    fn undoes_rounds() {
+        let mut game = Goofspiel::new(4, false);
+        let start = format!("{:?}", game);
+        game.apply_simultaneous_turn((&2, &4)).unwrap();
+        let middle = format!("{:?}", game);
+        game.apply_simultaneous_turn((&4, &1)).unwrap();
+
+        game.undo_turn(());
+        assert_eq!(format!("{:?}", game), middle);
+        game.undo_turn(());
+        assert_eq!(format!("{:?}", game), start);
+    }
+
+    #[test]
+    fn reveals_prizes() {
+        let game1 = Goofspiel::manual_new(vec![1, 2, 3]);
+        let game2 = Goofspiel::manual_new(vec![1, 3, 2]);
+        assert_eq!(game1.get_infoset(Player::P1).hash, game2.get_infoset(Player::P1).hash);
+
+        let mut game1 = game1;
+        let mut game2 = game2;
+        game1.take_simultaneous_turn((&1, &1)).unwrap();
+        game2.take_simultaneous_turn((&1, &1)).unwrap();
+        assert_ne!(game1.get_infoset(Player::P1).hash, game2.get_infoset(Player::P1).hash);
+    }
+}
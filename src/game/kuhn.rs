@@ -0,0 +1,312 @@
+//https://en.wikipedia.org/wiki/Kuhn_poker
+
+use rand::seq::SliceRandom;
+use std::fmt::{Display, Formatter};
+
+use crate::game::{Game, UndoGame, TensorGame, IllegalMove, IllegalMoveReason, Player, Infoset, Observation};
+use crate::game::{GameRecord, RecordedTurn, RecordGame, RecordError};
+
+/// Jack, queen and king
+const NUM_CARDS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub enum Action {
+    Fold,
+    /// Checks when there's no bet to call
+    Call,
+    Bet,
+}
+
+/// Stable id of each action
+fn action_id(action: Action) -> usize {
+    match action {
+        Action::Fold => 0,
+        Action::Call => 1,
+        Action::Bet => 2,
+    }
+}
+
+/// Kuhn poker, where P1 always acts first
+///
+/// Each player antes 1 and there's a single bet of 1, so P1's equilibrium value is -1/18
+#[derive(Clone, Debug)]
+pub struct KuhnPoker {
+    hands: (u32, u32),
+    history: Vec<Action>,
+}
+
+impl KuhnPoker {
+    /// Deals two cards from a shuffled deck
+    pub fn new() -> KuhnPoker {
+        let mut deck: Vec<u32> = (0..NUM_CARDS).collect();
+        deck.shuffle(&mut rand::thread_rng());
+        KuhnPoker::manual_new((deck[0], deck[1]))
+    }
+
+    /// Every possible deal, each with its probability
+    pub fn all_deals() -> Vec<(KuhnPoker, f32)> {
+        let num_deals = NUM_CARDS * (NUM_CARDS - 1);
+        let mut deals = vec![];
+        for hand1 in 0..NUM_CARDS {
+            for hand2 in (0..NUM_CARDS).filter(|&hand2| hand2 != hand1) {
+                deals.push((KuhnPoker::manual_new((hand1, hand2)), 1.0 / num_deals as f32));
+            }
+        }
+        deals
+    }
+
+    pub fn manual_new(hands: (u32, u32)) -> KuhnPoker {
+        KuhnPoker {
+            hands,
+            history: vec![],
+        }
+    }
+
+    fn current_player(&self) -> Player {
+        player_at(self.history.len())
+    }
+
+    fn current_actions(&self) -> Vec<Action> {
+        match self.history.as_slice() {
+            [] | [Action::Call] => vec![Action::Call, Action::Bet],
+            [Action::Bet] | [Action::Call, Action::Bet] => vec![Action::Fold, Action::Call],
+            _ => vec![],
+        }
+    }
+
+    /// Chips each player has put in the pot
+    fn get_pot(&self) -> (u32, u32) {
+        let mut pot = (1, 1);
+        let mut bet = false;
+        for (i, action) in self.history.iter().enumerate() {
+            let player = player_at(i);
+            match action {
+                Action::Bet => {
+                    *player.lens_mut(&mut pot) += 1;
+                    bet = true;
+                }
+                Action::Call if bet => *player.lens_mut(&mut pot) += 1,
+                _ => (),
+            }
+        }
+        pot
+    }
+}
+
+impl Game for KuhnPoker {
+    type Action = Action;
+
+    fn get_turn(&self) -> (Player, Vec<Action>) {
+        (self.current_player(), self.current_actions())
+    }
+
+    fn take_turn(&mut self, player: Player, action: &Action) -> Result<(), IllegalMove<Action>> {
+        let actions = self.current_actions();
+        let reason = if actions.is_empty() {
+            Some(IllegalMoveReason::GameOver)
+        } else if player != self.current_player() {
+            Some(IllegalMoveReason::WrongPlayer { expected: self.current_player() })
+        } else if !actions.contains(action) {
+            Some(IllegalMoveReason::InvalidAction("action is not allowed at this point in the betting"))
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(IllegalMove::new(player, action, reason));
+        }
+
+        self.history.push(*action);
+        Ok(())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        3
+    }
+
+    fn action_id(&self, action: &Action) -> usize {
+        action_id(*action)
+    }
+
+    fn parse_action(&self, action: &str) -> Option<Action> {
+        match action {
+            "Fold" => Some(Action::Fold),
+            "Call" => Some(Action::Call),
+            "Bet" => Some(Action::Bet),
+            _ => None,
+        }
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        if !self.current_actions().is_empty() {
+            return None;
+        }
+        //the winner gets the other player's contribution to the pot
+        let pot = self.get_pot();
+        let winner = match self.history.last() {
+            //the player who folded was the last to act
+            Some(Action::Fold) => self.current_player(),
+            _ if self.hands.0 > self.hands.1 => Player::P1,
+            _ => Player::P2,
+        };
+        match winner {
+            Player::P1 => Some(pot.1 as f32),
+            Player::P2 => Some(-(pot.0 as f32)),
+        }
+    }
+
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        //each player can put the ante and one bet into the pot
+        (-2.0, 2.0)
+    }
+
+    fn get_infoset(&self, player: Player) -> Infoset {
+        //the player's card is only known to them, and the seats never change
+        let hand = *player.lens(&self.hands);
+        Infoset::new((hand, self.history.clone()))
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        self.history.iter().map(Observation::new).collect()
+    }
+
+    fn get_private_observation(&self, player: Player) -> Observation {
+        Observation::new(*player.lens(&self.hands))
+    }
+}
+
+impl UndoGame for KuhnPoker {
+    //the history already has everything we need
+    type Undo = ();
+
+    fn apply_turn(&mut self, player: Player, action: &Action) -> Result<(), IllegalMove<Action>> {
+        self.take_turn(player, action)
+    }
+
+    fn undo_turn(&mut self, _undo: ()) {
+        self.history.pop().expect("Tried to undo with no history");
+    }
+}
+
+impl TensorGame for KuhnPoker {
+    fn tensor_size(&self) -> usize {
+        //one-hot card, then a one-hot action for each of the 3 possible bets
+        NUM_CARDS as usize + 3 * 3
+    }
+
+    fn get_infoset_tensor(&self, player: Player) -> Vec<f32> {
+        let mut tensor = vec![0.0; self.tensor_size()];
+        tensor[*player.lens(&self.hands) as usize] = 1.0;
+
+        let offset = NUM_CARDS as usize;
+        for (i, action) in self.history.iter().enumerate() {
+            tensor[offset + 3 * i + action_id(*action)] = 1.0;
+        }
+        tensor
+    }
+}
+
+impl RecordGame for KuhnPoker {
+    fn get_record(&self) -> GameRecord {
+        let mut record = GameRecord::new("kuhn");
+        record.add_setup("p1_card", self.hands.0);
+        record.add_setup("p2_card", self.hands.1);
+        record.turns = self.history.iter().enumerate()
+            .map(|(i, action)| RecordedTurn::new(player_at(i), action))
+            .collect();
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<KuhnPoker, RecordError> {
+        record.check_game("kuhn")?;
+        let hands: (u32, u32) = (record.get_setup("p1_card")?, record.get_setup("p2_card")?);
+        if hands.0 >= NUM_CARDS {
+            return Err(RecordError::BadSetup { key: "p1_card", value: hands.0.to_string() });
+        }
+        if hands.1 >= NUM_CARDS || hands.1 == hands.0 {
+            return Err(RecordError::BadSetup { key: "p2_card", value: hands.1.to_string() });
+        }
+
+        let mut game = KuhnPoker::manual_new(hands);
+        record.replay(&mut game)?;
+        Ok(game)
+    }
+}
+
+impl Display for KuhnPoker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "P1 card: {}, P2 card: {}; ", card_to_string(self.hands.0), card_to_string(self.hands.1))?;
+        for (i, action) in self.history.iter().enumerate() {
+            write!(f, "{} does {}; ", player_at(i), action)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)?;
+        Ok(())
+    }
+}
+
+/// Player who takes the given turn, counting from 0
+fn player_at(turn: usize) -> Player {
+    match turn % 2 {
+        0 => Player::P1,
+        _ => Player::P2,
+    }
+}
+
+fn card_to_string(card: u32) -> &'static str {
+    match card {
+        0 => "J",
+        1 => "Q",
+        _ => "K",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn showdowns() {
+        let mut game = KuhnPoker::manual_new((2, 0));
+        game.take_turn(Player::P1, &Action::Call).unwrap();
+        game.take_turn(Player::P2, &Action::Call).unwrap();
+        assert_eq!(game.get_reward(), Some(1.0));
+
+        let mut game = KuhnPoker::manual_new((1, 2));
+        game.take_turn(Player::P1, &Action::Call).unwrap();
+        game.take_turn(Player::P2, &Action::Bet).unwrap();
+        game.take_turn(Player::P1, &Action::Call).unwrap();
+        assert_eq!(game.get_reward(), Some(-2.0));
+    }
+
+    #[test]
+    fn folds() {
+        let mut game = KuhnPoker::manual_new((0, 2));
+        game.take_turn(Player::P1, &Action::Bet).unwrap();
+        assert_eq!(game.get_turn(), (Player::P2, vec![Action::Fold, Action::Call]));
+        game.take_turn(Player::P2, &Action::Fold).unwrap();
+        assert_eq!(game.get_reward(), Some(1.0));
+
+        let mut game = KuhnPoker::manual_new((2, 0));
+        game.take_turn(Player::P1, &Action::Call).unwrap();
+        game.take_turn(Player::P2, &Action::Bet).unwrap();
+        game.take_turn(Player::P1, &Action::Fold).unwrap();
+        assert_eq!(game.get_reward(), Some(-1.0));
+        assert!(game.take_turn(Player::P2, &Action::Call).is_err());
+    }
+
+    #[test]
+    fn deals_every_card_pair() {
+        let deals = KuhnPoker::all_deals();
+        assert_eq!(deals.len(), 6);
+        let total: f32 = deals.iter().map(|(_, prob)| prob).sum();
+        assert!((total - 1.0).abs() < 1e-6);
+
+        let game = KuhnPoker::new();
+        assert_ne!(game.hands.0, game.hands.1);
+    }
+}
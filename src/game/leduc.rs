@@ -0,0 +1,435 @@
+//http://poker.cs.ualberta.ca/publications/UAI05.pdf
+
+use rand::seq::SliceRandom;
+use std::fmt::{Display, Formatter};
+
+use crate::game::{Game, UndoGame, TensorGame, IllegalMove, IllegalMoveReason, Player, Infoset, Observation};
+use crate::game::{GameRecord, RecordedTurn, RecordGame, RecordError};
+
+/// Two suits of jack, queen and king, where card / 2 is the rank
+const NUM_CARDS: u32 = 6;
+const NUM_RANKS: u32 = 3;
+/// Bets and raises in each round, including the first bet
+const MAX_RAISES: u32 = 2;
+/// Most actions in a round, which is a check followed by every raise and a call
+const MAX_ROUND_ACTIONS: usize = MAX_RAISES as usize + 2;
+/// Size of a bet or raise in each round
+const BET_SIZES: [u32; 2] = [2, 4];
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub enum Action {
+    Fold,
+    /// Checks when there's no bet to call
+    Call,
+    /// Bets when there's no bet to raise
+    Raise,
+}
+
+/// Stable id of each action
+fn action_id(action: Action) -> usize {
+    match action {
+        Action::Fold => 0,
+        Action::Call => 1,
+        Action::Raise => 2,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LeducState {
+    Betting,
+    /// The player folded
+    FoldEnd(Player),
+    ShowdownEnd,
+}
+
+/// Leduc hold'em, where P1 acts first in both rounds
+///
+/// Each player antes 1 and gets a private card, then the public board card is
+/// shown between the two rounds of betting
+#[derive(Clone, Debug)]
+pub struct LeducPoker {
+    hands: (u32, u32),
+    board: u32,
+    pot: (u32, u32),
+    /// 0 before the board card is shown, 1 after
+    round: usize,
+    /// Index in the history where the current round started
+    round_start: usize,
+    raises: u32,
+    history: Vec<Action>,
+    state: LeducState,
+}
+
+impl LeducPoker {
+    /// Deals both hands and the board from a shuffled deck
+    pub fn new() -> LeducPoker {
+        let mut deck: Vec<u32> = (0..NUM_CARDS).collect();
+        deck.shuffle(&mut rand::thread_rng());
+        LeducPoker::manual_new((deck[0], deck[1]), deck[2])
+    }
+
+    /// Every possible deal, each with its probability
+    pub fn all_deals() -> Vec<(LeducPoker, f32)> {
+        let num_deals = NUM_CARDS * (NUM_CARDS - 1) * (NUM_CARDS - 2);
+        let mut deals = vec![];
+        for hand1 in 0..NUM_CARDS {
+            for hand2 in (0..NUM_CARDS).filter(|&hand2| hand2 != hand1) {
+                for board in (0..NUM_CARDS).filter(|&board| board != hand1 && board != hand2) {
+                    deals.push((LeducPoker::manual_new((hand1, hand2), board), 1.0 / num_deals as f32));
+                }
+            }
+        }
+        deals
+    }
+
+    pub fn manual_new(hands: (u32, u32), board: u32) -> LeducPoker {
+        LeducPoker {
+            hands,
+            board,
+            pot: (1, 1),
+            round: 0,
+            round_start: 0,
+            raises: 0,
+            history: vec![],
+            state: LeducState::Betting,
+        }
+    }
+
+    fn current_player(&self) -> Player {
+        match (self.history.len() - self.round_start) % 2 {
+            0 => Player::P1,
+            _ => Player::P2,
+        }
+    }
+
+    fn current_actions(&self) -> Vec<Action> {
+        if self.state != LeducState::Betting {
+            return vec![];
+        }
+        let mut actions = if self.pot.0 == self.pot.1 {
+            vec![Action::Call]
+        } else {
+            vec![Action::Fold, Action::Call]
+        };
+        if self.raises < MAX_RAISES {
+            actions.push(Action::Raise);
+        }
+        actions
+    }
+
+    /// The board card once it's been shown
+    fn get_visible_board(&self) -> Option<u32> {
+        if self.round > 0 {
+            Some(self.board)
+        } else {
+            None
+        }
+    }
+
+    /// Strength of a hand at showdown, where pairing the board beats any high card
+    fn hand_strength(&self, hand: u32) -> u32 {
+        if hand / 2 == self.board / 2 {
+            NUM_RANKS + hand / 2
+        } else {
+            hand / 2
+        }
+    }
+
+    /// Ends the current round once both players have acted and the bets are called
+    fn end_round(&mut self) {
+        if self.round == 0 {
+            self.round = 1;
+            self.round_start = self.history.len();
+            self.raises = 0;
+        } else {
+            self.state = LeducState::ShowdownEnd;
+        }
+    }
+}
+
+impl Game for LeducPoker {
+    type Action = Action;
+
+    fn get_turn(&self) -> (Player, Vec<Action>) {
+        (self.current_player(), self.current_actions())
+    }
+
+    fn take_turn(&mut self, player: Player, action: &Action) -> Result<(), IllegalMove<Action>> {
+        let actions = self.current_actions();
+        let reason = if actions.is_empty() {
+            Some(IllegalMoveReason::GameOver)
+        } else if player != self.current_player() {
+            Some(IllegalMoveReason::WrongPlayer { expected: self.current_player() })
+        } else if !actions.contains(action) {
+            Some(IllegalMoveReason::InvalidAction("action is not allowed at this point in the betting"))
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(IllegalMove::new(player, action, reason));
+        }
+
+        let other_pot = *player.other().lens(&self.pot);
+        let is_first_action = self.history.len() == self.round_start;
+        self.history.push(*action);
+        match action {
+            Action::Fold => self.state = LeducState::FoldEnd(player),
+            //checking first leaves the other player to act
+            Action::Call if is_first_action => (),
+            Action::Call => {
+                *player.lens_mut(&mut self.pot) = other_pot;
+                self.end_round();
+            }
+            Action::Raise => {
+                *player.lens_mut(&mut self.pot) = other_pot + BET_SIZES[self.round];
+                self.raises += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        3
+    }
+
+    fn action_id(&self, action: &Action) -> usize {
+        action_id(*action)
+    }
+
+    fn parse_action(&self, action: &str) -> Option<Action> {
+        match action {
+            "Fold" => Some(Action::Fold),
+            "Call" => Some(Action::Call),
+            "Raise" => Some(Action::Raise),
+            _ => None,
+        }
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        //the winner gets the other player's contribution to the pot
+        let winner = match self.state {
+            LeducState::Betting => return None,
+            LeducState::FoldEnd(player) => player.other(),
+            LeducState::ShowdownEnd => {
+                let strengths = (self.hand_strength(self.hands.0), self.hand_strength(self.hands.1));
+                if strengths.0 == strengths.1 {
+                    return Some(0.0);
+                } else if strengths.0 > strengths.1 {
+                    Player::P1
+                } else {
+                    Player::P2
+                }
+            }
+        };
+        match winner {
+            Player::P1 => Some(self.pot.1 as f32),
+            Player::P2 => Some(-(self.pot.0 as f32)),
+        }
+    }
+
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        //the ante and every raise in both rounds
+        let most = 1 + MAX_RAISES * (BET_SIZES[0] + BET_SIZES[1]);
+        (-(most as f32), most as f32)
+    }
+
+    fn get_infoset(&self, player: Player) -> Infoset {
+        //the player's card is only known to them, and the seats never change
+        let hand = *player.lens(&self.hands);
+        Infoset::new((hand, self.get_visible_board(), self.history.clone()))
+    }
+
+    /// Suits never matter, so cards of the same rank are the same
+    fn get_canonical_infoset(&self, player: Player) -> (Infoset, Option<Vec<usize>>) {
+        let hand = *player.lens(&self.hands) / 2;
+        let board = self.get_visible_board().map(|board| board / 2);
+        (Infoset::new((hand, board, self.history.clone())), None)
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        let mut observations: Vec<Observation> = self.history.iter().map(Observation::new).collect();
+        if let Some(board) = self.get_visible_board() {
+            observations.insert(self.round_start, Observation::new(board));
+        }
+        observations
+    }
+
+    fn get_private_observation(&self, player: Player) -> Observation {
+        Observation::new(*player.lens(&self.hands))
+    }
+}
+
+pub struct Undo {
+    pot: (u32, u32),
+    round: usize,
+    round_start: usize,
+    raises: u32,
+    state: LeducState,
+}
+
+impl UndoGame for LeducPoker {
+    type Undo = Undo;
+
+    fn apply_turn(&mut self, player: Player, action: &Action) -> Result<Undo, IllegalMove<Action>> {
+        let undo = Undo {
+            pot: self.pot,
+            round: self.round,
+            round_start: self.round_start,
+            raises: self.raises,
+            state: self.state,
+        };
+        self.take_turn(player, action)?;
+        Ok(undo)
+    }
+
+    fn undo_turn(&mut self, undo: Undo) {
+        self.pot = undo.pot;
+        self.round = undo.round;
+        self.round_start = undo.round_start;
+        self.raises = undo.raises;
+        self.state = undo.state;
+        self.history.pop();
+    }
+}
+
+impl TensorGame for LeducPoker {
+    fn tensor_size(&self) -> usize {
+        //one-hot hand rank, one-hot board rank once it's shown,
+        //then a one-hot action for each action in both rounds
+        2 * NUM_RANKS as usize + 2 * 3 * MAX_ROUND_ACTIONS
+    }
+
+    fn get_infoset_tensor(&self, player: Player) -> Vec<f32> {
+        let mut tensor = vec![0.0; self.tensor_size()];
+        tensor[(*player.lens(&self.hands) / 2) as usize] = 1.0;
+        if let Some(board) = self.get_visible_board() {
+            tensor[(NUM_RANKS + board / 2) as usize] = 1.0;
+        }
+
+        let offset = 2 * NUM_RANKS as usize;
+        for (i, action) in self.history.iter().enumerate() {
+            let (round, i) = if i < self.round_start || self.round == 0 {
+                (0, i)
+            } else {
+                (1, i - self.round_start)
+            };
+            tensor[offset + 3 * (round * MAX_ROUND_ACTIONS + i) + action_id(*action)] = 1.0;
+        }
+        tensor
+    }
+}
+
+impl RecordGame for LeducPoker {
+    fn get_record(&self) -> GameRecord {
+        let mut record = GameRecord::new("leduc");
+        record.add_setup("p1_card", self.hands.0);
+        record.add_setup("p2_card", self.hands.1);
+        record.add_setup("board", self.board);
+        //replaying works out where the second round starts
+        let mut replay = LeducPoker::manual_new(self.hands, self.board);
+        for action in self.history.iter() {
+            let player = replay.current_player();
+            record.turns.push(RecordedTurn::new(player, action));
+            replay.take_turn(player, action).expect("Failed to replay own history");
+        }
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<LeducPoker, RecordError> {
+        record.check_game("leduc")?;
+        let hands: (u32, u32) = (record.get_setup("p1_card")?, record.get_setup("p2_card")?);
+        let board: u32 = record.get_setup("board")?;
+        if hands.0 >= NUM_CARDS {
+            return Err(RecordError::BadSetup { key: "p1_card", value: hands.0.to_string() });
+        }
+        if hands.1 >= NUM_CARDS || hands.1 == hands.0 {
+            return Err(RecordError::BadSetup { key: "p2_card", value: hands.1.to_string() });
+        }
+        if board >= NUM_CARDS || board == hands.0 || board == hands.1 {
+            return Err(RecordError::BadSetup { key: "board", value: board.to_string() });
+        }
+
+        let mut game = LeducPoker::manual_new(hands, board);
+        record.replay(&mut game)?;
+        Ok(game)
+    }
+}
+
+impl Display for LeducPoker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "P1 hand: {}, P2 hand: {}, board: {}; ", card_to_string(self.hands.0), card_to_string(self.hands.1), card_to_string(self.board))?;
+        for (i, action) in self.history.iter().enumerate() {
+            if i == self.round_start && self.round > 0 {
+                write!(f, "board shown; ")?;
+            }
+            write!(f, "{}; ", action)?;
+        }
+        write!(f, "pot {:?}", self.pot)
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)?;
+        Ok(())
+    }
+}
+
+fn card_to_string(card: u32) -> &'static str {
+    match card / 2 {
+        0 => "J",
+        1 => "Q",
+        _ => "K",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game: &mut LeducPoker, actions: &[Action]) {
+        for action in actions.iter() {
+            let (player, _) = game.get_turn();
+            game.take_turn(player, action).unwrap();
+        }
+    }
+
+    #[test]
+    fn plays_two_rounds() {
+        //P1 has a king, P2 has a jack that pairs the board
+        let mut game = LeducPoker::manual_new((4, 0), 1);
+        play(&mut game, &[Action::Call, Action::Raise, Action::Call]);
+        assert_eq!(game.round, 1);
+        assert_eq!(game.get_turn(), (Player::P1, vec![Action::Call, Action::Raise]));
+        assert_eq!(game.get_visible_board(), Some(1));
+
+        play(&mut game, &[Action::Raise, Action::Raise, Action::Call]);
+        assert_eq!(game.pot, (11, 11));
+        assert_eq!(game.get_reward(), Some(-11.0));
+    }
+
+    #[test]
+    fn caps_raises() {
+        let mut game = LeducPoker::manual_new((2, 4), 0);
+        play(&mut game, &[Action::Raise, Action::Raise]);
+        assert_eq!(game.get_turn(), (Player::P1, vec![Action::Fold, Action::Call]));
+        play(&mut game, &[Action::Fold]);
+        assert_eq!(game.get_reward(), Some(-3.0));
+        assert_eq!(game.get_visible_board(), None);
+    }
+
+    #[test]
+    fn splits_ties() {
+        let mut game = LeducPoker::manual_new((2, 3), 0);
+        play(&mut game, &[Action::Call, Action::Call, Action::Call, Action::Call]);
+        assert_eq!(game.get_reward(), Some(0.0));
+    }
+
+    #[test]
+    fn ignores_suits() {
+        let game1 = LeducPoker::manual_new((2, 4), 0);
+        let game2 = LeducPoker::manual_new((3, 5), 1);
+        assert_ne!(game1.get_infoset(Player::P1).hash, game2.get_infoset(Player::P1).hash);
+        assert_eq!(game1.get_canonical_infoset(Player::P1).0.hash, game2.get_canonical_infoset(Player::P1).0.hash);
+    }
+}
@@ -0,0 +1,343 @@
+//https://en.wikipedia.org/wiki/Liar%27s_dice
+
+use rand::Rng;
+use std::fmt::{Display, Formatter};
+
+use crate::game::{Game, UndoGame, TensorGame, IllegalMove, IllegalMoveReason, Player, Infoset, Observation};
+use crate::game::{GameRecord, RecordedTurn, RecordGame, RecordError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub enum Action {
+    /// At least `quantity` dice across both hands show `face`
+    Bid { quantity: u8, face: u8 },
+    /// Claims the last bid is wrong, which ends the game
+    Liar,
+}
+
+/// Liar's dice where each player rolls the same number of dice, and P1 bids first
+///
+/// Bids have to go up in quantity, or stay at the same quantity with a higher face.
+/// Once someone calls liar the dice are revealed, and whoever was wrong loses
+#[derive(Clone, Debug)]
+pub struct LiarsDice {
+    num_dice: u8,
+    num_faces: u8,
+    /// Each player's dice, sorted since their order doesn't matter
+    dice: (Vec<u8>, Vec<u8>),
+    history: Vec<Action>,
+}
+
+impl LiarsDice {
+    /// Rolls every die
+    pub fn new(num_dice: u8, num_faces: u8) -> LiarsDice {
+        let mut rng = rand::thread_rng();
+        let mut roll = || (0..num_dice).map(|_| rng.gen_range(1, num_faces + 1)).collect();
+        let dice = (roll(), roll());
+        LiarsDice::manual_new(num_faces, dice)
+    }
+
+    /// Every possible roll, each with its probability
+    ///
+    /// There are faces^(2 * dice) rolls, so this is only for small games
+    pub fn all_rolls(num_dice: u8, num_faces: u8) -> Vec<(LiarsDice, f32)> {
+        let num_rolls = (num_faces as u32).pow(2 * num_dice as u32);
+        (0..num_rolls).map(|roll| {
+            let faces: Vec<u8> = (0..2 * num_dice as u32)
+                .map(|i| (roll / (num_faces as u32).pow(i) % num_faces as u32) as u8 + 1)
+                .collect();
+            let (dice1, dice2) = faces.split_at(num_dice as usize);
+            (LiarsDice::manual_new(num_faces, (dice1.to_vec(), dice2.to_vec())), 1.0 / num_rolls as f32)
+        }).collect()
+    }
+
+    /// Both players need the same number of dice
+    pub fn manual_new(num_faces: u8, mut dice: (Vec<u8>, Vec<u8>)) -> LiarsDice {
+        assert_eq!(dice.0.len(), dice.1.len(), "Players have different numbers of dice");
+        dice.0.sort();
+        dice.1.sort();
+        LiarsDice {
+            num_dice: dice.0.len() as u8,
+            num_faces,
+            dice,
+            history: vec![],
+        }
+    }
+
+    fn current_player(&self) -> Player {
+        player_at(self.history.len())
+    }
+
+    fn num_bids(&self) -> usize {
+        2 * self.num_dice as usize * self.num_faces as usize
+    }
+
+    /// Bids are ordered by quantity and then face, so a bid's index is also how high it is
+    fn bid_index(&self, quantity: u8, face: u8) -> usize {
+        (quantity as usize - 1) * self.num_faces as usize + (face as usize - 1)
+    }
+
+    fn current_actions(&self) -> Vec<Action> {
+        let first_bid = match self.history.last() {
+            None => 0,
+            Some(Action::Liar) => return vec![],
+            Some(Action::Bid { quantity, face }) => self.bid_index(*quantity, *face) + 1,
+        };
+        let num_faces = self.num_faces as usize;
+        let bids = (first_bid..self.num_bids()).map(|bid| Action::Bid {
+            quantity: (bid / num_faces + 1) as u8,
+            face: (bid % num_faces + 1) as u8,
+        });
+        if self.history.is_empty() {
+            bids.collect()
+        } else {
+            bids.chain(std::iter::once(Action::Liar)).collect()
+        }
+    }
+
+    /// Number of dice in both hands showing the face
+    fn count_face(&self, face: u8) -> u8 {
+        self.dice.0.iter().chain(self.dice.1.iter())
+            .filter(|die| **die == face)
+            .count() as u8
+    }
+}
+
+impl Game for LiarsDice {
+    type Action = Action;
+
+    fn get_turn(&self) -> (Player, Vec<Action>) {
+        (self.current_player(), self.current_actions())
+    }
+
+    fn take_turn(&mut self, player: Player, action: &Action) -> Result<(), IllegalMove<Action>> {
+        let actions = self.current_actions();
+        let reason = if actions.is_empty() {
+            Some(IllegalMoveReason::GameOver)
+        } else if player != self.current_player() {
+            Some(IllegalMoveReason::WrongPlayer { expected: self.current_player() })
+        } else if *action == Action::Liar && self.history.is_empty() {
+            Some(IllegalMoveReason::InvalidAction("there's no bid to call"))
+        } else if !actions.contains(action) {
+            Some(IllegalMoveReason::InvalidAction("bid has to be higher than the last bid"))
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(IllegalMove::new(player, action, reason));
+        }
+
+        self.history.push(*action);
+        Ok(())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        1 + self.num_bids()
+    }
+
+    fn action_id(&self, action: &Action) -> usize {
+        match action {
+            Action::Liar => 0,
+            Action::Bid { quantity, face } => 1 + self.bid_index(*quantity, *face),
+        }
+    }
+
+    fn parse_action(&self, action: &str) -> Option<Action> {
+        if action == "Liar" {
+            return Some(Action::Liar);
+        }
+        let bid = action.strip_prefix("Bid { quantity: ")?.strip_suffix(" }")?;
+        let (quantity, face) = bid.split_once(", face: ")?;
+        let (quantity, face): (u8, u8) = (quantity.parse().ok()?, face.parse().ok()?);
+        let is_valid = 1 <= quantity && quantity <= 2 * self.num_dice && 1 <= face && face <= self.num_faces;
+        Some(Action::Bid { quantity, face }).filter(|_| is_valid)
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        if self.history.last() != Some(&Action::Liar) {
+            return None;
+        }
+        let (quantity, face) = match self.history[self.history.len() - 2] {
+            Action::Bid { quantity, face } => (quantity, face),
+            Action::Liar => unreachable!("Liar was called twice"),
+        };
+        //the caller is the last player to act, and the bidder acted before them
+        let caller = player_at(self.history.len() - 1);
+        let winner = if self.count_face(face) >= quantity {
+            caller.other()
+        } else {
+            caller
+        };
+        match winner {
+            Player::P1 => Some(1.0),
+            Player::P2 => Some(-1.0),
+        }
+    }
+
+    fn get_infoset(&self, player: Player) -> Infoset {
+        //the player's dice are only known to them, and the seats never change
+        Infoset::new((player.lens(&self.dice).clone(), self.history.clone()))
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        self.history.iter().map(Observation::new).collect()
+    }
+
+    fn get_private_observation(&self, player: Player) -> Observation {
+        Observation::new(player.lens(&self.dice))
+    }
+}
+
+impl UndoGame for LiarsDice {
+    //the history already has everything we need
+    type Undo = ();
+
+    fn apply_turn(&mut self, player: Player, action: &Action) -> Result<(), IllegalMove<Action>> {
+        self.take_turn(player, action)
+    }
+
+    fn undo_turn(&mut self, _undo: ()) {
+        self.history.pop().expect("Tried to undo with no history");
+    }
+}
+
+impl TensorGame for LiarsDice {
+    fn tensor_size(&self) -> usize {
+        //how many of the player's dice show each face, which player made each bid, and whether liar was called
+        self.num_faces as usize + 2 * self.num_bids() + 1
+    }
+
+    fn get_infoset_tensor(&self, player: Player) -> Vec<f32> {
+        let mut tensor = vec![0.0; self.tensor_size()];
+        for die in player.lens(&self.dice).iter() {
+            tensor[*die as usize - 1] += 1.0;
+        }
+
+        let offset = self.num_faces as usize;
+        for (i, action) in self.history.iter().enumerate() {
+            //bids are from the player's perspective, so their own bids come first
+            match action {
+                Action::Bid { quantity, face } => {
+                    let seat = if player_at(i) == player { 0 } else { 1 };
+                    tensor[offset + 2 * self.bid_index(*quantity, *face) + seat] = 1.0;
+                }
+                Action::Liar => tensor[offset + 2 * self.num_bids()] = 1.0,
+            }
+        }
+        tensor
+    }
+}
+
+impl RecordGame for LiarsDice {
+    fn get_record(&self) -> GameRecord {
+        let mut record = GameRecord::new("liars_dice");
+        record.add_setup("faces", self.num_faces);
+        record.add_setup("p1_dice", dice_to_string(&self.dice.0));
+        record.add_setup("p2_dice", dice_to_string(&self.dice.1));
+        record.turns = self.history.iter().enumerate()
+            .map(|(i, action)| RecordedTurn::new(player_at(i), action))
+            .collect();
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<LiarsDice, RecordError> {
+        record.check_game("liars_dice")?;
+        let num_faces: u8 = record.get_setup("faces")?;
+        let parse_dice = |key: &'static str| -> Result<Vec<u8>, RecordError> {
+            let dice: String = record.get_setup(key)?;
+            dice.chars()
+                .map(|die| die.to_digit(10).map(|die| die as u8).filter(|die| 1 <= *die && *die <= num_faces))
+                .collect::<Option<Vec<u8>>>()
+                .ok_or(RecordError::BadSetup { key, value: dice.clone() })
+        };
+        let dice = (parse_dice("p1_dice")?, parse_dice("p2_dice")?);
+        if dice.1.len() != dice.0.len() {
+            return Err(RecordError::BadSetup { key: "p2_dice", value: dice_to_string(&dice.1) });
+        }
+
+        let mut game = LiarsDice::manual_new(num_faces, dice);
+        record.replay(&mut game)?;
+        Ok(game)
+    }
+}
+
+impl Display for LiarsDice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "P1 dice: {}, P2 dice: {}; ", dice_to_string(&self.dice.0), dice_to_string(&self.dice.1))?;
+        for action in self.history.iter() {
+            write!(f, "{}; ", action)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)?;
+        Ok(())
+    }
+}
+
+/// Player who takes the given turn, counting from 0
+fn player_at(turn: usize) -> Player {
+    match turn % 2 {
+        0 => Player::P1,
+        _ => Player::P2,
+    }
+}
+
+/// Writes dice as their faces in a row, like 136
+fn dice_to_string(dice: &[u8]) -> String {
+    dice.iter().map(|die| die.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bids_go_up() {
+        let mut game = LiarsDice::manual_new(3, (vec![1], vec![3]));
+        let (player, actions) = game.get_turn();
+        assert_eq!(player, Player::P1);
+        assert_eq!(actions.len(), 6);
+        assert!(game.take_turn(Player::P1, &Action::Liar).is_err());
+
+        game.take_turn(Player::P1, &Action::Bid { quantity: 1, face: 2 }).unwrap();
+        assert_eq!(game.get_turn(), (Player::P2, vec![
+            Action::Bid { quantity: 1, face: 3 },
+            Action::Bid { quantity: 2, face: 1 },
+            Action::Bid { quantity: 2, face: 2 },
+            Action::Bid { quantity: 2, face: 3 },
+            Action::Liar,
+        ]));
+        assert!(game.take_turn(Player::P2, &Action::Bid { quantity: 1, face: 1 }).is_err());
+    }
+
+    #[test]
+    fn calls_liar() {
+        //there's only one 3, so P1's bid is wrong and P2 wins by calling it
+        let mut game = LiarsDice::manual_new(3, (vec![1], vec![3]));
+        game.take_turn(Player::P1, &Action::Bid { quantity: 2, face: 3 }).unwrap();
+        game.take_turn(Player::P2, &Action::Liar).unwrap();
+        assert_eq!(game.get_reward(), Some(-1.0));
+        assert_eq!(game.get_turn().1, vec![]);
+
+        //P2's bid is right, so P1 loses by calling it
+        let mut game = LiarsDice::manual_new(3, (vec![1, 3], vec![3, 2]));
+        game.take_turn(Player::P1, &Action::Bid { quantity: 1, face: 2 }).unwrap();
+        game.take_turn(Player::P2, &Action::Bid { quantity: 2, face: 3 }).unwrap();
+        game.take_turn(Player::P1, &Action::Liar).unwrap();
+        assert_eq!(game.get_reward(), Some(-1.0));
+    }
+
+    #[test]
+    fn rolls_every_combination() {
+        let rolls = LiarsDice::all_rolls(1, 3);
+        assert_eq!(rolls.len(), 9);
+        let total: f32 = rolls.iter().map(|(_, prob)| prob).sum();
+        assert!((total - 1.0).abs() < 1e-6);
+
+        let game = LiarsDice::new(2, 6);
+        assert!(game.dice.0.iter().chain(game.dice.1.iter()).all(|die| 1 <= *die && *die <= 6));
+    }
+}
@@ -0,0 +1,481 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::game::{Game, UndoGame, IllegalMove, IllegalMoveReason, Player, Infoset, Observation, PublicState};
+
+/// What players remember about earlier rounds of a match
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RoundVisibility {
+    /// Only which round it is, so players don't have perfect recall
+    Nothing,
+    /// The result of every earlier round, but not how it was played, so players forget their own actions
+    Rewards,
+    /// Results along with each player's own actions and final infoset, which keeps perfect recall
+    Full,
+}
+
+/// How round rewards add up to the match reward
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MatchReward {
+    Sum,
+    /// Average over the rounds that were played
+    Average,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchConfig {
+    /// Most rounds the match can have
+    pub rounds: usize,
+    /// Players swap seats every round, so they take turns going first or dealing
+    pub alternate: bool,
+    pub visibility: RoundVisibility,
+    pub reward: MatchReward,
+    /// Reward each player starts with, like chips in a poker session
+    ///
+    /// The match ends early once either player can't afford to lose another round
+    pub stack: Option<f32>,
+    /// End early once the remaining rounds can't change who's ahead, like a best of 3
+    pub stop_when_decided: bool,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig {
+            rounds: 3,
+            alternate: true,
+            visibility: RoundVisibility::Full,
+            reward: MatchReward::Sum,
+            stack: None,
+            stop_when_decided: false,
+        }
+    }
+}
+
+/// What happened in a finished round, from the match players' point of view
+#[derive(Clone, Debug)]
+struct RoundResult {
+    /// P1's reward
+    reward: f32,
+    /// Each player's action ids
+    actions: (Vec<usize>, Vec<usize>),
+    /// Each player's infoset at the end of the round
+    infosets: (u64, u64),
+}
+
+/// Plays a game several times in a row as one game
+///
+/// Each round is a new game, and P1 and P2 always refer to the same players across rounds,
+/// even when they swap seats
+#[derive(Clone)]
+pub struct Match<G: Game> {
+    new_game: Arc<dyn Fn() -> G + Send + Sync>,
+    config: MatchConfig,
+    game: G,
+    /// Each player's action ids so far in this round
+    actions: (Vec<usize>, Vec<usize>),
+    rounds: Vec<RoundResult>,
+}
+
+impl<G: Game> Match<G> {
+    pub fn new(new_game: impl Fn() -> G + Send + Sync + 'static, config: MatchConfig) -> Match<G> {
+        if config.rounds == 0 {
+            panic!("Match needs at least one round");
+        }
+        Match {
+            game: new_game(),
+            new_game: Arc::new(new_game),
+            config,
+            actions: (vec![], vec![]),
+            rounds: vec![],
+        }
+    }
+
+    fn is_swapped(&self, round: usize) -> bool {
+        self.config.alternate && round % 2 == 1
+    }
+
+    /// Seat in the current round's game of the given match player, or the other way around
+    fn seat(&self, player: Player) -> Player {
+        if self.is_swapped(self.rounds.len()) {
+            player.other()
+        } else {
+            player
+        }
+    }
+
+    /// Swaps a tuple of seats in the current round to a tuple of match players, or the other way around
+    fn swap_pair<T>(&self, (p1, p2): (T, T)) -> (T, T) {
+        self.seat(Player::P1).pair(p1, p2)
+    }
+
+    /// Changes the players in an illegal move from seats to match players
+    fn unseat_move(&self, err: IllegalMove<G::Action>) -> IllegalMove<G::Action> {
+        let reason = match err.reason {
+            IllegalMoveReason::WrongPlayer { expected } => IllegalMoveReason::WrongPlayer { expected: self.seat(expected) },
+            reason => reason,
+        };
+        IllegalMove {
+            player: self.seat(err.player),
+            reason,
+            ..err
+        }
+    }
+
+    /// P1's reward bounds in the given round
+    fn round_bounds(&self, round: usize) -> (f32, f32) {
+        let (min, max) = self.game.get_utility_bounds();
+        if self.is_swapped(round) {
+            (-max, -min)
+        } else {
+            (min, max)
+        }
+    }
+
+    fn total_reward(&self) -> f32 {
+        self.rounds.iter().map(|round| round.reward).sum()
+    }
+
+    /// Whether no more rounds will be played, which is only checked between rounds
+    fn is_over(&self) -> bool {
+        let played = self.rounds.len();
+        if played >= self.config.rounds {
+            return true;
+        }
+        let total = self.total_reward();
+
+        //each player has to be able to cover the most they could lose next round
+        if let Some(stack) = self.config.stack {
+            let (min, max) = self.round_bounds(played);
+            if stack + total < -min || stack - total < max {
+                return true;
+            }
+        }
+
+        if self.config.stop_when_decided && played > 0 {
+            let max_swing: f32 = (played .. self.config.rounds)
+                .map(|round| {
+                    let (min, max) = self.round_bounds(round);
+                    min.abs().max(max.abs())
+                })
+                .sum();
+            if total.abs() > max_swing {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Records the action ids of a turn that was just taken, and finishes the round if it's over
+    ///
+    /// Returns whether the round finished, and the finished game if a new round was started
+    fn end_turn(&mut self, actions: &[(Player, usize)]) -> (bool, Option<G>) {
+        for (player, id) in actions.iter() {
+            player.lens_mut(&mut self.actions).push(*id);
+        }
+
+        let reward = match self.game.get_reward() {
+            Some(reward) => *self.seat(Player::P1).lens(&(reward, -reward)),
+            None => return (false, None),
+        };
+        let infosets = (
+            self.game.get_infoset(self.seat(Player::P1)).hash,
+            self.game.get_infoset(self.seat(Player::P2)).hash,
+        );
+        self.rounds.push(RoundResult {
+            reward,
+            actions: std::mem::take(&mut self.actions),
+            infosets,
+        });
+
+        if self.is_over() {
+            (true, None)
+        } else {
+            (true, Some(std::mem::replace(&mut self.game, (*self.new_game)())))
+        }
+    }
+
+    /// Goes back to before `end_turn`
+    fn undo_end_turn(&mut self, actions: &[Player], (finished_round, finished_game): (bool, Option<G>)) {
+        if finished_round {
+            let round = self.rounds.pop().expect("Failed to find finished round");
+            self.actions = round.actions;
+        }
+        if let Some(game) = finished_game {
+            self.game = game;
+        }
+        for player in actions.iter() {
+            player.lens_mut(&mut self.actions).pop();
+        }
+    }
+
+    /// What the player remembers of earlier rounds
+    fn get_round_history(&self, player: Player) -> Vec<(u32, &[usize], u64)> {
+        match self.config.visibility {
+            RoundVisibility::Full => self.rounds.iter()
+                .map(|round| (round.reward.to_bits(), player.lens(&round.actions).as_slice(), *player.lens(&round.infosets)))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn get_visible_rewards(&self) -> Vec<u32> {
+        match self.config.visibility {
+            RoundVisibility::Nothing => vec![],
+            _ => self.rounds.iter().map(|round| round.reward.to_bits()).collect(),
+        }
+    }
+}
+
+impl<G: Game + Clone> Game for Match<G> {
+    type Action = G::Action;
+
+    fn get_turn(&self) -> (Player, Vec<G::Action>) {
+        let (seat, actions) = self.game.get_turn();
+        (self.seat(seat), actions)
+    }
+
+    fn get_simultaneous_turn(&self) -> Option<(Vec<G::Action>, Vec<G::Action>)> {
+        self.game.get_simultaneous_turn().map(|actions| self.swap_pair(actions))
+    }
+
+    fn take_turn(&mut self, player: Player, action: &G::Action) -> Result<(), IllegalMove<G::Action>> {
+        self.game.take_turn(self.seat(player), action)
+            .map_err(|err| self.unseat_move(err))?;
+        self.end_turn(&[(player, self.game.action_id(action))]);
+        Ok(())
+    }
+
+    fn take_simultaneous_turn(&mut self, actions: (&G::Action, &G::Action)) -> Result<(), IllegalMove<G::Action>> {
+        self.game.take_simultaneous_turn(self.swap_pair(actions))
+            .map_err(|err| self.unseat_move(err))?;
+        self.end_turn(&[(Player::P1, self.game.action_id(actions.0)), (Player::P2, self.game.action_id(actions.1))]);
+        Ok(())
+    }
+
+    fn take_turn_outcomes(&self, player: Player, action: &G::Action) -> Result<Vec<(Self, f32)>, IllegalMove<G::Action>> {
+        let outcomes = self.game.take_turn_outcomes(self.seat(player), action)
+            .map_err(|err| self.unseat_move(err))?;
+        let id = self.game.action_id(action);
+        Ok(outcomes.into_iter().map(|(game, prob)| {
+            let mut outcome = self.clone();
+            outcome.game = game;
+            outcome.end_turn(&[(player, id)]);
+            (outcome, prob)
+        }).collect())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        self.game.num_action_ids()
+    }
+
+    fn action_id(&self, action: &G::Action) -> usize {
+        self.game.action_id(action)
+    }
+
+    fn parse_action(&self, action: &str) -> Option<G::Action> {
+        self.game.parse_action(action)
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        if self.rounds.is_empty() || !self.is_over() {
+            return None;
+        }
+        let total = self.total_reward();
+        match self.config.reward {
+            MatchReward::Sum => Some(total),
+            MatchReward::Average => Some(total / self.rounds.len() as f32),
+        }
+    }
+
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        //rounds can end early, so the bounds have to cover any number of rounds
+        let (min, max) = (0 .. 2)
+            .map(|round| self.round_bounds(round))
+            .fold((0.0, 0.0), |(min, max), (round_min, round_max)| (round_min.min(min), round_max.max(max)));
+        match self.config.reward {
+            MatchReward::Sum => (min * self.config.rounds as f32, max * self.config.rounds as f32),
+            MatchReward::Average => (min, max),
+        }
+    }
+
+    fn get_infoset(&self, player: Player) -> Infoset {
+        Infoset::new((
+            self.rounds.len(),
+            self.get_visible_rewards(),
+            self.get_round_history(player),
+            self.game.get_infoset(self.seat(player)).hash,
+        ))
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        self.rounds.iter()
+            .map(|round| Observation::new(round.reward.to_bits()))
+            .chain(std::iter::once(Observation::new(self.game.get_public_state().hash)))
+            .collect()
+    }
+
+    fn get_private_observation(&self, player: Player) -> Observation {
+        Observation::new((self.get_round_history(player), self.game.get_private_observation(self.seat(player)).hash))
+    }
+
+    fn get_public_state(&self) -> PublicState {
+        PublicState::new(self.get_public_observations())
+    }
+
+    fn get_summary_string(&self, player: Player) -> String {
+        format!("Round {} of {}, you are {} this round, your total is {}\n{}",
+            self.rounds.len() + 1, self.config.rounds, self.seat(player),
+            *player.lens(&(self.total_reward(), -self.total_reward())),
+            self.game.get_summary_string(self.seat(player)))
+    }
+}
+
+/// Undoes a turn of a `Match`
+pub struct MatchUndo<G: UndoGame> {
+    undo: G::Undo,
+    /// Players whose action ids were recorded
+    players: Vec<Player>,
+    /// Whether the turn finished a round, and the finished game if a new round was started
+    round_end: (bool, Option<G>),
+}
+
+impl<G: UndoGame + Clone> UndoGame for Match<G> {
+    type Undo = MatchUndo<G>;
+
+    fn apply_turn(&mut self, player: Player, action: &G::Action) -> Result<MatchUndo<G>, IllegalMove<G::Action>> {
+        let undo = self.game.apply_turn(self.seat(player), action)
+            .map_err(|err| self.unseat_move(err))?;
+        let round_end = self.end_turn(&[(player, self.game.action_id(action))]);
+        Ok(MatchUndo {
+            undo,
+            players: vec![player],
+            round_end,
+        })
+    }
+
+    fn apply_simultaneous_turn(&mut self, actions: (&G::Action, &G::Action)) -> Result<MatchUndo<G>, IllegalMove<G::Action>> {
+        let undo = self.game.apply_simultaneous_turn(self.swap_pair(actions))
+            .map_err(|err| self.unseat_move(err))?;
+        let round_end = self.end_turn(&[(Player::P1, self.game.action_id(actions.0)), (Player::P2, self.game.action_id(actions.1))]);
+        Ok(MatchUndo {
+            undo,
+            players: vec![Player::P1, Player::P2],
+            round_end,
+        })
+    }
+
+    fn undo_turn(&mut self, undo: MatchUndo<G>) {
+        self.undo_end_turn(&undo.players, undo.round_end);
+        self.game.undo_turn(undo.undo);
+    }
+}
+
+impl<G: Game + Clone> fmt::Display for Match<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.get_reward().is_some() {
+            writeln!(f, "Finished after {} rounds, {} total: {}", self.rounds.len(), Player::P1, self.total_reward())?;
+        } else {
+            writeln!(f, "Round {} of {}, {} total: {}", self.rounds.len() + 1, self.config.rounds, Player::P1, self.total_reward())?;
+        }
+        if self.is_swapped(self.rounds.len()) {
+            writeln!(f, "Players have swapped seats this round")?;
+        }
+        self.game.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{MatrixGame, OneCardPoker, OneCardPokerAction, OneCardPokerRules};
+
+    const ROCK: usize = 0;
+    const PAPER: usize = 1;
+    const SCISSORS: usize = 2;
+
+    fn rps_match(config: MatchConfig) -> Match<MatrixGame> {
+        Match::new(MatrixGame::new_rock_paper_scissors, config)
+    }
+
+    #[test]
+    fn sums_rounds() {
+        let mut game = rps_match(MatchConfig { alternate: false, ..MatchConfig::default() });
+        game.take_simultaneous_turn((&ROCK, &SCISSORS)).unwrap();
+        game.take_simultaneous_turn((&ROCK, &PAPER)).unwrap();
+        assert_eq!(game.get_reward(), None);
+        game.take_simultaneous_turn((&PAPER, &PAPER)).unwrap();
+        assert_eq!(game.get_reward(), Some(0.0));
+        assert_eq!(game.get_utility_bounds(), (-3.0, 3.0));
+    }
+
+    #[test]
+    fn averages_rounds() {
+        let mut game = rps_match(MatchConfig { rounds: 2, reward: MatchReward::Average, ..MatchConfig::default() });
+        game.take_simultaneous_turn((&ROCK, &SCISSORS)).unwrap();
+        game.take_simultaneous_turn((&PAPER, &PAPER)).unwrap();
+        assert_eq!(game.get_reward(), Some(0.5));
+    }
+
+    #[test]
+    fn alternates_seats() {
+        let config = MatchConfig { rounds: 2, ..MatchConfig::default() };
+        let mut game = Match::new(|| MatrixGame::new(2, vec![1.0, 0.9, -0.7, 1.0]), config);
+        game.take_simultaneous_turn((&0, &0)).unwrap();
+        //P1 now plays the columns, so this is row 1 column 0
+        game.take_simultaneous_turn((&0, &1)).unwrap();
+        assert_eq!(game.get_reward(), Some(1.0 + 0.7));
+
+        //the dealer alternates too, and P2 deals the first hand
+        let mut game = Match::new(|| OneCardPoker::manual_with_rules(OneCardPokerRules::default(), (3, 5), Player::P2), MatchConfig::default());
+        assert_eq!(game.get_turn().0, Player::P1);
+        game.take_turn(Player::P1, &OneCardPokerAction::Call).unwrap();
+        game.take_turn(Player::P2, &OneCardPokerAction::Call).unwrap();
+        assert_eq!(game.get_turn().0, Player::P2);
+        assert!(game.take_turn(Player::P1, &OneCardPokerAction::Call).is_err());
+    }
+
+    #[test]
+    fn ends_early() {
+        //best of 3
+        let mut game = rps_match(MatchConfig { stop_when_decided: true, ..MatchConfig::default() });
+        game.take_simultaneous_turn((&ROCK, &SCISSORS)).unwrap();
+        assert_eq!(game.get_reward(), None);
+        game.take_simultaneous_turn((&SCISSORS, &PAPER)).unwrap();
+        assert_eq!(game.get_reward(), Some(2.0));
+
+        //P2 only has half a round left to lose
+        let mut game = rps_match(MatchConfig { rounds: 10, stack: Some(1.5), ..MatchConfig::default() });
+        game.take_simultaneous_turn((&ROCK, &ROCK)).unwrap();
+        assert_eq!(game.get_reward(), None);
+        game.take_simultaneous_turn((&ROCK, &SCISSORS)).unwrap();
+        assert_eq!(game.get_reward(), Some(1.0));
+    }
+
+    #[test]
+    fn visibility() {
+        let infosets = |visibility, p1_action| {
+            let mut game = rps_match(MatchConfig { visibility, ..MatchConfig::default() });
+            game.take_simultaneous_turn((p1_action, &ROCK)).unwrap();
+            (game.get_infoset(Player::P1).hash, game.get_infoset(Player::P2).hash)
+        };
+        //P2 can only tell whether they won paper or scissors if they see the reward
+        assert_eq!(infosets(RoundVisibility::Nothing, &PAPER).1, infosets(RoundVisibility::Nothing, &SCISSORS).1);
+        assert_ne!(infosets(RoundVisibility::Rewards, &PAPER).1, infosets(RoundVisibility::Rewards, &SCISSORS).1);
+        assert_ne!(infosets(RoundVisibility::Full, &PAPER).0, infosets(RoundVisibility::Full, &SCISSORS).0);
+    }
+
+    #[test]
+    fn undoes_rounds() {
+        let mut game = rps_match(MatchConfig { rounds: 2, ..MatchConfig::default() });
+        let start = (game.to_string(), game.get_infoset(Player::P1).hash);
+        let undo1 = game.apply_simultaneous_turn((&ROCK, &SCISSORS)).unwrap();
+        let middle = (game.to_string(), game.get_infoset(Player::P1).hash);
+        let undo2 = game.apply_simultaneous_turn((&ROCK, &SCISSORS)).unwrap();
+        assert_eq!(game.get_reward(), Some(2.0));
+
+        game.undo_turn(undo2);
+        assert_eq!((game.to_string(), game.get_infoset(Player::P1).hash), middle);
+        game.undo_turn(undo1);
+        assert_eq!((game.to_string(), game.get_infoset(Player::P1).hash), start);
+    }
+}
@@ -1,20 +1,70 @@
-use std::fmt::{Display, Formatter};
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::Path;
 
 use crate::game;
-use crate::game::{Player};
+use crate::game::{Player, IllegalMove, IllegalMoveReason, GameRecord, RecordedTurn, RecordGame, RecordError};
 
+/// Zero sum game where both players move at once, and P1's reward is looked up in a matrix
+///
+/// P1 picks the row and P2 picks the column, so the players can have different numbers of moves
 #[derive(Debug, Clone)]
 pub struct MatrixGame {
     moves: (Option<Move>, Option<Move>),
-    num_moves: usize,
+    /// Moves each player has, which are the rows and columns of the matrix
+    num_moves: (usize, usize),
     matrix: Vec<f32>,
 }
 
 pub type Move = usize;
 
+/// Why a payoff matrix couldn't be loaded
+#[derive(Debug)]
+pub enum PayoffError {
+    Io(io::Error),
+    /// The file has no payoffs
+    Empty,
+    /// An entry on the line isn't a finite number
+    BadValue { line: usize, value: String },
+    /// The line has a different number of entries than the first row
+    RowLength { line: usize, expected: usize, found: usize },
+}
+
+impl Display for PayoffError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PayoffError::Io(err) => write!(f, "can't read payoff matrix: {}", err),
+            PayoffError::Empty => write!(f, "payoff matrix has no rows"),
+            PayoffError::BadValue { line, value } => write!(f, "payoff '{}' on line {} isn't a finite number", value, line),
+            PayoffError::RowLength { line, expected, found } =>
+                write!(f, "line {} has {} payoffs, but the first row has {}", line, found, expected),
+        }
+    }
+}
+
+impl error::Error for PayoffError {}
+
+impl From<io::Error> for PayoffError {
+    fn from(err: io::Error) -> PayoffError {
+        PayoffError::Io(err)
+    }
+}
+
 impl MatrixGame {
+    /// Square game where both players have the same moves
+    ///
+    /// Panics unless the matrix has `num_moves * num_moves` entries
     pub fn new(num_moves: usize, matrix: Vec<f32>) -> MatrixGame {
-        if matrix.len() != num_moves * num_moves {
+        MatrixGame::new_rectangular((num_moves, num_moves), matrix)
+    }
+
+    /// Game where P1 has `num_moves.0` moves and P2 has `num_moves.1`, with the matrix given row by row
+    ///
+    /// Panics unless the matrix has an entry for every pair of moves
+    pub fn new_rectangular(num_moves: (usize, usize), matrix: Vec<f32>) -> MatrixGame {
+        if num_moves.0 == 0 || num_moves.1 == 0 || matrix.len() != num_moves.0 * num_moves.1 {
             panic!("Illegal matrix game");
         }
         MatrixGame {
@@ -24,6 +74,51 @@ impl MatrixGame {
         }
     }
 
+    /// Builds a game from a row of payoffs for each of P1's moves
+    ///
+    /// Returns None if there are no moves or the rows aren't all the same length
+    pub fn from_rows(rows: Vec<Vec<f32>>) -> Option<MatrixGame> {
+        let num_moves = (rows.len(), rows.first()?.len());
+        if num_moves.1 == 0 || rows.iter().any(|row| row.len() != num_moves.1) {
+            return None;
+        }
+        Some(MatrixGame::new_rectangular(num_moves, rows.concat()))
+    }
+
+    /// Parses P1's payoffs with a row on each line, separated by commas or whitespace
+    ///
+    /// Blank lines and lines starting with # are skipped
+    pub fn parse_payoffs(text: &str) -> Result<MatrixGame, PayoffError> {
+        let mut rows: Vec<Vec<f32>> = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values: Vec<&str> = if line.contains(',') {
+                line.split(',').map(|value| value.trim()).collect()
+            } else {
+                line.split_whitespace().collect()
+            };
+            let row = values.iter()
+                .map(|value| value.parse().ok().filter(|payoff: &f32| payoff.is_finite())
+                    .ok_or_else(|| PayoffError::BadValue { line: i + 1, value: String::from(*value) }))
+                .collect::<Result<Vec<f32>, _>>()?;
+            if let Some(first) = rows.first() {
+                if row.len() != first.len() {
+                    return Err(PayoffError::RowLength { line: i + 1, expected: first.len(), found: row.len() });
+                }
+            }
+            rows.push(row);
+        }
+        MatrixGame::from_rows(rows).ok_or(PayoffError::Empty)
+    }
+
+    /// Loads a payoff matrix file, in the format read by `parse_payoffs`
+    pub fn load_payoffs<P: AsRef<Path>>(path: P) -> Result<MatrixGame, PayoffError> {
+        MatrixGame::parse_payoffs(&fs::read_to_string(path)?)
+    }
+
     pub fn new_rock_paper_scissors() -> MatrixGame {
         MatrixGame::new(3, 
             vec![
@@ -39,20 +134,53 @@ impl game::Game for MatrixGame {
     type Action = Move;
 
     fn get_turn(&self) -> (Player, Vec<Move>) {
-        let moves = (0 .. self.num_moves).collect();
-        if self.moves.0.is_none() {
-            (Player::P1, moves)
-        } else {
-            (Player::P2, moves)
+        match self.get_simultaneous_turn() {
+            Some((moves, _)) => (Player::P1, moves),
+            None => (Player::P2, vec![]),
+        }
+    }
+
+    fn get_simultaneous_turn(&self) -> Option<(Vec<Move>, Vec<Move>)> {
+        match self.moves {
+            (None, None) => Some(((0 .. self.num_moves.0).collect(), (0 .. self.num_moves.1).collect())),
+            _ => None,
         }
     }
 
-    fn take_turn(&mut self, player: Player, action: &Move) {
-        let player_move = player.lens_mut(&mut self.moves);
-        if player_move.is_some() {
-            panic!("Tried to issue second move for player");
+    fn take_turn(&mut self, player: Player, action: &Move) -> Result<(), IllegalMove<Move>> {
+        let reason = if self.get_reward().is_some() {
+            IllegalMoveReason::GameOver
+        } else {
+            IllegalMoveReason::InvalidAction("both players move at once")
+        };
+        Err(IllegalMove::new(player, action, reason))
+    }
+
+    fn take_simultaneous_turn(&mut self, actions: (&Move, &Move)) -> Result<(), IllegalMove<Move>> {
+        if self.get_reward().is_some() {
+            return Err(IllegalMove::new(Player::P1, actions.0, IllegalMoveReason::GameOver));
+        }
+        for player in [Player::P1, Player::P2].iter() {
+            let action = player.lens(&actions);
+            if **action >= *player.lens(&self.num_moves) {
+                return Err(IllegalMove::new(*player, *action, IllegalMoveReason::InvalidAction("move is outside of the matrix")));
+            }
         }
-        *player_move = Some(*action);
+
+        self.moves = (Some(*actions.0), Some(*actions.1));
+        Ok(())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        self.num_moves.0.max(self.num_moves.1)
+    }
+
+    fn action_id(&self, action: &Move) -> usize {
+        *action
+    }
+
+    fn parse_action(&self, action: &str) -> Option<Move> {
+        action.parse().ok().filter(|&m| m < self.num_action_ids())
     }
 
     fn get_reward(&self) -> Option<f32> {
@@ -65,22 +193,96 @@ impl game::Game for MatrixGame {
             None => return None,
         };
 
-        Some(self.matrix[self.num_moves * p1_move + p2_move])
+        Some(self.matrix[self.num_moves.1 * p1_move + p2_move])
+    }
+
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        let min = self.matrix.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = self.matrix.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        (min, max)
     }
 
     fn get_infoset(&self, _player: Player) -> game::Infoset {
         //there is no visible state
         game::Infoset::new(0)
     }
+
+    fn get_private_observation(&self, _player: Player) -> game::Observation {
+        //nothing is private either, since neither player sees the other's move
+        game::Observation::new(0)
+    }
+}
+
+impl game::UndoGame for MatrixGame {
+    type Undo = ();
+
+    fn apply_turn(&mut self, player: Player, action: &Move) -> Result<(), IllegalMove<Move>> {
+        game::Game::take_turn(self, player, action)
+    }
+
+    fn apply_simultaneous_turn(&mut self, actions: (&Move, &Move)) -> Result<(), IllegalMove<Move>> {
+        game::Game::take_simultaneous_turn(self, actions)
+    }
+
+    fn undo_turn(&mut self, _undo: ()) {
+        //the only turn is the simultaneous one
+        self.moves = (None, None);
+    }
+}
+
+impl MatrixGame {
+    /// Payoffs as space separated values, row by row
+    pub fn matrix_string(&self) -> String {
+        self.matrix.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(" ")
+    }
+
+    /// Builds a game from each player's number of moves and the payoffs written by `matrix_string`
+    pub fn from_matrix_string(num_moves: (usize, usize), matrix: &str) -> Option<MatrixGame> {
+        let matrix: Vec<f32> = matrix.split_whitespace()
+            .map(|x| x.parse().ok().filter(|x: &f32| x.is_finite()))
+            .collect::<Option<_>>()?;
+        if num_moves.0 > 0 && num_moves.1 > 0 && matrix.len() == num_moves.0 * num_moves.1 {
+            Some(MatrixGame::new_rectangular(num_moves, matrix))
+        } else {
+            None
+        }
+    }
+}
+
+impl RecordGame for MatrixGame {
+    fn get_record(&self) -> GameRecord {
+        let mut record = GameRecord::new("matrix");
+        record.add_setup("moves", self.num_moves.0);
+        record.add_setup("p2_moves", self.num_moves.1);
+        record.add_setup("matrix", self.matrix_string());
+        for player in [Player::P1, Player::P2].iter() {
+            if let Some(action) = player.lens(&self.moves) {
+                record.turns.push(RecordedTurn::new(*player, action));
+            }
+        }
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<MatrixGame, RecordError> {
+        record.check_game("matrix")?;
+        let matrix: String = record.get_setup("matrix")?;
+        let num_moves = record.get_setup("moves")?;
+        //older records only have square matrices
+        let num_moves = (num_moves, record.get_setup_or("p2_moves", num_moves)?);
+        let mut game = MatrixGame::from_matrix_string(num_moves, &matrix)
+            .ok_or(RecordError::BadSetup { key: "matrix", value: matrix })?;
+        record.replay(&mut game)?;
+        Ok(game)
+    }
 }
 
 impl Display for MatrixGame {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         //nothing to see here
         writeln!(f, "Matrix:")?;
-        for i in 0..self.num_moves {
-            for j in 0..self.num_moves {
-                write!(f, "{}\t", self.matrix[self.num_moves * i + j])?;
+        for i in 0..self.num_moves.0 {
+            for j in 0..self.num_moves.1 {
+                write!(f, "{}\t", self.matrix[self.num_moves.1 * i + j])?;
             }
             writeln!(f)?;
         }
@@ -100,8 +302,7 @@ mod tests {
     #[test]
     fn rps_p1_wins() {
         let mut game = MatrixGame::new_rock_paper_scissors();
-        game.take_turn(Player::P1, &SCISSORS);
-        game.take_turn(Player::P2, &PAPER);
+        game.take_simultaneous_turn((&SCISSORS, &PAPER)).unwrap();
         let reward = game.get_reward();
         assert_eq!(reward, Some(1.0));
     }
@@ -109,8 +310,7 @@ mod tests {
     #[test]
     fn rps_p2_wins() {
         let mut game = MatrixGame::new_rock_paper_scissors();
-        game.take_turn(Player::P1, &SCISSORS);
-        game.take_turn(Player::P2, &ROCK);
+        game.take_simultaneous_turn((&SCISSORS, &ROCK)).unwrap();
         let reward = game.get_reward();
         assert_eq!(reward, Some(-1.0));
     }
@@ -118,10 +318,55 @@ mod tests {
     #[test]
     fn rps_tie() {
         let mut game = MatrixGame::new_rock_paper_scissors();
-        game.take_turn(Player::P1, &SCISSORS);
-        game.take_turn(Player::P2, &SCISSORS);
+        game.take_simultaneous_turn((&SCISSORS, &SCISSORS)).unwrap();
         let reward = game.get_reward();
         assert_eq!(reward, Some(0.0));
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn moves_are_simultaneous() {
+        let mut game = MatrixGame::new_rock_paper_scissors();
+        assert_eq!(game.get_simultaneous_turn(), Some((vec![ROCK, PAPER, SCISSORS], vec![ROCK, PAPER, SCISSORS])));
+        assert!(game.take_turn(Player::P1, &ROCK).is_err());
+        assert!(game.take_simultaneous_turn((&ROCK, &3)).is_err());
+
+        game.take_simultaneous_turn((&ROCK, &ROCK)).unwrap();
+        assert_eq!(game.get_simultaneous_turn(), None);
+        assert!(game.take_simultaneous_turn((&ROCK, &ROCK)).is_err());
+    }
+
+    #[test]
+    fn utility_bounds() {
+        assert_eq!(MatrixGame::new_rock_paper_scissors().get_utility_bounds(), (-1.0, 1.0));
+        assert_eq!(MatrixGame::new(2, vec![3.0, 0.5, -2.0, 1.0]).get_utility_bounds(), (-2.0, 3.0));
+    }
+    #[test]
+    fn plays_rectangular_matrices() {
+        let mut game = MatrixGame::new_rectangular((2, 3), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(game.get_simultaneous_turn(), Some((vec![0, 1], vec![0, 1, 2])));
+        assert_eq!(game.num_action_ids(), 3);
+        assert!(game.take_simultaneous_turn((&2, &0)).is_err());
+        game.take_simultaneous_turn((&1, &2)).unwrap();
+        assert_eq!(game.get_reward(), Some(6.0));
+    }
+
+    #[test]
+    fn parses_payoff_files() {
+        let game = MatrixGame::parse_payoffs("# P1's payoffs\n1, -2.5, 0\n\n-1,3,2\n").unwrap();
+        assert_eq!(game.num_moves, (2, 3));
+        assert_eq!(game.matrix, vec![1.0, -2.5, 0.0, -1.0, 3.0, 2.0]);
+
+        let game = MatrixGame::parse_payoffs("0 1\n  -1\t0\n2 2").unwrap();
+        assert_eq!(game.num_moves, (3, 2));
+
+        assert!(matches!(MatrixGame::parse_payoffs("1 2\n3"), Err(PayoffError::RowLength { line: 2, expected: 2, found: 1 })));
+        assert!(matches!(MatrixGame::parse_payoffs("1,two"), Err(PayoffError::BadValue { line: 1, .. })));
+        assert!(matches!(MatrixGame::parse_payoffs("1,,2"), Err(PayoffError::BadValue { line: 1, .. })));
+        //non-finite payoffs would make the utility bounds useless for scaling rewards
+        assert!(matches!(MatrixGame::parse_payoffs("1 2\nNaN 0"), Err(PayoffError::BadValue { line: 2, .. })));
+        assert!(matches!(MatrixGame::parse_payoffs("inf,-1"), Err(PayoffError::BadValue { line: 1, .. })));
+        assert!(MatrixGame::from_matrix_string((1, 2), "1 -inf").is_none());
+        assert!(matches!(MatrixGame::parse_payoffs("# nothing\n"), Err(PayoffError::Empty)));
+        assert!(matches!(MatrixGame::load_payoffs("/nonexistent/payoffs.csv"), Err(PayoffError::Io(_))));
+    }
+}
@@ -1,14 +1,43 @@
 mod game;
 mod tictactoe;
+mod phantom_tictactoe;
 mod ocp;
+mod kuhn;
+mod leduc;
+mod liars_dice;
+mod goofspiel;
+mod blotto;
 mod skulls;
 mod matrix_game;
 mod double_matrix_game;
+mod match_game;
+mod clone_undo;
+mod abstraction;
+mod sequential;
+mod registry;
+mod record;
+#[cfg(test)]
+mod conformance;
 
-pub use game::{Game, Infoset, Player};
+pub use game::{Game, UndoGame, TensorGame, IllegalMove, IllegalMoveReason, Infoset, Observation, Player, PublicState};
+pub use clone_undo::CloneUndo;
+pub use sequential::Sequential;
+pub use abstraction::{Abstracted, InfosetAbstraction, ActionAbstracted, ActionAbstraction, pseudo_harmonic};
+pub use registry::{GameVisitor, build_game, restore_game, describe_games};
+pub use record::{GameRecord, RecordedTurn, RecordGame, RecordError};
 pub use tictactoe::TicTacToe;
+pub use phantom_tictactoe::PhantomTicTacToe;
 pub use matrix_game::MatrixGame;
 pub use ocp::OneCardPoker;
 pub use ocp::Action as OneCardPokerAction;
+pub use ocp::CardBuckets;
+pub use ocp::OneCardPokerRules;
+pub use kuhn::KuhnPoker;
+pub use leduc::LeducPoker;
+pub use liars_dice::LiarsDice;
+pub use goofspiel::Goofspiel;
+pub use blotto::Blotto;
 pub use skulls::Skulls as Skulls;
-pub use double_matrix_game::DoubleMatrixGame;
\ No newline at end of file
+pub use skulls::{SkullsRules, RoundSummary, BidSizes};
+pub use double_matrix_game::DoubleMatrixGame;
+pub use match_game::{Match, MatchConfig, MatchReward, RoundVisibility};
\ No newline at end of file
@@ -3,64 +3,83 @@
 use rand::Rng;
 use std::fmt::{Display, Formatter};
 
-use crate::game::{Game, Player, Infoset};
+use crate::game::{Game, UndoGame, TensorGame, IllegalMove, IllegalMoveReason, Player, Infoset, Observation};
+use crate::game::{GameRecord, RecordedTurn, RecordGame, RecordError, InfosetAbstraction};
+
+/// Most bets and raises there can be in a hand
+pub const MAX_RAISES: u32 = 5;
+
+/// Deck, antes and bets for one card poker
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub struct OneCardPokerRules {
+    /// Cards in the deck, where higher cards win
+    pub num_cards: u32,
+    /// What each player puts in the pot before the deal
+    pub ante: u32,
+    /// Amounts a player can bet or raise by, each a different action
+    pub bet_sizes: Vec<u32>,
+    /// Bets and raises in the hand, including the first bet
+    pub max_raises: u32,
+}
+
+impl Default for OneCardPokerRules {
+    /// 13 cards with an ante of 1 and a single bet of 1
+    fn default() -> OneCardPokerRules {
+        OneCardPokerRules {
+            num_cards: 13,
+            ante: 1,
+            bet_sizes: vec![1],
+            max_raises: 1,
+        }
+    }
+}
 
-const NUM_CARDS: u32 = 13;
+impl OneCardPokerRules {
+    /// Most a player can put in the pot, which is the ante and the biggest bet every time
+    fn max_pot(&self) -> u32 {
+        let max_bet = self.bet_sizes.iter().max().copied().unwrap_or(0);
+        self.ante + self.max_raises * max_bet
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Hash)]
 pub enum Action {
     Fold,
+    /// Checks when there's no bet to call
     Call,
-    Bet,
+    /// Bets when there's no bet to raise
+    Bet { amount: u32 },
 }
 
-//these comments assume the dealer is p2
-//but it doesn't really matter
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum PokerState {
-    //just dealt, p1 can check or bet
-    P1Deal,
-    //p1 checked, p2 can check or bet
-    P2Check,
-    //p2 bet, p1 can call or fold
-    P1Raise,
-
-    //p1 bet on the first round, p2 can call or fold
-    P2Bet,
-
-    FoldEnd,
+    Betting,
+    /// The player folded
+    FoldEnd(Player),
     ShowdownEnd,
 }
 
-fn state_to_actions(state: PokerState) -> Vec<Action> {
-    match state {
-        PokerState::P1Deal => vec![Action::Call, Action::Bet],
-        PokerState::P2Check => vec![Action::Call, Action::Bet],
-        PokerState::P1Raise => vec![Action::Fold, Action::Call],
-        PokerState::P2Bet => vec![Action::Fold, Action::Call],
-        _ => vec![],
-    }
-}
-
-#[derive(Clone)]
+/// One card poker, where the dealer acts second
+///
+/// Each player antes and gets one card, then there's a single round of betting before the higher card wins
+#[derive(Clone, Debug)]
 pub struct OneCardPoker {
+    rules: OneCardPokerRules,
     dealer: Player,
     pot: (u32, u32),
     hands: (u32, u32),
     history: Vec<(Player, Action)>,
+    raises: u32,
     state: PokerState,
-    current_player: Player,
-    current_actions: Vec<Action>,
 }
 
 impl OneCardPoker {
-    pub fn new() -> OneCardPoker {
+    /// Deals two different cards and picks a random dealer
+    pub fn with_rules(rules: OneCardPokerRules) -> OneCardPoker {
         let mut rng = rand::thread_rng();
-        let hand1: u32 = rng.gen_range(0, NUM_CARDS);
-        let mut hand2: u32 = rng.gen_range(0, NUM_CARDS);
-        if hand2 >= hand1 {
-            hand2 += 1;
-        }
+        //two different cards from the deck, with every pair equally likely
+        let cards = rand::seq::index::sample(&mut rng, rules.num_cards as usize, 2);
+        let (hand1, hand2) = (cards.index(0) as u32, cards.index(1) as u32);
 
         let dealer = if rng.gen()  {
             Player::P1
@@ -68,20 +87,56 @@ impl OneCardPoker {
             Player::P2
         };
 
-        OneCardPoker::manual_new((hand1, hand2), dealer)
+        OneCardPoker::manual_with_rules(rules, (hand1, hand2), dealer)
     }
 
-    pub fn manual_new(hands: (u32, u32), dealer: Player) -> OneCardPoker {
+    /// Every possible deal, each with its probability
+    pub fn all_deals(rules: &OneCardPokerRules) -> Vec<(OneCardPoker, f32)> {
+        let num_cards = rules.num_cards;
+        let num_deals = num_cards * (num_cards - 1) * 2;
+        let mut deals = vec![];
+        for hand1 in 0..num_cards {
+            for hand2 in (0..num_cards).filter(|&hand2| hand2 != hand1) {
+                for dealer in [Player::P1, Player::P2].iter() {
+                    deals.push((OneCardPoker::manual_with_rules(rules.clone(), (hand1, hand2), *dealer), 1.0 / num_deals as f32));
+                }
+            }
+        }
+        deals
+    }
+
+    pub fn manual_with_rules(rules: OneCardPokerRules, hands: (u32, u32), dealer: Player) -> OneCardPoker {
         OneCardPoker {
             dealer,
             hands,
-            pot: (1, 1),
+            pot: (rules.ante, rules.ante),
             history: vec![],
-            state: PokerState::P1Deal,
-            current_player: dealer.other(),
-            current_actions: state_to_actions(PokerState::P1Deal),
+            raises: 0,
+            state: PokerState::Betting,
+            rules,
         }
+    }
 
+    fn current_player(&self) -> Player {
+        match self.history.last() {
+            Some((player, _)) => player.other(),
+            None => self.dealer.other(),
+        }
+    }
+
+    fn current_actions(&self) -> Vec<Action> {
+        if self.state != PokerState::Betting {
+            return vec![];
+        }
+        let mut actions = if self.pot.0 == self.pot.1 {
+            vec![Action::Call]
+        } else {
+            vec![Action::Fold, Action::Call]
+        };
+        if self.raises < self.rules.max_raises {
+            actions.extend(self.rules.bet_sizes.iter().map(|&amount| Action::Bet { amount }));
+        }
+        actions
     }
 }
 
@@ -89,74 +144,243 @@ impl Game for OneCardPoker {
     type Action = Action;
 
     fn get_turn(&self) -> (Player, Vec<Action>) {
-        (self.current_player, self.current_actions.clone())
+        (self.current_player(), self.current_actions())
     }
 
-    fn take_turn(&mut self, player: Player, action: &Action) {
-        if player != self.current_player {
-            panic!("Tried to take turn with wrong player");
-        }
-        if !self.current_actions.contains(action) {
-            panic!("Tried to take turn with illegal action");
+    fn take_turn(&mut self, player: Player, action: &Action) -> Result<(), IllegalMove<Action>> {
+        let actions = self.current_actions();
+        let reason = if actions.is_empty() {
+            Some(IllegalMoveReason::GameOver)
+        } else if player != self.current_player() {
+            Some(IllegalMoveReason::WrongPlayer { expected: self.current_player() })
+        } else if !actions.contains(action) {
+            Some(IllegalMoveReason::InvalidAction("action is not allowed at this point in the betting"))
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(IllegalMove::new(player, action, reason));
         }
 
-        let p2 = self.dealer;
-        let p1 = p2.other();
-
-        let (current_player, state) = match self.state {
-            PokerState::P1Deal if *action == Action::Call => (p2, PokerState::P2Check),
-            PokerState::P1Deal if *action == Action::Bet => {
-                *p1.lens_mut(&mut self.pot) += 1;
-                (p2, PokerState::P2Bet)
-            },
-            PokerState::P2Check if *action == Action::Call => (p1, PokerState::ShowdownEnd),
-            PokerState::P2Check if *action == Action::Bet => {
-                *p2.lens_mut(&mut self.pot) += 1;
-                (p1, PokerState::P1Raise)
-            }
-            PokerState::P2Bet if *action == Action::Fold => (p1, PokerState::FoldEnd),
-            PokerState::P2Bet if *action == Action::Call => {
-                *p2.lens_mut(&mut self.pot) += 1;
-                (p1, PokerState::ShowdownEnd)
-            },
-            PokerState::P1Raise if *action == Action::Fold => (p2, PokerState::FoldEnd),
-            PokerState::P1Raise if *action == Action::Call => {
-                *p1.lens_mut(&mut self.pot) += 1;
-                (p2, PokerState::ShowdownEnd)
-            },
-            _ => panic!("Illegal action for the current state"),
-        };
-        self.current_player = current_player;
-        self.state = state;
-        self.current_actions = state_to_actions(state);
+        let other_pot = *player.other().lens(&self.pot);
+        let is_first_action = self.history.is_empty();
         self.history.push((player, *action));
+        match action {
+            Action::Fold => self.state = PokerState::FoldEnd(player),
+            //checking first leaves the other player to act
+            Action::Call if is_first_action => (),
+            Action::Call => {
+                *player.lens_mut(&mut self.pot) = other_pot;
+                self.state = PokerState::ShowdownEnd;
+            }
+            Action::Bet { amount } => {
+                *player.lens_mut(&mut self.pot) = other_pot + amount;
+                self.raises += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        2 + self.rules.bet_sizes.len()
+    }
+
+    fn action_id(&self, action: &Action) -> usize {
+        match action {
+            Action::Fold => 0,
+            Action::Call => 1,
+            Action::Bet { amount } => 2 + self.rules.bet_sizes.iter().position(|size| size == amount)
+                .expect("Failed to find the bet size in the rules"),
+        }
+    }
+
+    fn parse_action(&self, action: &str) -> Option<Action> {
+        match action {
+            "Fold" => Some(Action::Fold),
+            "Call" => Some(Action::Call),
+            //older records only had a single bet size
+            "Bet" => self.rules.bet_sizes.first().map(|&amount| Action::Bet { amount }),
+            _ => action.strip_prefix("Bet { amount: ")?.strip_suffix(" }")?.parse().ok()
+                .filter(|amount| self.rules.bet_sizes.contains(amount))
+                .map(|amount| Action::Bet { amount }),
+        }
     }
 
     fn get_reward(&self) -> Option<f32> {
         //the reward is the other player's contribution to the pot
-        //divide by 2 to put the rewards between -1 and 1
+        //divide by 2 to put the default game's rewards between -1 and 1
         match self.state {
-            PokerState::FoldEnd if self.current_player == Player::P1 => Some(self.pot.1 as f32 / 2.0),
-            PokerState::FoldEnd if self.current_player == Player::P2 => Some(-1.0 * self.pot.0 as f32 / 2.0),
+            PokerState::FoldEnd(Player::P2) => Some(self.pot.1 as f32 / 2.0),
+            PokerState::FoldEnd(Player::P1) => Some(-(self.pot.0 as f32) / 2.0),
             PokerState::ShowdownEnd if self.hands.0 > self.hands.1 => Some(self.pot.1 as f32 / 2.0),
-            PokerState::ShowdownEnd => Some(-1.0 * self.pot.0 as f32 / 2.0),
-            _ => None,
+            PokerState::ShowdownEnd => Some(-(self.pot.0 as f32) / 2.0),
+            PokerState::Betting => None,
         }
     }
 
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        let most = self.rules.max_pot() as f32 / 2.0;
+        (-most, most)
+    }
+
     fn get_infoset(&self, player: Player) -> Infoset {
         //the player's hand is only known to them
         let hand = *player.lens(&self.hands);
+        Infoset::new((&self.rules, hand, self.get_bet_history(player)))
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        self.history.iter().map(Observation::new).collect()
+    }
 
-        let bet_history: Vec<(Player, Action)> = if player == Player::P1 {
+    fn get_private_observation(&self, player: Player) -> Observation {
+        Observation::new(*player.lens(&self.hands))
+    }
+}
+
+impl OneCardPoker {
+    /// Betting so far, from the player's perspective
+    fn get_bet_history(&self, player: Player) -> Vec<(Player, Action)> {
+        if player == Player::P1 {
             self.history.clone()
         } else {
             //swap so always from P1's perspective
             self.history.iter().map(|(p, a)| {
                 (p.other(), *a)
             }).collect()
+        }
+    }
+}
+
+/// Abstraction that groups neighbouring cards into buckets, keeping the betting history
+///
+/// With as many buckets as cards, this is the same as the real game
+#[derive(Clone, Copy, Debug)]
+pub struct CardBuckets {
+    pub num_buckets: u32,
+}
+
+impl CardBuckets {
+    pub fn get_bucket(&self, card: u32, num_cards: u32) -> u32 {
+        card * self.num_buckets / num_cards
+    }
+}
+
+impl InfosetAbstraction<OneCardPoker> for CardBuckets {
+    fn get_abstract_infoset(&self, game: &OneCardPoker, player: Player) -> Infoset {
+        let bucket = self.get_bucket(*player.lens(&game.hands), game.rules.num_cards);
+        Infoset::new((&game.rules, bucket, game.get_bet_history(player)))
+    }
+}
+
+pub struct Undo {
+    pot: (u32, u32),
+    raises: u32,
+    state: PokerState,
+}
+
+impl UndoGame for OneCardPoker {
+    type Undo = Undo;
+
+    fn apply_turn(&mut self, player: Player, action: &Action) -> Result<Undo, IllegalMove<Action>> {
+        let undo = Undo {
+            pot: self.pot,
+            raises: self.raises,
+            state: self.state,
         };
-        Infoset::new((hand, bet_history))
+        self.take_turn(player, action)?;
+        Ok(undo)
+    }
+
+    fn undo_turn(&mut self, undo: Undo) {
+        self.pot = undo.pot;
+        self.raises = undo.raises;
+        self.state = undo.state;
+        self.history.pop();
+    }
+}
+
+impl TensorGame for OneCardPoker {
+    fn tensor_size(&self) -> usize {
+        //one-hot hand, whether the player acts first or second, then a one-hot action for each possible turn,
+        //where the most turns is a check followed by every raise and a call
+        let max_actions = self.rules.max_raises as usize + 2;
+        self.rules.num_cards as usize + 2 + max_actions * self.num_action_ids()
+    }
+
+    fn get_infoset_tensor(&self, player: Player) -> Vec<f32> {
+        let mut tensor = vec![0.0; self.tensor_size()];
+        tensor[*player.lens(&self.hands) as usize] = 1.0;
+
+        //the dealer acts second
+        let offset = self.rules.num_cards as usize;
+        if player == self.dealer {
+            tensor[offset + 1] = 1.0;
+        } else {
+            tensor[offset] = 1.0;
+        }
+
+        let offset = offset + 2;
+        let num_action_ids = self.num_action_ids();
+        for (i, (_, action)) in self.history.iter().enumerate() {
+            tensor[offset + num_action_ids * i + self.action_id(action)] = 1.0;
+        }
+        tensor
+    }
+}
+
+impl RecordGame for OneCardPoker {
+    fn get_record(&self) -> GameRecord {
+        let mut record = GameRecord::new("ocp");
+        record.add_setup("dealer", self.dealer);
+        record.add_setup("p1_card", self.hands.0);
+        record.add_setup("p2_card", self.hands.1);
+        record.add_setup("cards", self.rules.num_cards);
+        record.add_setup("ante", self.rules.ante);
+        record.add_setup("bets", self.rules.bet_sizes.iter().map(|size| size.to_string()).collect::<Vec<String>>().join("/"));
+        record.add_setup("raises", self.rules.max_raises);
+        record.turns = self.history.iter()
+            .map(|(player, action)| RecordedTurn::new(*player, action))
+            .collect();
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<OneCardPoker, RecordError> {
+        record.check_game("ocp")?;
+        let dealer = record.get_setup("dealer")?;
+        let defaults = OneCardPokerRules::default();
+        let num_cards: u32 = record.get_setup_or("cards", defaults.num_cards)?;
+        if num_cards < 2 {
+            return Err(RecordError::BadSetup { key: "cards", value: num_cards.to_string() });
+        }
+        let bets_string = record.get_setup_or("bets", String::from("1"))?;
+        let bet_sizes: Vec<u32> = bets_string.split('/')
+            .map(|size| size.parse().ok().filter(|&size| size > 0))
+            .collect::<Option<_>>()
+            .filter(|sizes: &Vec<u32>| (1..sizes.len()).all(|i| !sizes[..i].contains(&sizes[i])))
+            .ok_or_else(|| RecordError::BadSetup { key: "bets", value: bets_string.clone() })?;
+        let max_raises = record.get_setup_or("raises", defaults.max_raises)?;
+        if max_raises > MAX_RAISES {
+            return Err(RecordError::BadSetup { key: "raises", value: max_raises.to_string() });
+        }
+        let rules = OneCardPokerRules {
+            num_cards,
+            ante: record.get_setup_or("ante", defaults.ante)?,
+            bet_sizes,
+            max_raises,
+        };
+
+        let hands: (u32, u32) = (record.get_setup("p1_card")?, record.get_setup("p2_card")?);
+        if hands.0 >= num_cards {
+            return Err(RecordError::BadSetup { key: "p1_card", value: hands.0.to_string() });
+        }
+        if hands.1 >= num_cards || hands.1 == hands.0 {
+            return Err(RecordError::BadSetup { key: "p2_card", value: hands.1.to_string() });
+        }
+
+        let mut game = OneCardPoker::manual_with_rules(rules, hands, dealer);
+        record.replay(&mut game)?;
+        Ok(game)
     }
 }
 
@@ -179,79 +403,215 @@ impl Display for Action {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
     use super::*;
     use crate::game::*;
 
+    /// Game with the default rules
+    fn manual_new(hands: (u32, u32), dealer: Player) -> OneCardPoker {
+        OneCardPoker::manual_with_rules(OneCardPokerRules::default(), hands, dealer)
+    }
+
     #[test]
     fn early_showdown() {
-        let mut game = OneCardPoker::manual_new((3,5), Player::P1);
+        let mut game = manual_new((3,5), Player::P1);
 
-        game.take_turn(Player::P2, &Action::Call);
+        game.take_turn(Player::P2, &Action::Call).unwrap();
 
         let infoset = game.get_infoset(Player::P2);
         let infoset = game.get_infoset(Player::P1);
 
-        game.take_turn(Player::P1, &Action::Call);
+        game.take_turn(Player::P1, &Action::Call).unwrap();
         let reward = game.get_reward();
         assert_eq!(reward, Some(-0.5));
     }
 
     #[test]
     fn early_p2_fold() {
-        let mut game = OneCardPoker::manual_new((3,5), Player::P2);
+        let mut game = manual_new((3,5), Player::P2);
 
-        game.take_turn(Player::P1, &Action::Bet);
+        game.take_turn(Player::P1, &Action::Bet { amount: 1 }).unwrap();
 
         let infoset = game.get_infoset(Player::P2);
         let infoset = game.get_infoset(Player::P1);
 
-        game.take_turn(Player::P2, &Action::Fold);
+        game.take_turn(Player::P2, &Action::Fold).unwrap();
         let reward = game.get_reward();
         assert_eq!(reward, Some(0.5));
     }
 
     #[test]
     fn late_p2_fold() {
-        let mut game = OneCardPoker::manual_new((3,5), Player::P1);
+        let mut game = manual_new((3,5), Player::P1);
 
-        game.take_turn(Player::P2, &Action::Call);
-        game.take_turn(Player::P1, &Action::Bet);
+        game.take_turn(Player::P2, &Action::Call).unwrap();
+        game.take_turn(Player::P1, &Action::Bet { amount: 1 }).unwrap();
 
         let infoset = game.get_infoset(Player::P2);
         let infoset = game.get_infoset(Player::P1);
 
-        game.take_turn(Player::P2, &Action::Fold);
+        game.take_turn(Player::P2, &Action::Fold).unwrap();
         let reward = game.get_reward();
         assert_eq!(reward, Some(0.5));
     }
 
     #[test]
     fn late_p1_fold() {
-        let mut game = OneCardPoker::manual_new((3,5), Player::P2);
+        let mut game = manual_new((3,5), Player::P2);
 
-        game.take_turn(Player::P1, &Action::Call);
-        game.take_turn(Player::P2, &Action::Bet);
+        game.take_turn(Player::P1, &Action::Call).unwrap();
+        game.take_turn(Player::P2, &Action::Bet { amount: 1 }).unwrap();
 
         let infoset = game.get_infoset(Player::P1);
         let infoset = game.get_infoset(Player::P2);
 
-        game.take_turn(Player::P1, &Action::Fold);
+        game.take_turn(Player::P1, &Action::Fold).unwrap();
         let reward = game.get_reward();
         assert_eq!(reward, Some(-0.5));
     }
 
     #[test]
     fn late_showdown() {
-        let mut game = OneCardPoker::manual_new((5,3), Player::P1);
+        let mut game = manual_new((5,3), Player::P1);
 
-        game.take_turn(Player::P2, &Action::Call);
-        game.take_turn(Player::P1, &Action::Bet);
+        game.take_turn(Player::P2, &Action::Call).unwrap();
+        game.take_turn(Player::P1, &Action::Bet { amount: 1 }).unwrap();
 
         let infoset = game.get_infoset(Player::P2);
         let infoset = game.get_infoset(Player::P1);
 
-        game.take_turn(Player::P2, &Action::Call);
+        game.take_turn(Player::P2, &Action::Call).unwrap();
         let reward = game.get_reward();
         assert_eq!(reward, Some(1.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rejects_illegal_turns() {
+        let mut game = manual_new((3,5), Player::P1);
+
+        let err = game.take_turn(Player::P1, &Action::Bet { amount: 1 }).unwrap_err();
+        assert_eq!(err.reason, IllegalMoveReason::WrongPlayer { expected: Player::P2 });
+        let err = game.take_turn(Player::P2, &Action::Fold).unwrap_err();
+        assert_eq!(err.action, Action::Fold);
+
+        game.take_turn(Player::P2, &Action::Bet { amount: 1 }).unwrap();
+        game.take_turn(Player::P1, &Action::Fold).unwrap();
+        let err = game.take_turn(Player::P2, &Action::Call).unwrap_err();
+        assert_eq!(err.reason, IllegalMoveReason::GameOver);
+        assert_eq!(game.history.len(), 2);
+    }
+
+    #[test]
+    fn encodes_infoset() {
+        let mut game = manual_new((5,3), Player::P1);
+        game.take_turn(Player::P2, &Action::Call).unwrap();
+        game.take_turn(Player::P1, &Action::Bet { amount: 1 }).unwrap();
+
+        let tensor = game.get_infoset_tensor(Player::P2);
+        assert_eq!(tensor.len(), game.tensor_size());
+        assert_eq!(tensor.iter().sum::<f32>(), 4.0);
+        assert_eq!(tensor[3], 1.0);
+        //P2 acts first as P1 is the dealer
+        assert_eq!(tensor[13], 1.0);
+        assert_eq!(tensor[15 + 1], 1.0);
+        assert_eq!(tensor[15 + 3 + 2], 1.0);
+
+        assert_eq!(game.get_legal_action_mask(), vec![true, true, false]);
+    }
+
+    #[test]
+    fn hands_are_private() {
+        let mut game1 = manual_new((5,3), Player::P1);
+        game1.take_turn(Player::P2, &Action::Bet { amount: 1 }).unwrap();
+        let mut game2 = manual_new((5,8), Player::P1);
+        game2.take_turn(Player::P2, &Action::Bet { amount: 1 }).unwrap();
+
+        assert_eq!(game1.get_public_state(), game2.get_public_state());
+        assert_eq!(game1.get_private_observation(Player::P1), game2.get_private_observation(Player::P1));
+        assert_ne!(game1.get_private_observation(Player::P2), game2.get_private_observation(Player::P2));
+        assert_eq!(game1.get_infoset(Player::P1).hash, game2.get_infoset(Player::P1).hash);
+    }
+
+    #[test]
+    fn buckets_cards() {
+        let buckets = CardBuckets { num_buckets: 4 };
+        assert_eq!((0..13).map(|card| buckets.get_bucket(card, 13)).collect::<Vec<u32>>(),
+            vec![0, 0, 0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3]);
+
+        let game1 = manual_new((1, 11), Player::P1);
+        let game2 = manual_new((2, 12), Player::P1);
+        assert_ne!(game1.get_infoset(Player::P2).hash, game2.get_infoset(Player::P2).hash);
+        let game1 = Abstracted::new(game1, buckets);
+        let game2 = Abstracted::new(game2, buckets);
+        assert_eq!(game1.get_infoset(Player::P2).hash, game2.get_infoset(Player::P2).hash);
+    }
+
+    #[test]
+    fn deals_cards_in_the_deck() {
+        //the second card used to be drawn from the whole deck and shifted up past the first, so it could be one past the top
+        let rules = OneCardPokerRules { num_cards: 3, ..OneCardPokerRules::default() };
+        let mut seen = HashSet::new();
+        for _ in 0..300 {
+            let game = OneCardPoker::with_rules(rules.clone());
+            assert!(game.hands.0 < 3 && game.hands.1 < 3 && game.hands.0 != game.hands.1, "Dealt {:?}", game.hands);
+            seen.insert(game.hands);
+        }
+        assert_eq!(seen.len(), 6);
+    }
+
+    #[test]
+    fn raises_with_bet_sizes() {
+        let rules = OneCardPokerRules { num_cards: 5, ante: 2, bet_sizes: vec![1, 3], max_raises: 2 };
+        let mut game = OneCardPoker::manual_with_rules(rules, (4, 2), Player::P2);
+        assert_eq!(game.get_utility_bounds(), (-4.0, 4.0));
+        assert_eq!(game.get_turn(), (Player::P1, vec![Action::Call, Action::Bet { amount: 1 }, Action::Bet { amount: 3 }]));
+
+        game.take_turn(Player::P1, &Action::Bet { amount: 1 }).unwrap();
+        game.take_turn(Player::P2, &Action::Bet { amount: 3 }).unwrap();
+        assert_eq!(game.pot, (3, 6));
+        //the raise cap is reached, so P1 can only call or fold
+        assert_eq!(game.get_turn(), (Player::P1, vec![Action::Fold, Action::Call]));
+        assert_eq!(game.get_legal_action_mask(), vec![true, true, false, false]);
+
+        game.take_turn(Player::P1, &Action::Call).unwrap();
+        assert_eq!(game.get_reward(), Some(3.0));
+    }
+
+    #[test]
+    fn parses_bet_sizes() {
+        let rules = OneCardPokerRules { bet_sizes: vec![2, 4], ..OneCardPokerRules::default() };
+        let game = OneCardPoker::manual_with_rules(rules, (3, 5), Player::P1);
+        let bet = Action::Bet { amount: 4 };
+        assert_eq!(game.parse_action(&bet.to_string()), Some(bet));
+        assert_eq!(game.action_id(&bet), 3);
+        //records from before there were bet sizes use the first size
+        assert_eq!(game.parse_action("Bet"), Some(Action::Bet { amount: 2 }));
+        assert_eq!(game.parse_action("Bet { amount: 3 }"), None);
+    }
+
+    #[test]
+    fn rejects_bad_rules_in_records() {
+        let record_with = |bets: &str, raises: u32| {
+            let mut record = GameRecord::new("ocp");
+            record.add_setup("dealer", Player::P1);
+            record.add_setup("p1_card", 0);
+            record.add_setup("p2_card", 1);
+            record.add_setup("bets", bets);
+            record.add_setup("raises", raises);
+            record
+        };
+        assert!(OneCardPoker::from_record(&record_with("1/2", MAX_RAISES)).is_ok());
+        assert_eq!(OneCardPoker::from_record(&record_with("1/2/1", 1)).unwrap_err(),
+            RecordError::BadSetup { key: "bets", value: String::from("1/2/1") });
+        assert_eq!(OneCardPoker::from_record(&record_with("1", MAX_RAISES + 1)).unwrap_err(),
+            RecordError::BadSetup { key: "raises", value: String::from("6") });
+    }
+
+    #[test]
+    fn infosets_include_rules() {
+        let rules = OneCardPokerRules { ante: 2, ..OneCardPokerRules::default() };
+        let game1 = manual_new((3, 5), Player::P1);
+        let game2 = OneCardPoker::manual_with_rules(rules, (3, 5), Player::P1);
+        assert_ne!(game1.get_infoset(Player::P2).hash, game2.get_infoset(Player::P2).hash);
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt::{Display, Formatter};
+
+use crate::game::{Game, UndoGame, TensorGame, TicTacToe, IllegalMove, IllegalMoveReason, Player, Infoset, Observation};
+use crate::game::{GameRecord, RecordedTurn, RecordGame, RecordError};
+use crate::game::tictactoe::space_to_string;
+
+/// A player's tries at spaces, and whether they got each space
+type Attempts = Vec<(usize, bool)>;
+
+/// TicTacToe where players can't see the other player's marks
+///
+/// Trying a space the opponent already has fails, and the player has to try again.
+/// Players only know their own marks and which of their tries failed
+#[derive(Clone, Debug)]
+pub struct PhantomTicTacToe {
+    /// The real board
+    game: TicTacToe,
+    attempts: (Attempts, Attempts),
+    /// Every try in order, so turns can be undone and recorded
+    history: Vec<(Player, usize, bool)>,
+}
+
+impl PhantomTicTacToe {
+    pub fn new() -> PhantomTicTacToe {
+        PhantomTicTacToe {
+            game: TicTacToe::new(),
+            attempts: (vec![], vec![]),
+            history: vec![],
+        }
+    }
+
+    /// What the player knows about the space, which is their own mark or a failed try
+    fn get_known_space(&self, player: Player, space: usize) -> Option<Player> {
+        player.lens(&self.attempts).iter()
+            .find(|(s, _)| *s == space)
+            .map(|(_, success)| if *success { player } else { player.other() })
+    }
+}
+
+impl Game for PhantomTicTacToe {
+    type Action = usize;
+
+    fn get_turn(&self) -> (Player, Vec<usize>) {
+        let (player, spaces) = self.game.get_turn();
+        if spaces.is_empty() {
+            return (player, spaces);
+        }
+        //the player can try any space they don't know is taken
+        let spaces = (0..9).filter(|space| self.get_known_space(player, *space).is_none()).collect();
+        (player, spaces)
+    }
+
+    fn take_turn(&mut self, player: Player, action: &usize) -> Result<(), IllegalMove<usize>> {
+        let (current_player, spaces) = self.get_turn();
+        let reason = if self.game.get_reward().is_some() {
+            Some(IllegalMoveReason::GameOver)
+        } else if player != current_player {
+            Some(IllegalMoveReason::WrongPlayer { expected: current_player })
+        } else if *action >= 9 {
+            Some(IllegalMoveReason::InvalidAction("space is off the board"))
+        } else if !spaces.contains(action) {
+            Some(IllegalMoveReason::InvalidAction("space is already known to be taken"))
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(IllegalMove::new(player, action, reason));
+        }
+
+        //a space the opponent has fails without changing the turn
+        let success = self.game.get_space(*action).is_none();
+        if success {
+            self.game.take_turn(player, action)?;
+        }
+        player.lens_mut(&mut self.attempts).push((*action, success));
+        self.history.push((player, *action, success));
+        Ok(())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        self.game.num_action_ids()
+    }
+
+    fn action_id(&self, action: &usize) -> usize {
+        *action
+    }
+
+    fn parse_action(&self, action: &str) -> Option<usize> {
+        self.game.parse_action(action)
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        self.game.get_reward()
+    }
+
+    fn get_infoset(&self, player: Player) -> Infoset {
+        //the turns alternate, so the player's own tries also tell them how many marks the opponent has
+        Infoset::new(player.lens(&self.attempts).clone())
+    }
+
+    /// Nothing is public, since a failed try doesn't change the turn and the opponent can't tell it happened
+    fn get_private_observation(&self, player: Player) -> Observation {
+        Observation::new(player.lens(&self.attempts))
+    }
+
+    fn get_summary_string(&self, player: Player) -> String {
+        let mut summary = String::new();
+        for row in 0..3 {
+            for col in 0..3 {
+                summary += &format!("|{}", space_to_string(self.get_known_space(player, 3 * row + col)));
+            }
+            summary += "\n";
+        }
+        summary
+    }
+}
+
+impl UndoGame for PhantomTicTacToe {
+    //the history already has everything we need
+    type Undo = ();
+
+    fn apply_turn(&mut self, player: Player, action: &usize) -> Result<(), IllegalMove<usize>> {
+        self.take_turn(player, action)
+    }
+
+    fn undo_turn(&mut self, _undo: ()) {
+        let (player, _, success) = self.history.pop().expect("Tried to undo with no history");
+        player.lens_mut(&mut self.attempts).pop();
+        if success {
+            self.game.undo_turn(());
+        }
+    }
+}
+
+impl TensorGame for PhantomTicTacToe {
+    fn tensor_size(&self) -> usize {
+        3 * 9
+    }
+
+    fn get_infoset_tensor(&self, player: Player) -> Vec<f32> {
+        //each space is one-hot as the player's mark, a known opponent mark, or unknown
+        let mut tensor = vec![0.0; self.tensor_size()];
+        for space in 0..9 {
+            let offset = match self.get_known_space(player, space) {
+                Some(p) if p == player => 0,
+                Some(_) => 1,
+                None => 2,
+            };
+            tensor[3 * space + offset] = 1.0;
+        }
+        tensor
+    }
+}
+
+impl RecordGame for PhantomTicTacToe {
+    fn get_record(&self) -> GameRecord {
+        let mut record = GameRecord::new("phantom_tictactoe");
+        record.turns = self.history.iter()
+            .map(|(player, space, _)| RecordedTurn::new(*player, space))
+            .collect();
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<PhantomTicTacToe, RecordError> {
+        record.check_game("phantom_tictactoe")?;
+        let mut game = PhantomTicTacToe::new();
+        record.replay(&mut game)?;
+        Ok(game)
+    }
+}
+
+impl Display for PhantomTicTacToe {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.game.fmt(f)?;
+        let failed = self.history.iter()
+            .filter(|(_, _, success)| !success)
+            .map(|(player, space, _)| format!("{} {}", player, space))
+            .collect::<Vec<String>>();
+        writeln!(f, "Failed tries: {}", failed.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_taken_spaces() {
+        let mut game = PhantomTicTacToe::new();
+        game.take_turn(Player::P1, &4).unwrap();
+        assert_eq!(game.get_turn(), (Player::P2, vec![0, 1, 2, 3, 4, 5, 6, 7, 8]));
+
+        //P2 finds P1's mark and has to go again
+        game.take_turn(Player::P2, &4).unwrap();
+        assert_eq!(game.get_turn(), (Player::P2, vec![0, 1, 2, 3, 5, 6, 7, 8]));
+        assert!(game.take_turn(Player::P2, &4).is_err());
+        game.take_turn(Player::P2, &0).unwrap();
+        assert_eq!(game.get_turn(), (Player::P1, vec![0, 1, 2, 3, 5, 6, 7, 8]));
+    }
+
+    #[test]
+    fn hides_opponent_marks() {
+        let mut game1 = PhantomTicTacToe::new();
+        let mut game2 = PhantomTicTacToe::new();
+        game1.take_turn(Player::P1, &0).unwrap();
+        game2.take_turn(Player::P1, &8).unwrap();
+        assert_eq!(game1.get_infoset(Player::P2).hash, game2.get_infoset(Player::P2).hash);
+        assert_ne!(game1.get_infoset(Player::P1).hash, game2.get_infoset(Player::P1).hash);
+        assert_eq!(game1.get_summary_string(Player::P2), game2.get_summary_string(Player::P2));
+    }
+
+    #[test]
+    fn undoes_tries() {
+        let mut game = PhantomTicTacToe::new();
+        game.apply_turn(Player::P1, &4).unwrap();
+        let middle = format!("{:?}", game);
+        game.apply_turn(Player::P2, &4).unwrap();
+        game.apply_turn(Player::P2, &2).unwrap();
+
+        game.undo_turn(());
+        game.undo_turn(());
+        assert_eq!(format!("{:?}", game), middle);
+    }
+}
@@ -0,0 +1,442 @@
+//! Saving games as text and restoring them
+//!
+//! A record is how the game was set up plus every turn taken since, along with
+//! anything chance decided during a turn. Restoring replays the turns with `take_turn`,
+//! so a record can only describe positions the game can actually reach.
+//!
+//! Records are written as JSON with only strings, objects and arrays, like
+//! `{"game": "ocp", "setup": {"dealer": "P2"}, "turns": [{"player": "P1", "action": "Bet"}]}`
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::game::{Game, Player};
+
+/// Game that can be saved as a `GameRecord` and restored from one
+pub trait RecordGame: Game + Sized {
+
+    /// Describes how the game was set up and every turn taken
+    fn get_record(&self) -> GameRecord;
+
+    /// Sets the game up and replays the turns in the record
+    fn from_record(record: &GameRecord) -> Result<Self, RecordError>;
+}
+
+/// A turn that was taken, and what chance decided during it, if anything
+#[derive(PartialEq, Clone, Debug)]
+pub struct RecordedTurn {
+    pub player: Player,
+    pub action: String,
+    pub chance: Option<String>,
+}
+
+impl RecordedTurn {
+    pub fn new(player: Player, action: &impl fmt::Display) -> RecordedTurn {
+        RecordedTurn {
+            player,
+            action: action.to_string(),
+            chance: None,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct GameRecord {
+    /// Name the game is registered under
+    pub game: String,
+    /// Settings and starting chance outcomes, like dealt cards, in a fixed order
+    pub setup: Vec<(String, String)>,
+    pub turns: Vec<RecordedTurn>,
+}
+
+/// Why a record couldn't be read or restored
+#[derive(PartialEq, Clone, Debug)]
+pub enum RecordError {
+    /// The text isn't a record, with a description of what was wrong
+    Syntax(String),
+    UnknownGame(String),
+    WrongGame { expected: &'static str, found: String },
+    MissingSetup(&'static str),
+    BadSetup { key: &'static str, value: String },
+    /// A turn has an action or chance outcome the game can't parse
+    BadTurn { turn: usize, value: String },
+    /// A turn was parsed but the game didn't allow it
+    IllegalTurn { turn: usize, reason: String },
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordError::Syntax(reason) => write!(f, "can't parse record: {}", reason),
+            RecordError::UnknownGame(game) => write!(f, "there is no game called '{}'", game),
+            RecordError::WrongGame { expected, found } => write!(f, "expected a record of {}, found {}", expected, found),
+            RecordError::MissingSetup(key) => write!(f, "record is missing setup '{}'", key),
+            RecordError::BadSetup { key, value } => write!(f, "record has an invalid setup {}={}", key, value),
+            RecordError::BadTurn { turn, value } => write!(f, "turn {} has an invalid value '{}'", turn, value),
+            RecordError::IllegalTurn { turn, reason } => write!(f, "turn {} isn't allowed: {}", turn, reason),
+        }
+    }
+}
+
+impl error::Error for RecordError {}
+
+impl GameRecord {
+    /// Makes an empty record for the game, to have setup and turns added to
+    pub fn new(game: &str) -> GameRecord {
+        GameRecord {
+            game: String::from(game),
+            setup: vec![],
+            turns: vec![],
+        }
+    }
+
+    pub fn add_setup(&mut self, key: &str, value: impl fmt::Display) {
+        self.setup.push((String::from(key), value.to_string()));
+    }
+
+    /// Returns an error unless this is a record of the given game
+    pub fn check_game(&self, game: &'static str) -> Result<(), RecordError> {
+        if self.game == game {
+            Ok(())
+        } else {
+            Err(RecordError::WrongGame { expected: game, found: self.game.clone() })
+        }
+    }
+
+    pub fn get_setup<T: FromStr>(&self, key: &'static str) -> Result<T, RecordError> {
+        let (_, value) = self.setup.iter().find(|(k, _)| k == key)
+            .ok_or(RecordError::MissingSetup(key))?;
+        value.parse().map_err(|_| RecordError::BadSetup { key, value: value.clone() })
+    }
+
+    /// Like `get_setup`, but missing setup falls back to the default, for setup that older records don't have
+    pub fn get_setup_or<T: FromStr>(&self, key: &'static str, default: T) -> Result<T, RecordError> {
+        match self.get_setup(key) {
+            Err(RecordError::MissingSetup(_)) => Ok(default),
+            result => result,
+        }
+    }
+
+    /// Replays every turn on a game that was set up from this record
+    ///
+    /// This is for games without chance during turns, so turns with a chance outcome are rejected.
+    /// Simultaneous turns are recorded as P1's turn followed by P2's turn
+    pub fn replay<G: Game>(&self, game: &mut G) -> Result<(), RecordError> {
+        let mut i = 0;
+        while i < self.turns.len() {
+            let is_simultaneous = game.get_simultaneous_turn().is_some();
+            let num_turns = if is_simultaneous { 2 } else { 1 };
+            for j in i .. (i + num_turns).min(self.turns.len()) {
+                if let Some(chance) = &self.turns[j].chance {
+                    return Err(RecordError::BadTurn { turn: j, value: chance.clone() });
+                }
+            }
+
+            let action = self.parse_action(game, i)?;
+            let result = if is_simultaneous {
+                if self.turns[i].player != Player::P1 || self.turns.get(i + 1).map(|turn| turn.player) != Some(Player::P2) {
+                    return Err(RecordError::IllegalTurn { turn: i, reason: String::from("simultaneous turns need P1's turn then P2's turn") });
+                }
+                let p2_action = self.parse_action(game, i + 1)?;
+                game.take_simultaneous_turn((&action, &p2_action))
+            } else {
+                game.take_turn(self.turns[i].player, &action)
+            };
+            result.map_err(|err| RecordError::IllegalTurn { turn: i, reason: err.reason.to_string() })?;
+            i += num_turns;
+        }
+        Ok(())
+    }
+
+    /// Parses the action of the given turn
+    pub fn parse_action<G: Game>(&self, game: &G, turn: usize) -> Result<G::Action, RecordError> {
+        let action = &self.turns[turn].action;
+        game.parse_action(action)
+            .ok_or_else(|| RecordError::BadTurn { turn, value: action.clone() })
+    }
+
+    /// Reads a record written by `Display`
+    pub fn parse(text: &str) -> Result<GameRecord, RecordError> {
+        let mut parser = Parser { text, pos: 0 };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos != text.len() {
+            return Err(parser.error("unexpected text after the record"));
+        }
+
+        let mut fields = value.into_object("record")?;
+        let game = take_field(&mut fields, "game")?.into_string("game")?;
+        let setup = take_field(&mut fields, "setup")?.into_object("setup")?.into_iter()
+            .map(|(key, value)| Ok((key, value.into_string("setup value")?)))
+            .collect::<Result<_, RecordError>>()?;
+        let turns = take_field(&mut fields, "turns")?.into_array("turns")?.into_iter()
+            .map(|turn| {
+                let mut fields = turn.into_object("turn")?;
+                let player = take_field(&mut fields, "player")?.into_string("player")?.parse()
+                    .map_err(RecordError::Syntax)?;
+                let action = take_field(&mut fields, "action")?.into_string("action")?;
+                let chance = match take_field(&mut fields, "chance") {
+                    Ok(chance) => Some(chance.into_string("chance")?),
+                    Err(_) => None,
+                };
+                Ok(RecordedTurn { player, action, chance })
+            })
+            .collect::<Result<_, RecordError>>()?;
+
+        Ok(GameRecord { game, setup, turns })
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{\"game\": {}, \"setup\": {{", quote(&self.game))?;
+        for (i, (key, value)) in self.setup.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            write!(f, "{}{}: {}", separator, quote(key), quote(value))?;
+        }
+        write!(f, "}}, \"turns\": [")?;
+        for (i, turn) in self.turns.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            write!(f, "{}{{\"player\": {}, \"action\": {}", separator, quote(&turn.player.to_string()), quote(&turn.action))?;
+            if let Some(chance) = &turn.chance {
+                write!(f, ", \"chance\": {}", quote(chance))?;
+            }
+            write!(f, "}}")?;
+        }
+        write!(f, "]}}")
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted += "\\\"",
+            '\\' => quoted += "\\\\",
+            '\n' => quoted += "\\n",
+            '\r' => quoted += "\\r",
+            '\t' => quoted += "\\t",
+            //JSON strings can't hold any other control characters either
+            c if c < ' ' => quoted += &format!("\\u{:04x}", c as u32),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The subset of JSON that records use
+enum Value {
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn into_string(self, name: &str) -> Result<String, RecordError> {
+        match self {
+            Value::String(s) => Ok(s),
+            _ => Err(RecordError::Syntax(format!("{} should be a string", name))),
+        }
+    }
+
+    fn into_array(self, name: &str) -> Result<Vec<Value>, RecordError> {
+        match self {
+            Value::Array(values) => Ok(values),
+            _ => Err(RecordError::Syntax(format!("{} should be an array", name))),
+        }
+    }
+
+    fn into_object(self, name: &str) -> Result<Vec<(String, Value)>, RecordError> {
+        match self {
+            Value::Object(fields) => Ok(fields),
+            _ => Err(RecordError::Syntax(format!("{} should be an object", name))),
+        }
+    }
+}
+
+fn take_field(fields: &mut Vec<(String, Value)>, name: &str) -> Result<Value, RecordError> {
+    let index = fields.iter().position(|(key, _)| key == name)
+        .ok_or_else(|| RecordError::Syntax(format!("missing field '{}'", name)))?;
+    Ok(fields.remove(index).1)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    /// Byte offset of the next character
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, reason: &str) -> RecordError {
+        RecordError::Syntax(format!("{} at position {}", reason, self.pos))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), RecordError> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected)))
+        }
+    }
+
+    /// Parses comma separated items until the closing character
+    fn parse_list<T>(&mut self, close: char, mut parse_item: impl FnMut(&mut Self) -> Result<T, RecordError>) -> Result<Vec<T>, RecordError> {
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(parse_item(self)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(items);
+                }
+                _ => return Err(self.error(&format!("expected ',' or '{}'", close))),
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, RecordError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.parse_string()?)),
+            Some('[') => {
+                self.pos += 1;
+                Ok(Value::Array(self.parse_list(']', |parser| parser.parse_value())?))
+            }
+            Some('{') => {
+                self.pos += 1;
+                Ok(Value::Object(self.parse_list('}', |parser| {
+                    let key = parser.parse_string()?;
+                    parser.expect(':')?;
+                    Ok((key, parser.parse_value()?))
+                })?))
+            }
+            _ => Err(self.error("expected a string, array or object")),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, RecordError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += escaped.len_utf8();
+                    match escaped {
+                        '"' => s.push('"'),
+                        '\\' => s.push('\\'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => {
+                            let code = self.text.get(self.pos..self.pos + 4)
+                                .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(std::char::from_u32)
+                                .ok_or_else(|| self.error("bad unicode escape"))?;
+                            self.pos += 4;
+                            s.push(code);
+                        }
+                        _ => return Err(self.error("unknown escape")),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> GameRecord {
+        let mut record = GameRecord::new("example");
+        record.add_setup("name", "\"quoted\\\" \n");
+        record.add_setup("size", 3);
+        record.turns.push(RecordedTurn::new(Player::P1, &"Bid { amount: 2 }"));
+        record.turns.push(RecordedTurn {
+            player: Player::P2,
+            action: String::from("Pass"),
+            chance: Some(String::from("Skull")),
+        });
+        record
+    }
+
+    #[test]
+    fn writes_json() {
+        assert_eq!(example().to_string(), concat!(
+            r#"{"game": "example", "setup": {"name": "\"quoted\\\" \n", "size": "3"}, "#,
+            r#""turns": [{"player": "P1", "action": "Bid { amount: 2 }"}, {"player": "P2", "action": "Pass", "chance": "Skull"}]}"#,
+        ));
+    }
+
+    #[test]
+    fn parses_what_it_writes() {
+        let record = example();
+        assert_eq!(GameRecord::parse(&record.to_string()), Ok(record.clone()));
+        assert_eq!(record.get_setup::<u32>("size"), Ok(3));
+        assert_eq!(record.get_setup::<u32>("name"), Err(RecordError::BadSetup { key: "name", value: String::from("\"quoted\\\" \n") }));
+        assert_eq!(record.get_setup::<u32>("other"), Err(RecordError::MissingSetup("other")));
+        assert_eq!(record.get_setup_or::<u32>("other", 5), Ok(5));
+        assert_eq!(record.get_setup_or::<u32>("size", 5), Ok(3));
+        assert!(record.get_setup_or::<u32>("name", 5).is_err());
+
+        let empty = GameRecord::new("empty");
+        assert_eq!(GameRecord::parse(&empty.to_string()), Ok(empty));
+    }
+
+    #[test]
+    fn escapes_control_characters() {
+        let mut record = GameRecord::new("example");
+        let control: String = (0..0x20).map(|c| std::char::from_u32(c).unwrap()).collect();
+        record.add_setup("control", format!("a{}\u{7f}é\\u0041", control));
+        let text = record.to_string();
+        assert!(text.contains(r#"\u0000\u0001"#) && text.contains(r#"\t\n\u000b\u000c\r"#), "Wrote {}", text);
+        assert!(!text.chars().any(|c| c < ' '), "Wrote {}", text);
+        assert_eq!(GameRecord::parse(&text), Ok(record));
+    }
+
+    #[test]
+    fn rejects_bad_text() {
+        let bad = [
+            "",
+            "{\"game\": \"example\", \"setup\": {}}",
+            "{\"game\": \"example\", \"setup\": {}, \"turns\": []",
+            "{\"game\": \"example\", \"setup\": {}, \"turns\": []} extra",
+            "{\"game\": \"example\", \"setup\": [], \"turns\": []}",
+            "{\"game\": \"example\", \"setup\": {}, \"turns\": [{\"player\": \"P3\", \"action\": \"Pass\"}]}",
+            "{\"game\": \"example\", \"setup\": {\"a\": \"\\q\"}, \"turns\": []}",
+            "{\"game\": \"example\", \"setup\": {\"a\": \"\\u00\"}, \"turns\": []}",
+            "{\"game\": \"example\", \"setup\": {\"a\": \"\\ud800\"}, \"turns\": []}",
+            "{\"game\": \"example\", \"setup\": {\"a\": \"\\u+041\"}, \"turns\": []}",
+        ];
+        for text in bad.iter() {
+            assert!(matches!(GameRecord::parse(text), Err(RecordError::Syntax(_))), "Parsed {}", text);
+        }
+    }
+}
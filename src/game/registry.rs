@@ -0,0 +1,760 @@
+//! Builds games from spec strings like `skulls(first=p1,skulls=1,flowers=2)`
+//!
+//! Each game is a different type, so instead of returning the game, the registry
+//! hands a constructor for it to a `GameVisitor`.
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::game::{UndoGame, TicTacToe, PhantomTicTacToe, OneCardPoker, KuhnPoker, LeducPoker, LiarsDice, Goofspiel, Blotto, Skulls, MatrixGame, DoubleMatrixGame};
+use crate::game::{Match, MatchConfig, MatchReward, RoundVisibility, Abstracted, CardBuckets, RoundSummary};
+use crate::game::{ActionAbstracted, BidSizes, InfosetAbstraction, SkullsRules, OneCardPokerRules};
+use crate::game::{GameRecord, RecordGame, RecordError};
+use crate::game::skulls::{Seat, MAX_PLAYERS, parse_seat};
+use crate::game::blotto::{is_playable, MAX_SOLDIERS, MAX_BATTLEFIELDS};
+use crate::game::ocp::MAX_RAISES;
+
+/// Something that can be done with any game, given a way to make new games
+pub trait GameVisitor {
+    type Output;
+
+    fn visit<G, F>(self, new_game: F) -> Self::Output
+        where G: UndoGame + Clone + 'static,
+              F: Fn() -> G + Clone + Send + Sync + 'static;
+}
+
+pub struct ParamInfo {
+    pub name: &'static str,
+    pub default: &'static str,
+    pub description: &'static str,
+}
+
+pub struct GameInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Parameters in the order they can be given without names
+    pub params: &'static [ParamInfo],
+}
+
+/// Every game that can be built from a spec
+pub const GAMES: &[GameInfo] = &[
+    GameInfo {
+        name: "tictactoe",
+        description: "TicTacToe where the first to get enough marks in a line wins",
+        params: &[
+            ParamInfo { name: "width", default: "3", description: "columns on the board, from 1 to 15" },
+            ParamInfo { name: "height", default: "3", description: "rows on the board, from 1 to 15" },
+            ParamInfo { name: "win", default: "3", description: "marks in a line needed to win, up to the width or height" },
+        ],
+    },
+    GameInfo {
+        name: "phantom_tictactoe",
+        description: "TicTacToe where players can't see the other player's marks",
+        params: &[],
+    },
+    GameInfo {
+        name: "ocp",
+        description: "One card poker with a single round of betting",
+        params: &[
+            ParamInfo { name: "cards", default: "13", description: "cards in the deck, from 2 to 52" },
+            ParamInfo { name: "buckets", default: "none", description: "none, or groups of neighbouring cards that share a strategy, up to the number of cards" },
+            ParamInfo { name: "ante", default: "1", description: "what each player puts in the pot before the deal" },
+            ParamInfo { name: "bets", default: "1", description: "sizes a player can bet or raise by separated by /, like 1/2" },
+            ParamInfo { name: "raises", default: "1", description: "bets and raises in a hand including the first bet, from 0 to 5" },
+        ],
+    },
+    GameInfo {
+        name: "kuhn",
+        description: "Kuhn poker with a 3 card deck, where P1 always acts first",
+        params: &[],
+    },
+    GameInfo {
+        name: "leduc",
+        description: "Leduc hold'em with a 6 card deck and two betting rounds",
+        params: &[],
+    },
+    GameInfo {
+        name: "liars_dice",
+        description: "Liar's dice where P1 bids first and calling liar ends the game",
+        params: &[
+            ParamInfo { name: "dice", default: "1", description: "dice each player rolls, from 1 to 5" },
+            ParamInfo { name: "faces", default: "6", description: "faces on each die, from 2 to 9" },
+        ],
+    },
+    GameInfo {
+        name: "goofspiel",
+        description: "Goofspiel where both players bid on each prize at once",
+        params: &[
+            ParamInfo { name: "cards", default: "4", description: "cards in each hand and in the prizes, from 1 to 13" },
+            ParamInfo { name: "order", default: "ascending", description: "order of the prizes, ascending or random" },
+        ],
+    },
+    GameInfo {
+        name: "blotto",
+        description: "Colonel Blotto, where both players split their soldiers between the battlefields at once",
+        params: &[
+            ParamInfo { name: "soldiers", default: "5", description: "soldiers each player has, from 1 to 30" },
+            ParamInfo { name: "battlefields", default: "3", description: "battlefields to fight over, from 1 to 10, with at most 5000 ways to split the soldiers" },
+        ],
+    },
+    GameInfo {
+        name: "skulls",
+        description: "Skulls, where with more than 2 players it's P1's seat against a team of every other seat playing as P2, not real multi-player Skull",
+        params: &[
+            ParamInfo { name: "first", default: "p1", description: "seat to stack first, from p1 up to the number of players" },
+            ParamInfo { name: "skulls", default: "1", description: "skulls in each player's hand" },
+            ParamInfo { name: "flowers", default: "3", description: "flowers in each player's hand" },
+            ParamInfo { name: "abstraction", default: "none", description: "none, or round to only remember the current round and points" },
+            ParamInfo { name: "bids", default: "all", description: "all, or the bid amounts to keep separated by /, like 1/2/4" },
+            ParamInfo { name: "players", default: "2", description: "seats at the table, from 2 to 6, where every seat after the first is on P2's team" },
+            ParamInfo { name: "points", default: "2", description: "successful challenges needed to win" },
+            ParamInfo { name: "bidding", default: "turns", description: "turns, or simultaneous for everyone but the leader to bid at once each round" },
+            ParamInfo { name: "own_skull", default: "flowers", description: "flowers to lose a flower first when flipping your own skull, or random" },
+            ParamInfo { name: "opponent_skull", default: "random", description: "random to lose a random card when flipping an opponent's skull, or flowers" },
+            ParamInfo { name: "rng", default: "on", description: "on, or off to always lose flowers first" },
+        ],
+    },
+    GameInfo {
+        name: "matrix",
+        description: "Matrix game where both players move at once",
+        params: &[
+            ParamInfo { name: "preset", default: "rps", description: "rps or example" },
+            ParamInfo { name: "file", default: "none", description: "none, or a file of P1's payoffs to use instead of the preset, a row per line separated by commas or whitespace" },
+        ],
+    },
+    GameInfo {
+        name: "double_matrix",
+        description: "Two matrix games in a row, where each player remembers only their own first move",
+        params: &[
+            ParamInfo { name: "preset", default: "rps", description: "rps or example" },
+        ],
+    },
+    GameInfo {
+        name: "match",
+        description: "Several rounds of another game played as one game",
+        params: &[
+            ParamInfo { name: "game", default: "ocp", description: "spec of the game played each round" },
+            ParamInfo { name: "rounds", default: "3", description: "most rounds in the match" },
+            ParamInfo { name: "alternate", default: "true", description: "whether players swap seats every round" },
+            ParamInfo { name: "visibility", default: "full", description: "what players see of earlier rounds, nothing, rewards or full" },
+            ParamInfo { name: "reward", default: "sum", description: "sum or average of the round rewards" },
+            ParamInfo { name: "stack", default: "none", description: "reward each player starts with, the match ends when either can't cover a round" },
+            ParamInfo { name: "stop_when_decided", default: "false", description: "whether to end once the remaining rounds can't change who's ahead" },
+        ],
+    },
+];
+
+/// Matrices that matrix games can be built with
+enum MatrixPreset {
+    RockPaperScissors,
+    /// The 2x2 matrix do_cfr used to train on
+    Example,
+}
+
+const EXAMPLE_MATRIX: [f32; 4] = [1.0, 0.9, -0.7, 1.0];
+
+/// Why a spec couldn't be turned into a game
+#[derive(PartialEq, Clone, Debug)]
+pub enum SpecError {
+    /// The spec isn't `name` or `name(params)`
+    Syntax(String),
+    UnknownGame(String),
+    UnknownParam { game: &'static str, param: String },
+    TooManyParams { game: &'static str },
+    BadValue { game: &'static str, param: &'static str, value: String, reason: &'static str },
+    /// The parameter names a file that couldn't be loaded, with the loader's explanation
+    BadFile { game: &'static str, param: &'static str, value: String, message: String },
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpecError::Syntax(spec) => write!(f, "can't parse game spec '{}', expected name(key=value,...)", spec),
+            SpecError::UnknownGame(name) => write!(f, "there is no game called '{}'", name),
+            SpecError::UnknownParam { game, param } => write!(f, "{} has no parameter '{}'", game, param),
+            SpecError::TooManyParams { game } => write!(f, "{} was given too many parameters", game),
+            SpecError::BadValue { game, param, value, reason } =>
+                write!(f, "{} can't have {}={}: {}", game, param, value, reason),
+            SpecError::BadFile { game, param, value, message } =>
+                write!(f, "{} can't load {}={}: {}", game, param, value, message),
+        }
+    }
+}
+
+impl error::Error for SpecError {}
+
+/// A spec split into the game's name and its parameters
+///
+/// Parameters without a name are None
+#[derive(PartialEq, Debug)]
+struct GameSpec {
+    name: String,
+    params: Vec<(Option<String>, String)>,
+}
+
+impl GameSpec {
+    fn parse(spec: &str) -> Result<GameSpec, SpecError> {
+        let syntax_error = || SpecError::Syntax(String::from(spec));
+        let spec = spec.trim();
+
+        let (name, params) = match spec.find('(') {
+            None => (spec, ""),
+            Some(i) => {
+                let params = spec[i + 1..].strip_suffix(')').ok_or_else(syntax_error)?;
+                (&spec[..i], params)
+            }
+        };
+        let name = name.trim();
+        if name.is_empty() || name.contains(')') {
+            return Err(syntax_error());
+        }
+
+        let params = if params.trim().is_empty() {
+            vec![]
+        } else {
+            split_params(params).ok_or_else(syntax_error)?.into_iter().map(|param| {
+                //values can be specs with their own parameters, so only look for a key before any parentheses
+                let key_end = param.find('=').filter(|&i| !param[..i].contains('('));
+                let (key, value) = match key_end {
+                    None => (None, param.trim()),
+                    Some(i) => (Some(param[..i].trim().to_lowercase()), param[i + 1..].trim()),
+                };
+                if value.is_empty() || key.as_deref() == Some("") {
+                    return Err(syntax_error());
+                }
+                Ok((key, String::from(value)))
+            }).collect::<Result<_, _>>()?
+        };
+
+        Ok(GameSpec {
+            name: name.to_lowercase(),
+            params,
+        })
+    }
+}
+
+/// Splits parameters at commas that aren't inside parentheses
+///
+/// Returns None if the parentheses don't match
+fn split_params(params: &str) -> Option<Vec<&str>> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut split = vec![];
+    for (i, c) in params.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return None,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                split.push(&params[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return None;
+    }
+    split.push(&params[start..]);
+    Some(split)
+}
+
+/// Every parameter of a game, with defaults filled in
+struct Params {
+    game: &'static GameInfo,
+    values: HashMap<&'static str, String>,
+}
+
+impl Params {
+    fn new(game: &'static GameInfo, spec: GameSpec) -> Result<Params, SpecError> {
+        let mut values: HashMap<&'static str, String> = game.params.iter()
+            .map(|param| (param.name, String::from(param.default)))
+            .collect();
+
+        for (i, (key, value)) in spec.params.into_iter().enumerate() {
+            let param = match key {
+                Some(key) => game.params.iter().find(|param| param.name == key)
+                    .ok_or(SpecError::UnknownParam { game: game.name, param: key })?,
+                None => game.params.get(i)
+                    .ok_or(SpecError::TooManyParams { game: game.name })?,
+            };
+            values.insert(param.name, value);
+        }
+
+        Ok(Params {
+            game,
+            values,
+        })
+    }
+
+    fn bad_value(&self, param: &'static str, reason: &'static str) -> SpecError {
+        SpecError::BadValue {
+            game: self.game.name,
+            param,
+            value: self.values[param].clone(),
+            reason,
+        }
+    }
+
+    fn bad_file(&self, param: &'static str, err: impl error::Error) -> SpecError {
+        SpecError::BadFile {
+            game: self.game.name,
+            param,
+            value: self.values[param].clone(),
+            message: err.to_string(),
+        }
+    }
+
+    fn get<T: FromStr>(&self, param: &'static str) -> Result<T, SpecError> {
+        self.values[param].parse()
+            .map_err(|_| self.bad_value(param, "not a valid number"))
+    }
+
+    fn get_seat(&self, param: &'static str, num_players: usize) -> Result<Seat, SpecError> {
+        parse_seat(&self.values[param]).filter(|seat| *seat < num_players)
+            .ok_or_else(|| self.bad_value(param, "expected p1 up to the number of players"))
+    }
+
+    fn get_matrix(&self, param: &'static str) -> Result<MatrixPreset, SpecError> {
+        match self.values[param].to_lowercase().as_str() {
+            "rps" => Ok(MatrixPreset::RockPaperScissors),
+            "example" => Ok(MatrixPreset::Example),
+            _ => Err(self.bad_value(param, "expected rps or example")),
+        }
+    }
+
+    fn get_bool(&self, param: &'static str) -> Result<bool, SpecError> {
+        self.values[param].to_lowercase().parse()
+            .map_err(|_| self.bad_value(param, "expected true or false"))
+    }
+
+    fn get_optional<T: FromStr>(&self, param: &'static str) -> Result<Option<T>, SpecError> {
+        match self.values[param].to_lowercase().as_str() {
+            "none" => Ok(None),
+            _ => self.get(param).map(Some),
+        }
+    }
+
+    fn get_bids(&self, param: &'static str) -> Result<Option<Vec<u8>>, SpecError> {
+        match self.values[param].to_lowercase().as_str() {
+            "all" => Ok(None),
+            bids => bids.split('/').map(|amount| amount.trim().parse().ok()).collect::<Option<_>>()
+                .map(Some)
+                .ok_or_else(|| self.bad_value(param, "expected all or amounts separated by /")),
+        }
+    }
+
+    fn get_visibility(&self, param: &'static str) -> Result<RoundVisibility, SpecError> {
+        match self.values[param].to_lowercase().as_str() {
+            "nothing" => Ok(RoundVisibility::Nothing),
+            "rewards" => Ok(RoundVisibility::Rewards),
+            "full" => Ok(RoundVisibility::Full),
+            _ => Err(self.bad_value(param, "expected nothing, rewards or full")),
+        }
+    }
+
+    fn get_match_reward(&self, param: &'static str) -> Result<MatchReward, SpecError> {
+        match self.values[param].to_lowercase().as_str() {
+            "sum" => Ok(MatchReward::Sum),
+            "average" => Ok(MatchReward::Average),
+            _ => Err(self.bad_value(param, "expected sum or average")),
+        }
+    }
+}
+
+/// Wraps whichever game it's given in a match before passing it on
+struct MatchVisitor<V> {
+    visitor: V,
+    config: MatchConfig,
+}
+
+impl<V: GameVisitor> GameVisitor for MatchVisitor<V> {
+    type Output = V::Output;
+
+    fn visit<G, F>(self, new_game: F) -> V::Output
+        where G: UndoGame + Clone + 'static,
+              F: Fn() -> G + Clone + Send + Sync + 'static
+    {
+        let config = self.config;
+        self.visitor.visit(move || Match::new(new_game.clone(), config.clone()))
+    }
+}
+
+/// Builds the game described by the spec and gives it to the visitor
+///
+/// Parameters can be given by name, or in order without names. Any that are left out use their defaults
+pub fn build_game<V: GameVisitor>(spec: &str, visitor: V) -> Result<V::Output, SpecError> {
+    let params = parse_params(spec)?;
+    if params.game.name != "match" {
+        return build_round_game(params, visitor);
+    }
+
+    let config = MatchConfig {
+        rounds: params.get("rounds")?,
+        alternate: params.get_bool("alternate")?,
+        visibility: params.get_visibility("visibility")?,
+        reward: params.get_match_reward("reward")?,
+        stack: params.get_optional("stack")?,
+        stop_when_decided: params.get_bool("stop_when_decided")?,
+    };
+    if config.rounds == 0 {
+        return Err(params.bad_value("rounds", "a match needs at least one round"));
+    }
+    //the game played each round is built like any other, then wrapped
+    let round_params = parse_params(&params.values["game"])?;
+    if round_params.game.name == "match" {
+        return Err(params.bad_value("game", "matches can't be nested"));
+    }
+    build_round_game(round_params, MatchVisitor { visitor, config })
+}
+
+fn parse_params(spec: &str) -> Result<Params, SpecError> {
+    let spec = GameSpec::parse(spec)?;
+    let game = GAMES.iter().find(|game| game.name == spec.name)
+        .ok_or_else(|| SpecError::UnknownGame(spec.name.clone()))?;
+    Params::new(game, spec)
+}
+
+/// Builds any game other than a match
+fn build_round_game<V: GameVisitor>(params: Params, visitor: V) -> Result<V::Output, SpecError> {
+    let output = match params.game.name {
+        "tictactoe" => {
+            let width: usize = params.get("width")?;
+            if !(1..=15).contains(&width) {
+                return Err(params.bad_value("width", "must be between 1 and 15"));
+            }
+            let height = params.get("height")?;
+            if !(1..=15).contains(&height) {
+                return Err(params.bad_value("height", "must be between 1 and 15"));
+            }
+            let win_length = params.get("win")?;
+            if win_length == 0 || win_length > width.max(height) {
+                return Err(params.bad_value("win", "must be between 1 and the larger of the width and height"));
+            }
+            visitor.visit(move || TicTacToe::new_mnk(width, height, win_length))
+        }
+        "phantom_tictactoe" => visitor.visit(PhantomTicTacToe::new),
+        "ocp" => {
+            let num_cards = params.get("cards")?;
+            if !(2..=52).contains(&num_cards) {
+                return Err(params.bad_value("cards", "must be between 2 and 52"));
+            }
+            let bet_sizes: Vec<u32> = params.values["bets"].split('/')
+                .map(|size| size.trim().parse().ok().filter(|&size| size > 0))
+                .collect::<Option<_>>()
+                .ok_or_else(|| params.bad_value("bets", "expected sizes above 0 separated by /"))?;
+            if (1..bet_sizes.len()).any(|i| bet_sizes[..i].contains(&bet_sizes[i])) {
+                return Err(params.bad_value("bets", "bet sizes can't repeat"));
+            }
+            let max_raises = params.get("raises")?;
+            if max_raises > MAX_RAISES {
+                return Err(params.bad_value("raises", "must be between 0 and 5"));
+            }
+            let rules = OneCardPokerRules { num_cards, ante: params.get("ante")?, bet_sizes, max_raises };
+            match params.get_optional("buckets")? {
+                Some(num_buckets) if num_buckets == 0 || num_buckets > num_cards => {
+                    return Err(params.bad_value("buckets", "must be between 1 and the number of cards"));
+                }
+                Some(num_buckets) if num_buckets < num_cards => {
+                    visitor.visit(move || Abstracted::new(OneCardPoker::with_rules(rules.clone()), CardBuckets { num_buckets }))
+                }
+                _ => visitor.visit(move || OneCardPoker::with_rules(rules.clone())),
+            }
+        }
+        "kuhn" => visitor.visit(KuhnPoker::new),
+        "leduc" => visitor.visit(LeducPoker::new),
+        "liars_dice" => {
+            let num_dice = params.get("dice")?;
+            let num_faces = params.get("faces")?;
+            if num_dice == 0 || num_dice > 5 {
+                return Err(params.bad_value("dice", "must be between 1 and 5"));
+            }
+            if !(2..=9).contains(&num_faces) {
+                return Err(params.bad_value("faces", "must be between 2 and 9"));
+            }
+            visitor.visit(move || LiarsDice::new(num_dice, num_faces))
+        }
+        "goofspiel" => {
+            let num_cards = params.get("cards")?;
+            if num_cards == 0 || num_cards > 13 {
+                return Err(params.bad_value("cards", "must be between 1 and 13"));
+            }
+            let ascending = match params.values["order"].to_lowercase().as_str() {
+                "ascending" => true,
+                "random" => false,
+                _ => return Err(params.bad_value("order", "expected ascending or random")),
+            };
+            visitor.visit(move || Goofspiel::new(num_cards, ascending))
+        }
+        "blotto" => {
+            let soldiers = params.get("soldiers")?;
+            if !(1..=MAX_SOLDIERS).contains(&soldiers) {
+                return Err(params.bad_value("soldiers", "must be between 1 and 30"));
+            }
+            let battlefields = params.get("battlefields")?;
+            if !(1..=MAX_BATTLEFIELDS).contains(&battlefields) {
+                return Err(params.bad_value("battlefields", "must be between 1 and 10"));
+            }
+            //both are in range, so the game is only unplayable with too many ways to split the soldiers
+            if !is_playable(soldiers, battlefields) {
+                return Err(params.bad_value("battlefields", "there can be at most 5000 ways to split the soldiers"));
+            }
+            //the allocations are only listed once, then shared by every copy
+            let game = Blotto::new(soldiers, battlefields);
+            visitor.visit(move || game.clone())
+        }
+        "skulls" => {
+            let simultaneous_bidding = match params.values["bidding"].to_lowercase().as_str() {
+                "turns" => false,
+                "simultaneous" => true,
+                _ => return Err(params.bad_value("bidding", "expected turns or simultaneous")),
+            };
+            let self_flip_loses_flowers_first = match params.values["own_skull"].to_lowercase().as_str() {
+                "flowers" => true,
+                "random" => false,
+                _ => return Err(params.bad_value("own_skull", "expected flowers or random")),
+            };
+            let opponent_flip_loses_flowers_first = match params.values["opponent_skull"].to_lowercase().as_str() {
+                "flowers" => true,
+                "random" => false,
+                _ => return Err(params.bad_value("opponent_skull", "expected flowers or random")),
+            };
+            let no_rng = match params.values["rng"].to_lowercase().as_str() {
+                "on" => false,
+                "off" => true,
+                _ => return Err(params.bad_value("rng", "expected on or off")),
+            };
+            let rules = SkullsRules {
+                num_players: params.get("players")?,
+                num_skulls: params.get("skulls")?,
+                num_flowers: params.get("flowers")?,
+                points_to_win: params.get("points")?,
+                simultaneous_bidding,
+                self_flip_loses_flowers_first,
+                opponent_flip_loses_flowers_first,
+                no_rng,
+            };
+            if !(2..=MAX_PLAYERS).contains(&rules.num_players) {
+                return Err(params.bad_value("players", "must be between 2 and 6"));
+            }
+            let first = params.get_seat("first", rules.num_players)?;
+            if rules.num_skulls as u32 + rules.num_flowers as u32 == 0 {
+                return Err(params.bad_value("flowers", "players need at least one card"));
+            }
+            if rules.points_to_win == 0 {
+                return Err(params.bad_value("points", "must be at least 1"));
+            }
+            let round_summary = match params.values["abstraction"].to_lowercase().as_str() {
+                "none" => false,
+                "round" => true,
+                _ => return Err(params.bad_value("abstraction", "expected none or round")),
+            };
+            let new_game = move || Skulls::with_rules(rules, first);
+            match (round_summary, params.get_bids("bids")?) {
+                (false, None) => visitor.visit(new_game),
+                (true, None) => visitor.visit(move || Abstracted::new(new_game(), RoundSummary)),
+                (false, Some(amounts)) => visitor.visit(move || ActionAbstracted::new(new_game(), BidSizes { amounts: amounts.clone() })),
+                (true, Some(amounts)) => visitor.visit(move || {
+                    let game = ActionAbstracted::new(new_game(), BidSizes { amounts: amounts.clone() });
+                    Abstracted::new(game, |game: &ActionAbstracted<Skulls>, player| RoundSummary.get_abstract_infoset(game.get_game(), player))
+                }),
+            }
+        }
+        "matrix" => {
+            let preset = params.get_matrix("preset")?;
+            match params.get_optional::<String>("file")? {
+                Some(path) => {
+                    let game = MatrixGame::load_payoffs(path)
+                        .map_err(|err| params.bad_file("file", err))?;
+                    visitor.visit(move || game.clone())
+                }
+                None => match preset {
+                    MatrixPreset::RockPaperScissors => visitor.visit(MatrixGame::new_rock_paper_scissors),
+                    MatrixPreset::Example => visitor.visit(|| MatrixGame::new(2, EXAMPLE_MATRIX.to_vec())),
+                }
+            }
+        }
+        "double_matrix" => match params.get_matrix("preset")? {
+            MatrixPreset::RockPaperScissors => visitor.visit(DoubleMatrixGame::new_rock_paper_scissors),
+            MatrixPreset::Example => visitor.visit(|| DoubleMatrixGame::new(2, EXAMPLE_MATRIX.to_vec())),
+        }
+        name => unreachable!("{} is listed but can't be built", name),
+    };
+    Ok(output)
+}
+
+fn restore<G, V>(record: &GameRecord, visitor: V) -> Result<V::Output, RecordError>
+    where G: RecordGame + UndoGame + Clone + Send + Sync + 'static,
+          V: GameVisitor
+{
+    let game = G::from_record(record)?;
+    Ok(visitor.visit(move || game.clone()))
+}
+
+/// Restores the recorded position and gives the visitor a way to make copies of it
+pub fn restore_game<V: GameVisitor>(record: &GameRecord, visitor: V) -> Result<V::Output, RecordError> {
+    match record.game.as_str() {
+        "tictactoe" => restore::<TicTacToe, V>(record, visitor),
+        "phantom_tictactoe" => restore::<PhantomTicTacToe, V>(record, visitor),
+        "ocp" => restore::<OneCardPoker, V>(record, visitor),
+        "kuhn" => restore::<KuhnPoker, V>(record, visitor),
+        "leduc" => restore::<LeducPoker, V>(record, visitor),
+        "liars_dice" => restore::<LiarsDice, V>(record, visitor),
+        "goofspiel" => restore::<Goofspiel, V>(record, visitor),
+        "skulls" => restore::<Skulls, V>(record, visitor),
+        "blotto" => restore::<Blotto, V>(record, visitor),
+        "matrix" => restore::<MatrixGame, V>(record, visitor),
+        "double_matrix" => restore::<DoubleMatrixGame, V>(record, visitor),
+        _ => Err(RecordError::UnknownGame(record.game.clone())),
+    }
+}
+
+/// Lists every game and its parameters
+pub fn describe_games() -> String {
+    let mut description = String::new();
+    for game in GAMES.iter() {
+        description += &format!("{}: {}\n", game.name, game.description);
+        for param in game.params.iter() {
+            description += &format!("    {} (default {}): {}\n", param.name, param.default, param.description);
+        }
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Player;
+
+    /// Returns who moves first and how many actions they have
+    struct FirstTurn;
+
+    impl GameVisitor for FirstTurn {
+        type Output = (Player, usize);
+
+        fn visit<G, F>(self, new_game: F) -> (Player, usize)
+            where G: UndoGame + Clone + 'static,
+                  F: Fn() -> G + Clone + Send + Sync + 'static
+        {
+            let (player, actions) = new_game().get_turn();
+            (player, actions.len())
+        }
+    }
+
+    #[test]
+    fn parses_specs() {
+        assert_eq!(GameSpec::parse("tictactoe"), Ok(GameSpec { name: String::from("tictactoe"), params: vec![] }));
+        assert_eq!(GameSpec::parse(" Skulls( first = P2, 1 ) "), Ok(GameSpec {
+            name: String::from("skulls"),
+            params: vec![(Some(String::from("first")), String::from("P2")), (None, String::from("1"))],
+        }));
+        assert!(GameSpec::parse("skulls(first=p2").is_err());
+        assert!(GameSpec::parse("skulls(=p2)").is_err());
+        assert!(GameSpec::parse("skulls(first=)").is_err());
+        assert!(GameSpec::parse("skulls(1,,2)").is_err());
+        assert!(GameSpec::parse("(1)").is_err());
+
+        assert_eq!(GameSpec::parse("match(game=skulls(p1, 1), 2)"), Ok(GameSpec {
+            name: String::from("match"),
+            params: vec![(Some(String::from("game")), String::from("skulls(p1, 1)")), (None, String::from("2"))],
+        }));
+        assert!(GameSpec::parse("match(skulls(p1), 2))").is_err());
+        assert!(GameSpec::parse("match(skulls(p1, 2)").is_err());
+    }
+
+    #[test]
+    fn builds_with_params() {
+        //stacking a skull or flower, and the first player can't bid yet
+        assert_eq!(build_game("skulls(first=p2,skulls=1,flowers=2)", FirstTurn), Ok((Player::P2, 2)));
+        assert_eq!(build_game("skulls(p2, 0, 2)", FirstTurn), Ok((Player::P2, 1)));
+        assert_eq!(build_game("matrix(example)", FirstTurn), Ok((Player::P1, 2)));
+        assert_eq!(build_game("tictactoe(width=5, height=4, win=4)", FirstTurn), Ok((Player::P1, 20)));
+        assert_eq!(build_game("matrix", FirstTurn), Ok((Player::P1, 3)));
+        assert_eq!(build_game("ocp(cards=13)", FirstTurn).map(|(_, n)| n), Ok(2));
+        assert_eq!(build_game("ocp(buckets=4)", FirstTurn).map(|(_, n)| n), Ok(2));
+        assert_eq!(build_game("ocp(cards=5, buckets=5, ante=2, bets=1/2/4, raises=3)", FirstTurn).map(|(_, n)| n), Ok(4));
+        assert_eq!(build_game("ocp(raises=0)", FirstTurn).map(|(_, n)| n), Ok(1));
+        assert_eq!(build_game("liars_dice(2, 4)", FirstTurn), Ok((Player::P1, 16)));
+        assert_eq!(build_game("goofspiel(6, random)", FirstTurn), Ok((Player::P1, 6)));
+        assert_eq!(build_game("blotto(soldiers=4, battlefields=3)", FirstTurn), Ok((Player::P1, 15)));
+        assert_eq!(build_game("skulls(p2, abstraction=round)", FirstTurn), Ok((Player::P2, 2)));
+        assert_eq!(build_game("skulls(bids=1/2/4)", FirstTurn), Ok((Player::P1, 2)));
+        assert_eq!(build_game("skulls(abstraction=round, bids=2)", FirstTurn), Ok((Player::P1, 2)));
+        assert_eq!(build_game("skulls(first=p3, players=4)", FirstTurn), Ok((Player::P2, 2)));
+        assert_eq!(build_game("skulls(points=3, bidding=simultaneous, own_skull=random, opponent_skull=flowers, rng=off)", FirstTurn), Ok((Player::P1, 2)));
+        assert_eq!(build_game("match(skulls(p2, 0, 2), rounds=2, stack=2.5)", FirstTurn), Ok((Player::P2, 1)));
+    }
+
+    #[test]
+    fn rejects_bad_specs() {
+        assert_eq!(build_game("chess", FirstTurn), Err(SpecError::UnknownGame(String::from("chess"))));
+        assert_eq!(build_game("skulls(cards=2)", FirstTurn),
+            Err(SpecError::UnknownParam { game: "skulls", param: String::from("cards") }));
+        assert_eq!(build_game("matrix(rps, none, 2)", FirstTurn), Err(SpecError::TooManyParams { game: "matrix" }));
+        assert!(matches!(build_game("skulls(first=p3)", FirstTurn), Err(SpecError::BadValue { param: "first", .. })));
+        assert!(matches!(build_game("skulls(skulls=-1)", FirstTurn), Err(SpecError::BadValue { param: "skulls", .. })));
+        assert!(matches!(build_game("skulls(players=7)", FirstTurn), Err(SpecError::BadValue { param: "players", .. })));
+        assert!(matches!(build_game("skulls(points=0)", FirstTurn), Err(SpecError::BadValue { param: "points", .. })));
+        assert!(matches!(build_game("skulls(bidding=shouting)", FirstTurn), Err(SpecError::BadValue { param: "bidding", .. })));
+        assert!(matches!(build_game("skulls(first=p4, players=3)", FirstTurn), Err(SpecError::BadValue { param: "first", .. })));
+        assert!(matches!(build_game("tictactoe(width=0)", FirstTurn), Err(SpecError::BadValue { param: "width", .. })));
+        assert!(matches!(build_game("tictactoe(3, 3, 4)", FirstTurn), Err(SpecError::BadValue { param: "win", .. })));
+        assert!(matches!(build_game("ocp(cards=1)", FirstTurn), Err(SpecError::BadValue { param: "cards", .. })));
+        assert!(matches!(build_game("ocp(buckets=14)", FirstTurn), Err(SpecError::BadValue { param: "buckets", .. })));
+        assert!(matches!(build_game("ocp(cards=5, buckets=6)", FirstTurn), Err(SpecError::BadValue { param: "buckets", .. })));
+        assert!(matches!(build_game("ocp(bets=2/0)", FirstTurn), Err(SpecError::BadValue { param: "bets", .. })));
+        assert!(matches!(build_game("ocp(bets=2/2)", FirstTurn), Err(SpecError::BadValue { param: "bets", .. })));
+        assert!(matches!(build_game("liars_dice(faces=10)", FirstTurn), Err(SpecError::BadValue { param: "faces", .. })));
+        assert!(matches!(build_game("goofspiel(order=descending)", FirstTurn), Err(SpecError::BadValue { param: "order", .. })));
+        assert!(matches!(build_game("blotto(battlefields=0)", FirstTurn), Err(SpecError::BadValue { param: "battlefields", .. })));
+        assert!(matches!(build_game("blotto(30, 10)", FirstTurn), Err(SpecError::BadValue { param: "battlefields", .. })));
+        assert!(build_game(&format!("blotto(soldiers={}, battlefields=1)", MAX_SOLDIERS), FirstTurn).is_ok());
+        assert!(matches!(build_game(&format!("blotto(soldiers={})", MAX_SOLDIERS + 1), FirstTurn), Err(SpecError::BadValue { param: "soldiers", .. })));
+        assert!(build_game(&format!("blotto(soldiers=1, battlefields={})", MAX_BATTLEFIELDS), FirstTurn).is_ok());
+        assert!(matches!(build_game(&format!("blotto(battlefields={})", MAX_BATTLEFIELDS + 1), FirstTurn), Err(SpecError::BadValue { param: "battlefields", .. })));
+        assert!(matches!(build_game("matrix(file=/nonexistent/payoffs.csv)", FirstTurn), Err(SpecError::BadFile { param: "file", .. })));
+        assert!(matches!(build_game("skulls(abstraction=cards)", FirstTurn), Err(SpecError::BadValue { param: "abstraction", .. })));
+        assert!(matches!(build_game("skulls(bids=1/two)", FirstTurn), Err(SpecError::BadValue { param: "bids", .. })));
+        assert!(matches!(build_game("match(rounds=0)", FirstTurn), Err(SpecError::BadValue { param: "rounds", .. })));
+        assert!(matches!(build_game("match(visibility=some)", FirstTurn), Err(SpecError::BadValue { param: "visibility", .. })));
+        assert_eq!(build_game("match(chess)", FirstTurn), Err(SpecError::UnknownGame(String::from("chess"))));
+        assert!(matches!(build_game("match(match)", FirstTurn), Err(SpecError::BadValue { param: "game", .. })));
+    }
+
+    #[test]
+    fn restores_records() {
+        let record = GameRecord::parse(r#"{"game": "skulls", "setup": {"first": "P2", "skulls": "1", "flowers": "2"}, "turns": [{"player": "P2", "action": "Stack { card: Flower }"}]}"#).unwrap();
+        //P1 can only stack because they haven't yet
+        assert_eq!(restore_game(&record, FirstTurn), Ok((Player::P1, 2)));
+
+        let record = GameRecord::new("chess");
+        assert_eq!(restore_game(&record, FirstTurn), Err(RecordError::UnknownGame(String::from("chess"))));
+    }
+
+    #[test]
+    fn loads_matrix_files() {
+        let path = std::env::temp_dir().join("registry_payoffs.csv");
+        std::fs::write(&path, "1, -1, 0\n-2, 2, 1\n").expect("Failed to write the payoffs");
+        let spec = format!("matrix(file={})", path.display());
+        //P1 picks one of the 2 rows
+        assert_eq!(build_game(&spec, FirstTurn), Ok((Player::P1, 2)));
+
+        //the loader's explanation makes it into the error
+        std::fs::write(&path, "1, -1, 0\n-2, 2\n").expect("Failed to write the payoffs");
+        let err = build_game(&spec, FirstTurn).unwrap_err();
+        assert!(matches!(err, SpecError::BadFile { param: "file", .. }));
+        assert!(err.to_string().contains("line 2 has 2 payoffs"), "{}", err);
+        std::fs::write(&path, "1, NaN\n").expect("Failed to write the payoffs");
+        assert!(matches!(build_game(&spec, FirstTurn), Err(SpecError::BadFile { param: "file", .. })));
+        std::fs::remove_file(&path).expect("Failed to remove the payoffs");
+    }
+
+    #[test]
+    fn builds_every_default() {
+        for game in GAMES.iter() {
+            assert!(build_game(game.name, FirstTurn).is_ok(), "Failed to build {}", game.name);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+
+use crate::game::{Game, UndoGame, IllegalMove, IllegalMoveReason, Player, Infoset, Observation, PublicState};
+
+/// Lets games with simultaneous turns be used where players have to alternate
+///
+/// At a simultaneous turn P1 picks first, then P2 picks without seeing P1's action,
+/// and then both actions are taken at once
+#[derive(Clone, Debug)]
+pub struct Sequential<G: Game> {
+    game: G,
+    /// P1's action at a simultaneous turn, waiting on P2's
+    pending: Option<G::Action>,
+}
+
+impl<G: Game> Sequential<G> {
+    pub fn new(game: G) -> Sequential<G> {
+        Sequential {
+            game,
+            pending: None,
+        }
+    }
+
+    /// Checks that the player can take the action at a simultaneous turn
+    fn check_simultaneous(&self, player: Player, action: &G::Action, actions: (Vec<G::Action>, Vec<G::Action>)) -> Result<(), IllegalMove<G::Action>> {
+        let expected = if self.pending.is_none() { Player::P1 } else { Player::P2 };
+        let reason = if player != expected {
+            Some(IllegalMoveReason::WrongPlayer { expected })
+        } else if !player.lens(&actions).iter().any(|a| self.game.action_id(a) == self.game.action_id(action)) {
+            Some(IllegalMoveReason::InvalidAction("action isn't allowed at this simultaneous turn"))
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => Err(IllegalMove::new(player, action, reason)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<G: Game + Clone> Game for Sequential<G> {
+    type Action = G::Action;
+
+    fn get_turn(&self) -> (Player, Vec<G::Action>) {
+        match (self.game.get_simultaneous_turn(), &self.pending) {
+            (Some((actions, _)), None) => (Player::P1, actions),
+            (Some((_, actions)), Some(_)) => (Player::P2, actions),
+            (None, _) => self.game.get_turn(),
+        }
+    }
+
+    fn take_turn(&mut self, player: Player, action: &G::Action) -> Result<(), IllegalMove<G::Action>> {
+        let actions = match self.game.get_simultaneous_turn() {
+            Some(actions) => actions,
+            None => return self.game.take_turn(player, action),
+        };
+        self.check_simultaneous(player, action, actions)?;
+
+        match &self.pending {
+            None => self.pending = Some(action.clone()),
+            Some(p1_action) => {
+                self.game.take_simultaneous_turn((p1_action, action))?;
+                self.pending = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn take_turn_outcomes(&self, player: Player, action: &G::Action) -> Result<Vec<(Self, f32)>, IllegalMove<G::Action>> {
+        let outcomes = match (self.game.get_simultaneous_turn(), &self.pending) {
+            (None, _) => self.game.take_turn_outcomes(player, action)?,
+            (Some(actions), Some(p1_action)) => {
+                self.check_simultaneous(player, action, actions)?;
+                self.game.take_simultaneous_turn_outcomes((p1_action, action))?
+            }
+            //P1's choice is only remembered, so there's no chance yet
+            (Some(_), None) => {
+                let mut game = self.clone();
+                game.take_turn(player, action)?;
+                return Ok(vec![(game, 1.0)]);
+            }
+        };
+        Ok(outcomes.into_iter().map(|(game, prob)| (Sequential::new(game), prob)).collect())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        self.game.num_action_ids()
+    }
+
+    fn action_id(&self, action: &G::Action) -> usize {
+        self.game.action_id(action)
+    }
+
+    fn parse_action(&self, action: &str) -> Option<G::Action> {
+        self.game.parse_action(action)
+    }
+
+    fn get_reward(&self) -> Option<f32> {
+        self.game.get_reward()
+    }
+
+    fn get_utility_bounds(&self) -> (f32, f32) {
+        self.game.get_utility_bounds()
+    }
+
+    /// P1's waiting action isn't part of either infoset, so P2 can't see it
+    fn get_infoset(&self, player: Player) -> Infoset {
+        self.game.get_infoset(player)
+    }
+
+    fn get_canonical_infoset(&self, player: Player) -> (Infoset, Option<Vec<usize>>) {
+        self.game.get_canonical_infoset(player)
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        self.game.get_public_observations()
+    }
+
+    fn get_private_observation(&self, player: Player) -> Observation {
+        self.game.get_private_observation(player)
+    }
+
+    fn get_public_state(&self) -> PublicState {
+        self.game.get_public_state()
+    }
+
+    fn get_summary_string(&self, player: Player) -> String {
+        self.game.get_summary_string(player)
+    }
+}
+
+/// Undoes a turn of a `Sequential` game
+pub enum SequentialUndo<G: UndoGame> {
+    /// An alternating turn of the game
+    Turn(G::Undo),
+    /// P1's choice at a simultaneous turn
+    Pending,
+    /// P2's choice at a simultaneous turn, which took the turn, along with P1's action
+    Simultaneous(G::Undo, G::Action),
+}
+
+impl<G: UndoGame + Clone> UndoGame for Sequential<G> {
+    type Undo = SequentialUndo<G>;
+
+    fn apply_turn(&mut self, player: Player, action: &G::Action) -> Result<SequentialUndo<G>, IllegalMove<G::Action>> {
+        let actions = match self.game.get_simultaneous_turn() {
+            Some(actions) => actions,
+            None => return Ok(SequentialUndo::Turn(self.game.apply_turn(player, action)?)),
+        };
+        self.check_simultaneous(player, action, actions)?;
+
+        match self.pending.take() {
+            None => {
+                self.pending = Some(action.clone());
+                Ok(SequentialUndo::Pending)
+            }
+            Some(p1_action) => match self.game.apply_simultaneous_turn((&p1_action, action)) {
+                Ok(undo) => Ok(SequentialUndo::Simultaneous(undo, p1_action)),
+                Err(err) => {
+                    self.pending = Some(p1_action);
+                    Err(err)
+                }
+            }
+        }
+    }
+
+    fn undo_turn(&mut self, undo: SequentialUndo<G>) {
+        match undo {
+            SequentialUndo::Turn(undo) => self.game.undo_turn(undo),
+            SequentialUndo::Pending => self.pending = None,
+            SequentialUndo::Simultaneous(undo, p1_action) => {
+                self.game.undo_turn(undo);
+                self.pending = Some(p1_action);
+            }
+        }
+    }
+}
+
+impl<G: Game> fmt::Display for Sequential<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        self.game.fmt(f)?;
+        if let Some(action) = &self.pending {
+            writeln!(f, "{} has picked {}", Player::P1, action)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MatrixGame;
+
+    #[test]
+    fn hides_p1_action() {
+        let mut game = Sequential::new(MatrixGame::new_rock_paper_scissors());
+        let infoset = game.get_infoset(Player::P2).hash;
+        assert_eq!(game.get_turn(), (Player::P1, vec![0, 1, 2]));
+        assert!(game.take_turn(Player::P2, &0).is_err());
+
+        game.take_turn(Player::P1, &2).unwrap();
+        assert_eq!(game.get_turn(), (Player::P2, vec![0, 1, 2]));
+        assert_eq!(game.get_infoset(Player::P2).hash, infoset);
+        assert_eq!(game.get_reward(), None);
+
+        assert!(game.take_turn(Player::P2, &3).is_err());
+        game.take_turn(Player::P2, &1).unwrap();
+        assert_eq!(game.get_reward(), Some(1.0));
+    }
+
+    #[test]
+    fn undoes_simultaneous_turns() {
+        let mut game = Sequential::new(MatrixGame::new_rock_paper_scissors());
+        let start = format!("{:?}", game);
+        let undo1 = game.apply_turn(Player::P1, &0).unwrap();
+        let middle = format!("{:?}", game);
+        let undo2 = game.apply_turn(Player::P2, &1).unwrap();
+        assert_eq!(game.get_reward(), Some(-1.0));
+
+        game.undo_turn(undo2);
+        assert_eq!(format!("{:?}", game), middle);
+        game.undo_turn(undo1);
+        assert_eq!(format!("{:?}", game), start);
+    }
+}
@@ -1,12 +1,68 @@
+//! Skull, also sold as Skulls and Roses
+//!
+//! With more than 2 players this is not real multi-player Skull. Seat 0 plays for P1 against a team of
+//! every other seat, which all play for P2 so the game stays zero-sum. Each of those seats only sees its
+//! own cards, so P2 doesn't have perfect recall and CFR isn't guaranteed to converge.
+
 use std::fmt;
 use rand::Rng;
 
-use crate::game::{Game, Player, Infoset};
+use crate::game::{Game, UndoGame, TensorGame, IllegalMove, IllegalMoveReason, Player, Infoset, Observation};
+use crate::game::{GameRecord, RecordedTurn, RecordGame, RecordError, InfosetAbstraction};
+use crate::game::{ActionAbstraction, pseudo_harmonic};
 
-/// no rng can make some analyses easier, but it's not accurate to the real game
-const FORCE_NO_RNG: bool = false;
+/// Most players that can sit at the table
+pub const MAX_PLAYERS: usize = 6;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+/// Place at the table, counting from 0 in turn order
+///
+/// With more than 2 players, P1 is seat 0 and every other seat plays for P2 as one team
+pub type Seat = usize;
+
+/// Everything that can change between versions of the game
+///
+/// The rules are part of every infoset, so strategies for different rules never share infosets
+#[derive(Copy, Clone, Debug, PartialEq, Hash)]
+pub struct SkullsRules {
+    /// From 2 to `MAX_PLAYERS`
+    pub num_players: usize,
+    /// Skulls in each player's starting hand
+    pub num_skulls: u8,
+    /// Flowers in each player's starting hand
+    pub num_flowers: u8,
+    /// Successful challenges needed to win
+    pub points_to_win: u8,
+    /// Whether everyone but the leader bids at once each round, seeing each other's bids only once the round is over,
+    /// instead of taking turns
+    ///
+    /// Both are the same with 2 players, as the leader never has a reason to raise their own bid
+    pub simultaneous_bidding: bool,
+    /// Whether flipping your own skull costs a flower while you have one, instead of a random card
+    pub self_flip_loses_flowers_first: bool,
+    /// Whether flipping an opponent's skull costs a flower while you have one, instead of a random card
+    ///
+    /// In the real game the skull's owner discards one of the challenger's cards without seeing them, which is random
+    pub opponent_flip_loses_flowers_first: bool,
+    /// Whether every lost card is a flower while there is one, which can make some analyses easier but isn't accurate to the real game
+    pub no_rng: bool,
+}
+
+impl Default for SkullsRules {
+    fn default() -> SkullsRules {
+        SkullsRules {
+            num_players: 2,
+            num_skulls: 1,
+            num_flowers: 3,
+            points_to_win: 2,
+            simultaneous_bidding: false,
+            self_flip_loses_flowers_first: true,
+            opponent_flip_loses_flowers_first: false,
+            no_rng: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Hash)]
 pub enum Card {
     Skull,
     Flower,
@@ -17,27 +73,34 @@ pub enum Action {
     Stack { card: Card },
     Bid { amount : u8 },
     Pass,
-    //flipping is automatic in 2 player games, so there's no need to encode
+    //flipping is automatic unless more than one opponent has cards left to flip, so it never comes up in 2 player games
+    Flip { seat: u8 },
+    /// Picks who starts the next round, after the challenger is eliminated by their own skull
+    Lead { seat: u8 },
 }
 
 #[derive(Clone, PartialEq, Debug)]
 enum GameState {
-    Stack { player: Player },
-    PreStack { player: Player },
-    Bid { amount: u8, leader: Player, player: Player, has_passed: bool },
-    End { winner: Player },
+    Stack { player: Seat },
+    PreStack { player: Seat },
+    /// With simultaneous bidding, the raises so far this round are hidden until it ends, and the history from `round_start` on is this round's bids
+    Bid { amount: u8, leader: Seat, player: Seat, passed: [bool; MAX_PLAYERS], raises: [u8; MAX_PLAYERS], round_start: usize },
+    /// The leader has flipped their own stack and picks whose card to flip next
+    Flip { leader: Seat, remaining: u8, revealed: [u8; MAX_PLAYERS] },
+    Lead { player: Seat },
+    End { winner: Seat },
 }
 
 
 #[derive(Clone, Debug)]
 enum HistoryEntry {
-    PlayerAction(Player, Action),
-    Flip(Player, Player, Card),
-    LoseCard(Player, Card),
-    GetPoint(Player),
+    PlayerAction(Seat, Action),
+    Flip(Seat, Seat, Card),
+    LoseCard(Seat, Card),
+    GetPoint(Seat),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Copy, Clone, Debug)]
 struct Hand {
     skulls: u8,
     flowers: u8,
@@ -45,224 +108,790 @@ struct Hand {
 
 #[derive(Clone, Debug)]
 pub struct Skulls {
-    hands: (Hand, Hand),
+    rules: SkullsRules,
+    hands: Vec<Hand>,
     game_state: GameState,
-    stacks: (Vec<Card>, Vec<Card>),
-    has_flipped: (bool, bool),
+    stacks: Vec<Vec<Card>>,
+    points: Vec<u8>,
     history: Vec<HistoryEntry>,
 }
 
 impl Skulls {
-    pub fn new() -> Skulls {
-        //I think we can get better results if P1 is always first
-        Skulls::manual_new(Player::P1, 1, 3)
+    /// Two player game with the default rules
+    pub fn manual_new(player: Player, num_skulls: u8, num_flowers: u8) -> Skulls {
+        let first = match player {
+            Player::P1 => 0,
+            Player::P2 => 1,
+        };
+        Skulls::with_rules(SkullsRules { num_skulls, num_flowers, ..SkullsRules::default() }, first)
     }
 
-    pub fn manual_new(player: Player, num_skulls: u8, num_flowers: u8) -> Skulls {
+    /// Game where the given seat stacks first, and with more than 2 players seat 0 plays against the rest
+    pub fn with_rules(rules: SkullsRules, first: Seat) -> Skulls {
+        assert!((2..=MAX_PLAYERS).contains(&rules.num_players), "Skulls needs 2 to {} players", MAX_PLAYERS);
+        let starting_hand = Hand { skulls: rules.num_skulls, flowers: rules.num_flowers };
         Skulls {
-            hands: (Hand { skulls: num_skulls, flowers: num_flowers}, Hand { skulls: num_skulls, flowers: num_flowers}),
-            game_state: GameState::PreStack { player },
-            stacks: (vec![], vec![]),
-            has_flipped: (false, false),
+            rules,
+            hands: vec![starting_hand; rules.num_players],
+            game_state: GameState::PreStack { player: first },
+            stacks: vec![vec![]; rules.num_players],
+            points: vec![0; rules.num_players],
             history: vec![],
         }
     }
-}
 
-impl Game for Skulls {
-    type Action = Action;
+    pub fn num_players(&self) -> usize {
+        self.rules.num_players
+    }
 
-    fn get_turn(&self) -> (Player, Vec<Action>) {
+    /// Seat that has to act, if the game isn't over
+    fn current_seat(&self) -> Option<Seat> {
         match self.game_state {
-            GameState::PreStack { player } => (player, hand_to_stack_actions(player.lens(&self.hands))),
-            GameState::Stack { player } => (player, [hand_to_stack_actions(player.lens(&self.hands)), board_to_bid_actions(0, &self.stacks)].concat()),
-            //if the player is the leader, then just pass, as there is no point in out-bidding yourself
-            GameState::Bid { amount, player, leader, .. } if leader != player => (player, board_to_bid_actions(amount, &self.stacks)),
-            GameState::Bid { player, .. } => (player, vec![Action::Pass]),
-            GameState::End { winner } => (winner, vec![]),
+            GameState::PreStack { player } | GameState::Stack { player } | GameState::Bid { player, .. } | GameState::Lead { player } => Some(player),
+            GameState::Flip { leader, .. } => Some(leader),
+            GameState::End { .. } => None,
         }
     }
 
-    fn take_turn(&mut self, player: Player, action: &Action) {
-        self.history.push(HistoryEntry::PlayerAction(player, *action));
+    /// Seat whose view the player has, which for P2 is whichever of their seats is acting
+    fn view_seat(&self, player: Player) -> Seat {
+        match (player, self.current_seat()) {
+            (Player::P1, _) => 0,
+            (Player::P2, Some(seat)) if seat != 0 => seat,
+            (Player::P2, _) => 1,
+        }
+    }
 
-        let cur_player = player;
-        let new_state = match (&self.game_state, action) {
-            (GameState::PreStack { player }, Action::Stack { card }) if *player == cur_player => {
-                play_card(*card, player.lens_mut(&mut self.stacks), player.lens_mut(&mut self.hands));
+    /// The seat to include in infosets, since seat 0 is on its own against everyone else once there are more than 2 players
+    ///
+    /// In 2 player games both players see themselves the same way, so strategies are shared between seats
+    fn seat_key(&self, seat: Seat) -> Option<Seat> {
+        if self.num_players() > 2 {
+            Some(seat)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the player still has any cards
+    fn is_active(&self, seat: Seat) -> bool {
+        let hand = &self.hands[seat];
+        hand.skulls + hand.flowers > 0 || !self.stacks[seat].is_empty()
+    }
+
+    /// Next seat after the given one that's still in the game
+    fn next_seat(&self, seat: Seat) -> Seat {
+        let num_players = self.num_players();
+        (1..=num_players).map(|i| (seat + i) % num_players)
+            .find(|&next| self.is_active(next))
+            .expect("Failed to find an active player")
+    }
 
-                if player.other().lens(&self.stacks).is_empty() {
-                    GameState::PreStack { player: player.other() }
+    /// Next seat after the given one that hasn't passed, or None if the leader is next
+    ///
+    /// Taking turns, this means everyone else has passed. Bidding at once, it means the round is over
+    fn next_bidder(&self, seat: Seat, leader: Seat, passed: &[bool; MAX_PLAYERS]) -> Option<Seat> {
+        let num_players = self.num_players();
+        (1..=num_players).map(|i| (seat + i) % num_players)
+            .take_while(|&next| next != leader)
+            .find(|&next| self.is_active(next) && !passed[next])
+    }
+
+    /// Seat that made the bid at the history index, if nobody else gets to see it yet
+    fn hidden_bid(&self, index: usize) -> Option<Seat> {
+        match (&self.game_state, &self.history[index]) {
+            (GameState::Bid { round_start, .. }, HistoryEntry::PlayerAction(seat, _)) if self.rules.simultaneous_bidding && index >= *round_start => Some(*seat),
+            _ => None,
+        }
+    }
+
+    /// Whether the challenger loses a random card, given whose skull they flipped
+    fn loses_random_card(&self, own_skull: bool) -> bool {
+        let flowers_first = self.rules.no_rng || if own_skull {
+            self.rules.self_flip_loses_flowers_first
+        } else {
+            self.rules.opponent_flip_loses_flowers_first
+        };
+        !flowers_first
+    }
+
+    /// Opponents with cards the leader hasn't flipped yet
+    fn flip_targets(&self, leader: Seat, revealed: &[u8; MAX_PLAYERS]) -> Vec<Seat> {
+        (0..self.num_players())
+            .filter(|&seat| seat != leader && (revealed[seat] as usize) < self.stacks[seat].len())
+            .collect()
+    }
+
+    fn active_seats(&self) -> Vec<Seat> {
+        (0..self.num_players()).filter(|&seat| self.is_active(seat)).collect()
+    }
+}
+
+impl Skulls {
+    /// Checks whether the player can take the action, without changing anything
+    fn check_turn(&self, cur_player: Player, action: &Action) -> Result<(), IllegalMoveReason> {
+        let num_cards = self.stacks.iter().map(|stack| stack.len()).sum::<usize>() as u8;
+        //the lowest legal bid if bidding is allowed
+        let (player, min_bid) = match self.game_state {
+            GameState::End { .. } => return Err(IllegalMoveReason::GameOver),
+            GameState::PreStack { player } | GameState::Lead { player } => (player, None),
+            GameState::Flip { leader, .. } => (leader, None),
+            GameState::Stack { player } => (player, Some(1)),
+            GameState::Bid { amount, player, .. } => (player, Some(amount + 1)),
+        };
+        if seat_player(player) != cur_player {
+            return Err(IllegalMoveReason::WrongPlayer { expected: seat_player(player) });
+        }
+
+        match (&self.game_state, action) {
+            (GameState::Flip { leader, revealed, .. }, Action::Flip { seat }) if self.flip_targets(*leader, revealed).contains(&(*seat as Seat)) => return Ok(()),
+            (GameState::Flip { .. }, _) => return Err(IllegalMoveReason::InvalidAction("the challenger has to flip an opponent's card")),
+            (GameState::Lead { .. }, Action::Lead { seat }) if self.active_seats().contains(&(*seat as Seat)) => return Ok(()),
+            (GameState::Lead { .. }, _) => return Err(IllegalMoveReason::InvalidAction("the eliminated challenger has to pick a player to start")),
+            (_, Action::Flip { .. }) => return Err(IllegalMoveReason::InvalidAction("can only flip cards during a challenge")),
+            (_, Action::Lead { .. }) => return Err(IllegalMoveReason::InvalidAction("can only pick who starts after eliminating yourself")),
+            _ => (),
+        }
+
+        let is_bidding = matches!(self.game_state, GameState::Bid { .. });
+        let hand = &self.hands[player];
+        match action {
+            Action::Stack { .. } if is_bidding => Err(IllegalMoveReason::InvalidAction("can't stack cards once bidding has started")),
+            Action::Stack { card: Card::Skull } if hand.skulls == 0 => Err(IllegalMoveReason::InvalidAction("no skulls left in hand")),
+            Action::Stack { card: Card::Flower } if hand.flowers == 0 => Err(IllegalMoveReason::InvalidAction("no flowers left in hand")),
+            Action::Stack { .. } => Ok(()),
+            Action::Bid { amount } => match min_bid {
+                None => Err(IllegalMoveReason::InvalidAction("can't bid before every player has stacked")),
+                Some(min_bid) if *amount < min_bid => Err(IllegalMoveReason::InvalidAction("bid must be higher than the current bid")),
+                Some(_) if *amount > num_cards => Err(IllegalMoveReason::InvalidAction("bid can't be more than the number of stacked cards")),
+                Some(_) => Ok(()),
+            },
+            Action::Pass if is_bidding => Ok(()),
+            Action::Pass => Err(IllegalMoveReason::InvalidAction("can only pass while bidding")),
+            Action::Flip { .. } | Action::Lead { .. } => unreachable!("Flipping and leading were already checked"),
+        }
+    }
+
+    /// Takes the turn, using the given function to pick which card is lost when flipping an opponent's skull
+    fn resolve_turn(&mut self, player: Player, action: &Action, choose_lost_card: &mut dyn FnMut(&Hand) -> Card) -> Result<(), IllegalMove<Action>> {
+        self.check_turn(player, action)
+            .map_err(|reason| IllegalMove::new(player, action, reason))?;
+
+        let seat = self.current_seat().expect("Legal turn when the game is over");
+        self.history.push(HistoryEntry::PlayerAction(seat, *action));
+
+        //the seat was already checked, so the state and action are all that matter
+        let new_state = match (self.game_state.clone(), action) {
+            (GameState::PreStack { player }, Action::Stack { card }) => {
+                play_card(*card, &mut self.stacks[player], &mut self.hands[player]);
+
+                let next = self.next_seat(player);
+                if self.stacks[next].is_empty() {
+                    GameState::PreStack { player: next }
                 } else {
-                    GameState::Stack { player: player.other() }
+                    GameState::Stack { player: next }
                 }
             },
 
-            (GameState::Stack { player }, Action::Stack { card }) if *player == cur_player => {
-                play_card(*card, player.lens_mut(&mut self.stacks), player.lens_mut(&mut self.hands));
-                GameState::Stack { player: player.other() }
+            (GameState::Stack { player }, Action::Stack { card }) => {
+                play_card(*card, &mut self.stacks[player], &mut self.hands[player]);
+                GameState::Stack { player: self.next_seat(player) }
             },
-            (GameState::Stack { player }, Action::Bid { amount }) if *player == cur_player => GameState::Bid { amount: *amount, leader: *player, player: player.other(), has_passed: false },
+            (GameState::Stack { player }, Action::Bid { amount }) => self.start_bidding_round(*amount, player, [false; MAX_PLAYERS]),
 
             //bidding is technically simultaneous, but for 2 player games bidding twice is basically the same as just bidding the higher amount
             //(unless you're just testing to see if the other person will bet, but then you have to get the timing exactly right and I've never seen that happen)
-            //so I'm just going to enforce taking turns while bidding
-            (GameState::Bid { player, .. }, Action::Bid { amount }) if *player == cur_player => GameState::Bid { amount: *amount, leader: *player, player: player.other(), has_passed: false},
-            (GameState::Bid { amount, leader, player, has_passed: false, }, Action::Pass) if *player == cur_player => /*GameState::Bid { amount: *amount, leader: *leader, player: player.other(), has_passed: true},
-            //end of round, both players passed
-            (GameState::Bid { amount, leader, player, has_passed: true, .. }, Action::Pass) if *player == cur_player =>*/ {
-
-                //restore hands based on stacks
-                for p in [Player::P1, Player::P2].iter() {
-                    for card in p.lens(&self.stacks) {
-                        match card {
-                            Card::Flower => p.lens_mut(&mut self.hands).flowers += 1,
-                            Card::Skull => p.lens_mut(&mut self.hands).skulls += 1,
-                        }
-                    }
+            //so by default we enforce taking turns while bidding
+            (GameState::Bid { player, passed, .. }, Action::Bid { amount }) if !self.rules.simultaneous_bidding => {
+                self.start_bidding_round(*amount, player, passed)
+            },
+            (GameState::Bid { amount, leader, player, passed, mut raises, round_start }, Action::Bid { amount: raise }) => {
+                raises[player] = *raise;
+                match self.next_bidder(player, leader, &passed) {
+                    Some(next) => GameState::Bid { amount, leader, player: next, passed, raises, round_start },
+                    None => self.end_bidding_round(amount, leader, passed, raises, choose_lost_card),
                 }
-
-                //get all cards to be flipped, leader first, then most recently played first
-                let flipped_cards = leader.lens(&self.stacks).iter().rev()
-                    .map(|c| { (leader, c) })
-                    .chain(
-                        leader.other().lens(&self.stacks).iter().rev()
-                        .map(|c| { (leader, c) })
-                    ).take(*amount as usize);
-                
-                //seach for skull
-                let mut found_skull = false;
-                for (player, card) in flipped_cards {
-                    //normally we'd record all card flips
-                    //but I'm trying out only recording skull flips to save memory
-                    //because that lets us figure out the result of flipping sequences with minimal information
-                    //actually, it might sufficient to make the game state unique, which means that our memory usage will be the same
-                    //self.history.push(HistoryEntry::Flip(*leader, *player, *card));
-                    if *card == Card::Skull {
-                        self.history.push(HistoryEntry::Flip(*leader, *player, *card));
-                        found_skull = true;
-                        let mut hand = leader.lens_mut(&mut self.hands);
-                        if FORCE_NO_RNG || *player == *leader {
-                            //remove flowers then skulls
-                            if hand.flowers > 0 {
-                                hand.flowers -= 1;
-                                self.history.push(HistoryEntry::LoseCard(*leader, Card::Flower));
-                            } else {
-                                hand.skulls -= 1;
-                                self.history.push(HistoryEntry::LoseCard(*leader, Card::Skull));
-                            }
-                        } else {
-                            //remove randomly
-                            let num_cards = hand.flowers + hand.skulls;
-                            let remove_index = rand::thread_rng().gen_range(0, num_cards);
-                            if remove_index < hand.flowers {
-                                hand.flowers -= 1;
-                                self.history.push(HistoryEntry::LoseCard(*leader, Card::Flower));
-                            } else {
-                                hand.skulls -= 1;
-                                self.history.push(HistoryEntry::LoseCard(*leader, Card::Skull));
-                            }
-                        }
-                        break;
-                    }
+            },
+            (GameState::Bid { amount, leader, player, mut passed, raises, round_start }, Action::Pass) => {
+                passed[player] = true;
+                match self.next_bidder(player, leader, &passed) {
+                    Some(next) => GameState::Bid { amount, leader, player: next, passed, raises, round_start },
+                    None => self.end_bidding_round(amount, leader, passed, raises, choose_lost_card),
                 }
+            },
+
+            (GameState::Flip { leader, remaining, revealed }, Action::Flip { seat }) => {
+                self.flip_cards(leader, remaining, revealed, Some(*seat as Seat), choose_lost_card)
+            },
 
-                //reset stacks
-                self.stacks.0.clear();
-                self.stacks.1.clear();
+            (GameState::Lead { .. }, Action::Lead { seat }) => GameState::PreStack { player: *seat as Seat },
 
-                //new game state
-                if found_skull {
-                    let hand = leader.lens(&self.hands);
-                    if hand.skulls == 0 && hand.flowers == 0 {
-                        GameState::End { winner: leader.other() }
-                    } else {
-                        GameState::PreStack { player: *leader }
-                    }
-                } else if *leader.lens(&self.has_flipped) {
-                        GameState::End { winner: *leader }
-                } else {
-                    *leader.lens_mut(&mut self.has_flipped) = true;
-                    self.history.push(HistoryEntry::GetPoint(*leader));
-                    GameState::PreStack { player: *leader }
+            _ => unreachable!("Legal action didn't match the current state"),
+        };
+
+        self.game_state = new_state;
+        Ok(())
+    }
+
+    /// Starts bidding from the new leader, which is a new round when bidding at once
+    fn start_bidding_round(&self, amount: u8, leader: Seat, passed: [bool; MAX_PLAYERS]) -> GameState {
+        //the old leader hasn't passed, so there's always someone to bid next
+        let player = self.next_bidder(leader, leader, &passed).expect("Failed to find a player who hasn't passed");
+        GameState::Bid { amount, leader, player, passed, raises: [0; MAX_PLAYERS], round_start: self.history.len() }
+    }
+
+    /// Reveals the round's raises, where the highest takes the lead and ties go to whoever is first after the leader
+    ///
+    /// If nobody raised, the leader has to flip. Taking turns, this only happens once everyone else has passed
+    fn end_bidding_round(&mut self, amount: u8, leader: Seat, passed: [bool; MAX_PLAYERS], raises: [u8; MAX_PLAYERS], choose_lost_card: &mut dyn FnMut(&Hand) -> Card) -> GameState {
+        let num_players = self.num_players();
+        let highest = (1..num_players).map(|i| (leader + i) % num_players)
+            .fold(None, |highest: Option<Seat>, seat| match highest {
+                Some(best) if raises[best] >= raises[seat] => Some(best),
+                _ if raises[seat] > 0 => Some(seat),
+                _ => highest,
+            });
+        match highest {
+            Some(seat) => self.start_bidding_round(raises[seat], seat, passed),
+            None => self.flip_cards(leader, amount, [0; MAX_PLAYERS], None, choose_lost_card),
+        }
+    }
+
+    /// Flips cards until finding a skull or meeting the bid, stopping early if the leader has to pick whose card is next
+    ///
+    /// The leader always flips their own stack first, most recently played first
+    fn flip_cards(&mut self, leader: Seat, mut remaining: u8, mut revealed: [u8; MAX_PLAYERS], mut target: Option<Seat>, choose_lost_card: &mut dyn FnMut(&Hand) -> Card) -> GameState {
+        loop {
+            let seat = match target.take() {
+                Some(seat) => seat,
+                None if (revealed[leader] as usize) < self.stacks[leader].len() => leader,
+                None => match self.flip_targets(leader, &revealed).as_slice() {
+                    //there's no choice to make with only one stack left
+                    [seat] => *seat,
+                    _ => return GameState::Flip { leader, remaining, revealed },
+                },
+            };
+
+            let stack = &self.stacks[seat];
+            let card = stack[stack.len() - 1 - revealed[seat] as usize];
+            revealed[seat] += 1;
+            remaining -= 1;
+            if card == Card::Skull {
+                return self.end_challenge(leader, Some(seat), choose_lost_card);
+            } else if remaining == 0 {
+                return self.end_challenge(leader, None, choose_lost_card);
+            }
+        }
+    }
+
+    /// Ends the round after the leader's challenge, given whose skull they flipped if any
+    fn end_challenge(&mut self, leader: Seat, skull_owner: Option<Seat>, choose_lost_card: &mut dyn FnMut(&Hand) -> Card) -> GameState {
+        //restore hands based on stacks
+        for (stack, hand) in self.stacks.iter_mut().zip(self.hands.iter_mut()) {
+            for card in stack.drain(..) {
+                match card {
+                    Card::Flower => hand.flowers += 1,
+                    Card::Skull => hand.skulls += 1,
                 }
+            }
+        }
+
+        let owner = match skull_owner {
+            Some(owner) => owner,
+            None if self.points[leader] + 1 >= self.rules.points_to_win => return GameState::End { winner: leader },
+            None => {
+                self.points[leader] += 1;
+                self.history.push(HistoryEntry::GetPoint(leader));
+                return GameState::PreStack { player: leader };
             },
+        };
 
-            _ => panic!("Tried to take turn with illegal action"),
+        //normally we'd record all card flips
+        //but I'm trying out only recording skull flips to save memory
+        //because that lets us figure out the result of flipping sequences with minimal information
+        //actually, it might sufficient to make the game state unique, which means that our memory usage will be the same
+        self.history.push(HistoryEntry::Flip(leader, owner, Card::Skull));
+        //the skull belongs to whoever stacked it, since the rules can treat flipping your own skull differently
+        let is_random = self.loses_random_card(owner == leader);
+        let hand = &mut self.hands[leader];
+        let card = if is_random {
+            //remove randomly
+            choose_lost_card(hand)
+        } else {
+            //remove flowers then skulls
+            if hand.flowers > 0 {
+                Card::Flower
+            } else {
+                Card::Skull
+            }
         };
+        match card {
+            Card::Flower => hand.flowers -= 1,
+            Card::Skull => hand.skulls -= 1,
+        }
+        self.history.push(HistoryEntry::LoseCard(leader, card));
 
-        self.game_state = new_state;
+        if self.is_active(leader) {
+            return GameState::PreStack { player: leader };
+        }
+        //once seat 0 is out every seat left plays for P2, so the game is already decided
+        if leader == 0 {
+            let winner = if owner == leader { self.next_seat(leader) } else { owner };
+            return GameState::End { winner };
+        }
+        //the leader is eliminated, so the owner of the skull starts, or the leader picks if it was their own
+        match self.active_seats().as_slice() {
+            [winner] => GameState::End { winner: *winner },
+            _ if owner == leader => GameState::Lead { player: leader },
+            _ => GameState::PreStack { player: owner },
+        }
+    }
+}
+
+impl Game for Skulls {
+    type Action = Action;
+
+    fn get_turn(&self) -> (Player, Vec<Action>) {
+        let (seat, actions) = match self.game_state {
+            GameState::PreStack { player } => (player, hand_to_stack_actions(&self.hands[player])),
+            GameState::Stack { player } => (player, [hand_to_stack_actions(&self.hands[player]), board_to_bid_actions(0, &self.stacks)].concat()),
+            //if the player is the leader, then just pass, as there is no point in out-bidding yourself
+            GameState::Bid { amount, player, leader, .. } if leader != player => (player, board_to_bid_actions(amount, &self.stacks)),
+            GameState::Bid { player, .. } => (player, vec![Action::Pass]),
+            GameState::Flip { leader, revealed, .. } => {
+                (leader, self.flip_targets(leader, &revealed).into_iter().map(|seat| Action::Flip { seat: seat as u8 }).collect())
+            },
+            GameState::Lead { player } => (player, self.active_seats().into_iter().map(|seat| Action::Lead { seat: seat as u8 }).collect()),
+            GameState::End { winner } => (winner, vec![]),
+        };
+        (seat_player(seat), actions)
+    }
+
+    fn take_turn(&mut self, player: Player, action: &Action) -> Result<(), IllegalMove<Action>> {
+        self.resolve_turn(player, action, &mut random_lost_card)
+    }
+
+    fn take_turn_outcomes(&self, player: Player, action: &Action) -> Result<Vec<(Skulls, f32)>, IllegalMove<Action>> {
+        let mut outcomes = vec![];
+        for lost_card in [Card::Flower, Card::Skull].iter() {
+            let mut game = self.clone();
+            let mut prob = None;
+            game.resolve_turn(player, action, &mut |hand| {
+                let num_cards = (hand.flowers + hand.skulls) as f32;
+                let (num_lost_card, other_card) = match lost_card {
+                    Card::Flower => (hand.flowers, Card::Skull),
+                    Card::Skull => (hand.skulls, Card::Flower),
+                };
+                prob = Some(num_lost_card as f32 / num_cards);
+                //a card the hand doesn't have can't be taken away, and this outcome gets dropped anyway
+                if num_lost_card > 0 { *lost_card } else { other_card }
+            })?;
+
+            match prob {
+                //chance wasn't involved, so there's only one outcome
+                None => return Ok(vec![(game, 1.0)]),
+                Some(prob) if prob > 0.0 => outcomes.push((game, prob)),
+                Some(_) => (),
+            }
+        }
+        Ok(outcomes)
+    }
+
+    fn num_action_ids(&self) -> usize {
+        //stacking either card, passing, and bidding up to every card in every stack,
+        //then flipping and picking each seat, which only comes up with more than 2 players
+        let seat_actions = if self.num_players() > 2 { 2 * self.num_players() } else { 0 };
+        3 + self.max_bid() + seat_actions
+    }
+
+    fn action_id(&self, action: &Action) -> usize {
+        match action {
+            Action::Stack { card: Card::Flower } => 0,
+            Action::Stack { card: Card::Skull } => 1,
+            Action::Pass => 2,
+            Action::Bid { amount } => 2 + *amount as usize,
+            Action::Flip { seat } => 3 + self.max_bid() + *seat as usize,
+            Action::Lead { seat } => 3 + self.max_bid() + self.num_players() + *seat as usize,
+        }
+    }
+
+    fn parse_action(&self, action: &str) -> Option<Action> {
+        let parse_field = |prefix: &str| -> Option<u8> {
+            action.strip_prefix(prefix)?.strip_suffix(" }")?.parse().ok()
+        };
+        match action {
+            "Stack { card: Flower }" => Some(Action::Stack { card: Card::Flower }),
+            "Stack { card: Skull }" => Some(Action::Stack { card: Card::Skull }),
+            "Pass" => Some(Action::Pass),
+            _ => parse_field("Bid { amount: ").map(|amount| Action::Bid { amount })
+                .or_else(|| parse_field("Flip { seat: ").map(|seat| Action::Flip { seat }))
+                .or_else(|| parse_field("Lead { seat: ").map(|seat| Action::Lead { seat })),
+        }
     }
 
     fn get_reward(&self) -> Option<f32> {
         if let GameState::End { winner } = self.game_state {
-            Some(*winner.lens(&(1.0, -1.0)))
+            Some(*seat_player(winner).lens(&(1.0, -1.0)))
         } else {
             None
         }
     }
 
     fn get_infoset(&self, player: Player) -> Infoset {
-        let infoset : Vec<(u8, u8, u8, u8)> = self.history.iter().map(|entry| {
+        let seat = self.view_seat(player);
+        let infoset : Vec<(Seat, Seat, u8, u8)> = self.history.iter().enumerate().map(|(i, entry)| {
             match *entry {
+                //nobody else knows what the player bid yet
+                _ if self.hidden_bid(i).filter(|p| *p != seat).is_some() => (entry_seat(entry), entry_seat(entry), 8, 0),
                 HistoryEntry::GetPoint(p) => (p, p, 0, 0),
                 HistoryEntry::PlayerAction(p, action) => {
                     //player knows everything they did
-                    if p == player {
+                    if p == seat {
                         match action {
                             Action::Bid { amount } => (p, p, 1, amount),
                             Action::Pass => (p, p, 2, 0),
                             //i'm reserving 0 for unknown
                             Action::Stack { card } => (p, p, 3, 1 + card as u8),
+                            Action::Flip { seat: target } => (p, target as Seat, 6, 0),
+                            Action::Lead { seat: target } => (p, target as Seat, 7, 0),
                         }
                     } else {
                         match action {
                             Action::Bid { amount } => (p, p, 1, amount),
                             Action::Pass => (p, p, 2, 0),
                             Action::Stack { .. } => (p, p, 3, 0),
+                            Action::Flip { seat: target } => (p, target as Seat, 6, 0),
+                            Action::Lead { seat: target } => (p, target as Seat, 7, 0),
                         }
                     }
                 },
                 HistoryEntry::Flip(flipper, target, card) => (flipper, target, 4, card as u8),
                 //again, reserving 0 for unknown
-                HistoryEntry::LoseCard(flipper, card) if flipper == player => (flipper, flipper, 5, 1 + card as u8),
+                HistoryEntry::LoseCard(flipper, card) if flipper == seat => (flipper, flipper, 5, 1 + card as u8),
                 HistoryEntry::LoseCard(flipper, _) => (flipper, flipper, 5, 0),
             }
         })
-        //change perspective so the player thinks they're P1
-        .map(|(p1, p2, x, y)| (player.view(p1) as u8, player.view(p2) as u8, x, y))
+        //change perspective so the player thinks they're in the first seat
+        .map(|(p1, p2, x, y)| (self.relative_seat(seat, p1), self.relative_seat(seat, p2), x, y))
         .collect();
-        Infoset::new(infoset)
+        Infoset::new((self.rules, self.seat_key(seat), infoset))
+    }
+
+    fn get_public_observations(&self) -> Vec<Observation> {
+        self.history.iter().enumerate().map(|(i, entry)| {
+            let observation = match *entry {
+                //everyone sees that the player bid, but not what they bid
+                _ if self.hidden_bid(i).is_some() => (entry_seat(entry), entry_seat(entry), 8, 0),
+                HistoryEntry::GetPoint(p) => (p, p, 0, 0),
+                HistoryEntry::PlayerAction(p, Action::Bid { amount }) => (p, p, 1, amount),
+                HistoryEntry::PlayerAction(p, Action::Pass) => (p, p, 2, 0),
+                //the stacked card is private
+                HistoryEntry::PlayerAction(p, Action::Stack { .. }) => (p, p, 3, 0),
+                HistoryEntry::PlayerAction(p, Action::Flip { seat }) => (p, seat as Seat, 6, 0),
+                HistoryEntry::PlayerAction(p, Action::Lead { seat }) => (p, seat as Seat, 7, 0),
+                HistoryEntry::Flip(flipper, target, card) => (flipper, target, 4, card as u8),
+                //the lost card is private
+                HistoryEntry::LoseCard(flipper, _) => (flipper, flipper, 5, 0),
+            };
+            Observation::new(observation)
+        }).collect()
+    }
+
+    fn get_private_observation(&self, player: Player) -> Observation {
+        //the only private information is which cards the player stacked and lost, and their bid this round when bidding at once
+        //indexed by position in the history so they line up with the public observations
+        let seat = self.view_seat(player);
+        let cards: Vec<(usize, Card)> = self.history.iter().enumerate().filter_map(|(i, entry)| {
+            match *entry {
+                HistoryEntry::PlayerAction(p, Action::Stack { card }) if p == seat => Some((i, card)),
+                HistoryEntry::LoseCard(p, card) if p == seat => Some((i, card)),
+                _ => None,
+            }
+        }).collect();
+        let bids: Vec<(usize, usize)> = self.history.iter().enumerate().filter_map(|(i, entry)| {
+            match *entry {
+                HistoryEntry::PlayerAction(_, action) if self.hidden_bid(i) == Some(seat) => Some((i, self.action_id(&action))),
+                _ => None,
+            }
+        }).collect();
+        Observation::new((self.seat_key(seat), cards, bids))
     }
 
     fn get_summary_string(&self, player: Player) -> String {
-        self.history.iter().map(|entry| {
+        let seat = self.view_seat(player);
+        self.history.iter().enumerate().map(|(i, entry)| {
             match *entry {
-                HistoryEntry::GetPoint(p) => format!("{} got a point", p),
+                _ if self.hidden_bid(i).filter(|p| *p != seat).is_some() => format!("{} bid... something", seat_name(entry_seat(entry))),
+                HistoryEntry::GetPoint(p) => format!("{} got a point", seat_name(p)),
                 HistoryEntry::PlayerAction(p, action) => {
-                    //player knows everything they did
-                    if p == player {
-                        match action {
-                            Action::Bid { amount } => format!("{} bid {}", p, amount),
-                            Action::Pass => format!("{} did not bid", p),
-                            Action::Stack { card } => format!("{} stacked {}", p, card),
-                        }
-                    } else {
-                        match action {
-                            Action::Bid { amount } => format!("{} bid {}", p, amount),
-                            Action::Pass => format!("{} did not bid", p),
-                            Action::Stack { .. } => format!("{} stacked... something", p),
-                        }
+                    let name = seat_name(p);
+                    match action {
+                        Action::Bid { amount } => format!("{} bid {}", name, amount),
+                        Action::Pass => format!("{} did not bid", name),
+                        //player knows everything they did
+                        Action::Stack { card } if p == seat => format!("{} stacked {}", name, card),
+                        Action::Stack { .. } => format!("{} stacked... something", name),
+                        Action::Flip { seat: target } => format!("{} chose to flip {}'s stack", name, seat_name(target as Seat)),
+                        Action::Lead { seat: target } => format!("{} chose {} to start", name, seat_name(target as Seat)),
                     }
                 },
-                HistoryEntry::Flip(flipper, target, card) => format!("{} flipped {}'s {}", flipper, target, card),
-                HistoryEntry::LoseCard(flipper, card) if flipper == player => format!("{} flipped a skull and lost {}", flipper, card),
-                HistoryEntry::LoseCard(flipper, _) => format!("{} flipped a skull and lost... something", flipper),
+                HistoryEntry::Flip(flipper, target, card) => format!("{} flipped {}'s {}", seat_name(flipper), seat_name(target), card),
+                HistoryEntry::LoseCard(flipper, card) if flipper == seat => format!("{} flipped a skull and lost {}", seat_name(flipper), card),
+                HistoryEntry::LoseCard(flipper, _) => format!("{} flipped a skull and lost... something", seat_name(flipper)),
             }
         }).collect::<Vec<String>>().join("\n")
     }
 }
 
+impl Skulls {
+    fn num_starting_cards(&self) -> usize {
+        (self.rules.num_skulls + self.rules.num_flowers) as usize
+    }
+
+    /// Highest bid possible, with every card stacked
+    fn max_bid(&self) -> usize {
+        self.num_players() * self.num_starting_cards()
+    }
+
+    /// Position of the seat counting from the viewer's seat in turn order
+    fn relative_seat(&self, viewer: Seat, seat: Seat) -> Seat {
+        (seat + self.num_players() - viewer) % self.num_players()
+    }
+}
+
+/// Player that the seat plays for
+fn seat_player(seat: Seat) -> Player {
+    match seat {
+        0 => Player::P1,
+        _ => Player::P2,
+    }
+}
+
+/// Name of the seat, which matches the player's name in 2 player games
+fn seat_name(seat: Seat) -> String {
+    format!("P{}", seat + 1)
+}
+
+fn entry_seat(entry: &HistoryEntry) -> Seat {
+    match *entry {
+        HistoryEntry::PlayerAction(seat, _) | HistoryEntry::Flip(seat, _, _) | HistoryEntry::LoseCard(seat, _) | HistoryEntry::GetPoint(seat) => seat,
+    }
+}
+
+/// Parses a seat's name, like P3, ignoring case
+pub fn parse_seat(name: &str) -> Option<Seat> {
+    let number: Seat = name.to_lowercase().strip_prefix('p')?.parse().ok()?;
+    number.checked_sub(1)
+}
+
+/// Abstraction that only keeps the current round and who has a point, forgetting how the game got there
+#[derive(Clone, Copy, Debug)]
+pub struct RoundSummary;
+
+impl InfosetAbstraction<Skulls> for RoundSummary {
+    fn get_abstract_infoset(&self, game: &Skulls, player: Player) -> Infoset {
+        let seat = game.view_seat(player);
+        let hand = &game.hands[seat];
+        let num_players = game.num_players();
+        //everyone else in turn order from the player
+        let others: Vec<(u8, usize, u8)> = (1..num_players).map(|i| (seat + i) % num_players)
+            .map(|other| {
+                let other_hand = &game.hands[other];
+                (other_hand.skulls + other_hand.flowers, game.stacks[other].len(), game.points[other])
+            })
+            .collect();
+        let state = match game.game_state {
+            GameState::PreStack { player: p } => (0, p == seat, 0, false),
+            GameState::Stack { player: p } => (1, p == seat, 0, false),
+            GameState::Bid { amount, leader, player: p, .. } => (2, p == seat, amount, leader == seat),
+            GameState::Flip { leader, remaining, .. } => (3, leader == seat, remaining, leader == seat),
+            GameState::Lead { player: p } => (4, p == seat, 0, false),
+            GameState::End { .. } => (5, false, 0, false),
+        };
+        //which stacks are left to flip decides the leader's options
+        let revealed: Vec<u8> = match game.game_state {
+            GameState::Flip { revealed, .. } => (0..num_players).map(|i| revealed[(seat + i) % num_players]).collect(),
+            _ => vec![],
+        };
+        Infoset::new((
+            game.rules,
+            game.seat_key(seat),
+            (hand.skulls, hand.flowers),
+            &game.stacks[seat],
+            others,
+            state,
+            revealed,
+            game.points[seat],
+        ))
+    }
+}
+
+/// Action abstraction that only keeps some bid amounts, along with the lowest legal bid so a player can always raise
+///
+/// Other bids are translated pseudo-harmonically onto the nearest kept bids
+#[derive(Clone, Debug)]
+pub struct BidSizes {
+    pub amounts: Vec<u8>,
+}
+
+impl ActionAbstraction<Skulls> for BidSizes {
+    fn restrict(&self, _game: &Skulls, _player: Player, actions: Vec<Action>) -> Vec<Action> {
+        let min_bid = actions.iter().filter_map(|action| match action {
+            Action::Bid { amount } => Some(*amount),
+            _ => None,
+        }).min();
+        actions.into_iter().filter(|action| match action {
+            Action::Bid { amount } => Some(*amount) == min_bid || self.amounts.contains(amount),
+            _ => true,
+        }).collect()
+    }
+
+    fn translate(&self, _game: &Skulls, _player: Player, action: &Action, kept: &[Action]) -> Vec<(Action, f32)> {
+        let amount = match action {
+            Action::Bid { amount } => *amount,
+            _ => return vec![(*action, 1.0)],
+        };
+        let kept_amounts = kept.iter().filter_map(|action| match action {
+            Action::Bid { amount } => Some(*amount),
+            _ => None,
+        });
+        let below = kept_amounts.clone().filter(|a| *a < amount).max();
+        let above = kept_amounts.filter(|a| *a > amount).min();
+        match (below, above) {
+            (Some(below), Some(above)) => {
+                let prob = pseudo_harmonic(below as f32, above as f32, amount as f32);
+                vec![(Action::Bid { amount: below }, prob), (Action::Bid { amount: above }, 1.0 - prob)]
+            }
+            (Some(amount), None) | (None, Some(amount)) => vec![(Action::Bid { amount }, 1.0)],
+            //the bid can't be outbid in this game, so there's nothing to take it as
+            (None, None) => vec![],
+        }
+    }
+}
+
+impl TensorGame for Skulls {
+    fn tensor_size(&self) -> usize {
+        //the player's hand, a one-hot card for each place in their stack,
+        //every opponent's stack and hand sizes, the state, the bid, and who has a point
+        let num_players = self.num_players();
+        2 + 2 * self.num_starting_cards() + 2 * (num_players - 1) + 6 + 3 + num_players
+    }
+
+    /// This only covers the current round and the points, not the full history
+    fn get_infoset_tensor(&self, player: Player) -> Vec<f32> {
+        let mut tensor = Vec::with_capacity(self.tensor_size());
+        let seat = self.view_seat(player);
+        let num_players = self.num_players();
+        //everyone in turn order from the player
+        let seats: Vec<Seat> = (0..num_players).map(|i| (seat + i) % num_players).collect();
+
+        let hand = &self.hands[seat];
+        tensor.push(hand.skulls as f32);
+        tensor.push(hand.flowers as f32);
+
+        let stack = &self.stacks[seat];
+        for i in 0..self.num_starting_cards() {
+            match stack.get(i) {
+                Some(Card::Flower) => tensor.extend_from_slice(&[1.0, 0.0]),
+                Some(Card::Skull) => tensor.extend_from_slice(&[0.0, 1.0]),
+                None => tensor.extend_from_slice(&[0.0, 0.0]),
+            }
+        }
+
+        for other in seats[1..].iter() {
+            let other_hand = &self.hands[*other];
+            tensor.push(self.stacks[*other].len() as f32);
+            tensor.push((other_hand.skulls + other_hand.flowers) as f32);
+        }
+
+        let (state_index, to_move, amount, is_leader) = match self.game_state {
+            GameState::PreStack { player: p } => (0, p == seat, 0, false),
+            GameState::Stack { player: p } => (1, p == seat, 0, false),
+            GameState::Bid { amount, leader, player: p, .. } => (2, p == seat, amount, leader == seat),
+            GameState::Flip { leader, remaining, .. } => (3, leader == seat, remaining, leader == seat),
+            GameState::Lead { player: p } => (4, p == seat, 0, false),
+            GameState::End { .. } => (5, false, 0, false),
+        };
+        let mut state = [0.0; 6];
+        state[state_index] = 1.0;
+        tensor.extend_from_slice(&state);
+        tensor.push(if to_move { 1.0 } else { 0.0 });
+        tensor.push(amount as f32);
+        tensor.push(if is_leader { 1.0 } else { 0.0 });
+
+        for s in seats.iter() {
+            tensor.push(self.points[*s] as f32);
+        }
+
+        tensor
+    }
+}
+
+/// How a turn changed the stacks
+#[derive(Debug)]
+enum StackChange {
+    Unchanged,
+    Pushed(Seat),
+    //the stacks are only cleared at the end of a round, which is rare enough to just save them
+    Saved(Vec<Vec<Card>>),
+}
+
+#[derive(Debug)]
+pub struct Undo {
+    game_state: GameState,
+    history_len: usize,
+    hands: Vec<Hand>,
+    points: Vec<u8>,
+    stack_change: StackChange,
+}
+
+impl UndoGame for Skulls {
+    type Undo = Undo;
+
+    fn apply_turn(&mut self, player: Player, action: &Action) -> Result<Undo, IllegalMove<Action>> {
+        let stack_change = match (action, self.current_seat()) {
+            (Action::Stack { .. }, Some(seat)) => StackChange::Pushed(seat),
+            //passing and flipping can end the round
+            (Action::Pass, _) | (Action::Flip { .. }, _) => StackChange::Saved(self.stacks.clone()),
+            _ => StackChange::Unchanged,
+        };
+        let undo = Undo {
+            game_state: self.game_state.clone(),
+            history_len: self.history.len(),
+            hands: self.hands.clone(),
+            points: self.points.clone(),
+            stack_change,
+        };
+        self.take_turn(player, action)?;
+        Ok(undo)
+    }
+
+    fn undo_turn(&mut self, undo: Undo) {
+        match undo.stack_change {
+            StackChange::Unchanged => (),
+            StackChange::Pushed(seat) => {
+                self.stacks[seat].pop();
+            },
+            StackChange::Saved(stacks) => self.stacks = stacks,
+        }
+        self.game_state = undo.game_state;
+        self.history.truncate(undo.history_len);
+        self.hands = undo.hands;
+        self.points = undo.points;
+    }
+}
+
+/// Picks a card to lose uniformly at random from the hand
+fn random_lost_card(hand: &Hand) -> Card {
+    let num_cards = hand.flowers + hand.skulls;
+    let remove_index = rand::thread_rng().gen_range(0, num_cards);
+    if remove_index < hand.flowers {
+        Card::Flower
+    } else {
+        Card::Skull
+    }
+}
+
 fn hand_to_stack_actions(hand: &Hand) -> Vec<Action> {
     let mut actions = vec![];
     if hand.flowers > 0 {
@@ -274,9 +903,8 @@ fn hand_to_stack_actions(hand: &Hand) -> Vec<Action> {
     actions
 }
 
-fn board_to_bid_actions(current_bid: u8, stacks: &(Vec<Card>, Vec<Card>)) -> Vec<Action> {
-    let (stack1, stack2) = stacks;
-    let num_cards = (stack1.len() + stack2.len()) as u8;
+fn board_to_bid_actions(current_bid: u8, stacks: &[Vec<Card>]) -> Vec<Action> {
+    let num_cards = stacks.iter().map(|stack| stack.len()).sum::<usize>() as u8;
 
     let maybe_pass = if current_bid == 0 {
         None
@@ -299,12 +927,116 @@ fn play_card(card: Card, stack: &mut Vec<Card>, hand: &mut Hand) {
 
 }
 
+impl RecordGame for Skulls {
+    /// Cards lost to flipping an opponent's skull are recorded as chance
+    fn get_record(&self) -> GameRecord {
+        let first_seat = self.history.iter()
+            .find_map(|entry| match entry {
+                HistoryEntry::PlayerAction(seat, _) => Some(*seat),
+                _ => None,
+            })
+            .or_else(|| self.current_seat())
+            .unwrap_or(0);
+
+        let mut record = GameRecord::new("skulls");
+        record.add_setup("players", self.num_players());
+        record.add_setup("first", seat_name(first_seat));
+        record.add_setup("skulls", self.rules.num_skulls);
+        record.add_setup("flowers", self.rules.num_flowers);
+        record.add_setup("points", self.rules.points_to_win);
+        record.add_setup("simultaneous_bidding", self.rules.simultaneous_bidding);
+        record.add_setup("self_flip_loses_flowers_first", self.rules.self_flip_loses_flowers_first);
+        record.add_setup("opponent_flip_loses_flowers_first", self.rules.opponent_flip_loses_flowers_first);
+        record.add_setup("no_rng", self.rules.no_rng);
+
+        let mut flipped_own_skull = false;
+        for entry in self.history.iter() {
+            match entry {
+                HistoryEntry::PlayerAction(seat, action) => record.turns.push(RecordedTurn::new(seat_player(*seat), action)),
+                HistoryEntry::Flip(leader, player, _) => flipped_own_skull = leader == player,
+                HistoryEntry::LoseCard(_, card) if self.loses_random_card(flipped_own_skull) => {
+                    let turn = record.turns.last_mut().expect("Lost a card before any turns");
+                    turn.chance = Some(card.to_string());
+                }
+                _ => (),
+            }
+        }
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<Skulls, RecordError> {
+        record.check_game("skulls")?;
+        //older records don't have every rule, and were played with the defaults
+        let defaults = SkullsRules::default();
+        let rules = SkullsRules {
+            num_players: record.get_setup_or("players", defaults.num_players)?,
+            num_skulls: record.get_setup("skulls")?,
+            num_flowers: record.get_setup("flowers")?,
+            points_to_win: record.get_setup_or("points", defaults.points_to_win)?,
+            simultaneous_bidding: record.get_setup_or("simultaneous_bidding", defaults.simultaneous_bidding)?,
+            self_flip_loses_flowers_first: record.get_setup_or("self_flip_loses_flowers_first", defaults.self_flip_loses_flowers_first)?,
+            opponent_flip_loses_flowers_first: record.get_setup_or("opponent_flip_loses_flowers_first", defaults.opponent_flip_loses_flowers_first)?,
+            no_rng: record.get_setup_or("no_rng", defaults.no_rng)?,
+        };
+        if !(2..=MAX_PLAYERS).contains(&rules.num_players) {
+            return Err(RecordError::BadSetup { key: "players", value: rules.num_players.to_string() });
+        }
+        if rules.num_skulls as u32 + rules.num_flowers as u32 == 0 {
+            return Err(RecordError::BadSetup { key: "flowers", value: rules.num_flowers.to_string() });
+        }
+        if rules.points_to_win == 0 {
+            return Err(RecordError::BadSetup { key: "points", value: rules.points_to_win.to_string() });
+        }
+        let first: String = record.get_setup("first")?;
+        let first = parse_seat(&first).filter(|seat| *seat < rules.num_players)
+            .ok_or(RecordError::BadSetup { key: "first", value: first })?;
+        let mut game = Skulls::with_rules(rules, first);
+
+        for (i, turn) in record.turns.iter().enumerate() {
+            let action = record.parse_action(&game, i)?;
+            let lost_card = match &turn.chance {
+                Some(chance) if chance == "Skull" => Some(Card::Skull),
+                Some(chance) if chance == "Flower" => Some(Card::Flower),
+                Some(chance) => return Err(RecordError::BadTurn { turn: i, value: chance.clone() }),
+                None => None,
+            };
+
+            //the recorded card is only used if chance decides something this turn
+            let mut used_chance = false;
+            let mut has_card = true;
+            game.resolve_turn(turn.player, &action, &mut |hand| {
+                used_chance = true;
+                match lost_card {
+                    Some(Card::Skull) if hand.skulls > 0 => Card::Skull,
+                    Some(Card::Flower) if hand.flowers > 0 => Card::Flower,
+                    _ => {
+                        has_card = false;
+                        random_lost_card(hand)
+                    }
+                }
+            }).map_err(|err| RecordError::IllegalTurn { turn: i, reason: err.reason.to_string() })?;
+
+            if !has_card {
+                let reason = match &turn.chance {
+                    Some(chance) => format!("the leader has no {} to lose", chance),
+                    None => String::from("the lost card wasn't recorded"),
+                };
+                return Err(RecordError::IllegalTurn { turn: i, reason });
+            }
+            if !used_chance && lost_card.is_some() {
+                return Err(RecordError::IllegalTurn { turn: i, reason: String::from("no card is lost by chance this turn") });
+            }
+        }
+        Ok(game)
+    }
+}
+
 impl fmt::Display for Skulls {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "hands {:?}", self.hands)?;
         writeln!(f, "stacks {:?}", self.stacks)?;
         writeln!(f, "state {:?}", self.game_state)?;
-        writeln!(f, "has flipped {:?}", self.has_flipped)?;
+        writeln!(f, "points {:?}", self.points)?;
         Ok(())
     }
 }
@@ -328,13 +1060,20 @@ mod tests {
     use super::*;
     use crate::game::*;
 
+    fn seat(player: Player) -> Seat {
+        match player {
+            Player::P1 => 0,
+            Player::P2 => 1,
+        }
+    }
+
     fn wins_game_flipping(player: Player) -> Option<f32> {
         let mut game = Skulls::manual_new(player, 1, 3);
 
         //prestack
-        game.take_turn(player, &Action::Stack { card: Card::Flower });
-        game.take_turn(player.other(), &Action::Stack { card: Card::Flower });
-        assert_eq!(game.game_state, GameState::Stack { player });
+        game.take_turn(player, &Action::Stack { card: Card::Flower }).unwrap();
+        game.take_turn(player.other(), &Action::Stack { card: Card::Flower }).unwrap();
+        assert_eq!(game.game_state, GameState::Stack { player: seat(player) });
 
         //stack
         let (_, actions) = game.get_turn();
@@ -346,21 +1085,21 @@ mod tests {
         assert_eq!(actions.len(), 4);
 
         //bid
-        game.take_turn(player, &Action::Bid { amount: 2 });
+        game.take_turn(player, &Action::Bid { amount: 2 }).unwrap();
         let (p, actions) = game.get_turn();
         assert_eq!(p, player.other());
         assert_eq!(actions.len(), 1);
         assert_eq!(actions[0], Action::Pass);
-        game.take_turn(player.other(), &Action::Pass);
+        game.take_turn(player.other(), &Action::Pass).unwrap();
 
         //check result of bid
-        assert_eq!(player.lens(&game.has_flipped), &true);
-        assert_eq!(player.other().lens(&game.has_flipped), &false);
+        assert_eq!(game.points[seat(player)], 1);
+        assert_eq!(game.points[seat(player.other())], 0);
 
         //prestack
-        game.take_turn(player, &Action::Stack { card: Card::Flower });
-        game.take_turn(player.other(), &Action::Stack { card: Card::Flower });
-        assert_eq!(game.game_state, GameState::Stack { player: player });
+        game.take_turn(player, &Action::Stack { card: Card::Flower }).unwrap();
+        game.take_turn(player.other(), &Action::Stack { card: Card::Flower }).unwrap();
+        assert_eq!(game.game_state, GameState::Stack { player: seat(player) });
 
         //stack
         let (_, actions) = game.get_turn();
@@ -372,12 +1111,12 @@ mod tests {
         assert_eq!(actions.len(), 4);
 
         //bid
-        game.take_turn(player, &Action::Bid { amount: 2 });
+        game.take_turn(player, &Action::Bid { amount: 2 }).unwrap();
         let (p, actions) = game.get_turn();
         assert_eq!(p, player.other());
         assert_eq!(actions.len(), 1);
         assert_eq!(actions[0], Action::Pass);
-        game.take_turn(player.other(), &Action::Pass);
+        game.take_turn(player.other(), &Action::Pass).unwrap();
 
         game.get_reward()
     }
@@ -388,29 +1127,29 @@ mod tests {
 
         for i in 0..3 {
             //prestack
-            game.take_turn(player, &Action::Stack { card: Card::Flower });
-            game.take_turn(player.other(), &Action::Stack { card: Card::Flower });
+            game.take_turn(player, &Action::Stack { card: Card::Flower }).unwrap();
+            game.take_turn(player.other(), &Action::Stack { card: Card::Flower }).unwrap();
 
             //stack flowers while we can
-            while player.lens(&game.hands).flowers > 0 {
-                game.take_turn(player, &Action::Stack { card: Card::Flower });
-                game.take_turn(player.other(), &Action::Stack { card: Card::Flower });
+            while game.hands[seat(player)].flowers > 0 {
+                game.take_turn(player, &Action::Stack { card: Card::Flower }).unwrap();
+                game.take_turn(player.other(), &Action::Stack { card: Card::Flower }).unwrap();
             }
             //then play a skull
-            game.take_turn(player, &Action::Stack { card: Card::Skull});
-            game.take_turn(player.other(), &Action::Stack { card: Card::Skull });
+            game.take_turn(player, &Action::Stack { card: Card::Skull}).unwrap();
+            game.take_turn(player.other(), &Action::Stack { card: Card::Skull }).unwrap();
 
             //bid
-            game.take_turn(player, &Action::Bid { amount: 2 });
-            game.take_turn(player.other(), &Action::Pass);
+            game.take_turn(player, &Action::Bid { amount: 2 }).unwrap();
+            game.take_turn(player.other(), &Action::Pass).unwrap();
 
-            assert_eq!(player.lens(&game.hands).flowers, 3 - i - 1);
+            assert_eq!(game.hands[seat(player)].flowers, 3 - i - 1);
         }
 
-        game.take_turn(player, &Action::Stack { card: Card::Skull });
-        game.take_turn(player.other(), &Action::Stack { card: Card::Flower });
-        game.take_turn(player, &Action::Bid { amount: 2 });
-        game.take_turn(player.other(), &Action::Pass);
+        game.take_turn(player, &Action::Stack { card: Card::Skull }).unwrap();
+        game.take_turn(player.other(), &Action::Stack { card: Card::Flower }).unwrap();
+        game.take_turn(player, &Action::Bid { amount: 2 }).unwrap();
+        game.take_turn(player.other(), &Action::Pass).unwrap();
 
         game.get_reward()
     }
@@ -445,24 +1184,426 @@ mod tests {
             let player = *player;
             let mut game = Skulls::manual_new(player, 1, 3);
             //prestack
-            game.take_turn(player, &Action::Stack { card: Card::Skull });
-            game.take_turn(player.other(), &Action::Stack { card: Card::Skull });
+            game.take_turn(player, &Action::Stack { card: Card::Skull }).unwrap();
+            game.take_turn(player.other(), &Action::Stack { card: Card::Skull }).unwrap();
 
             //stack flowers while we can
             for _ in 0..3 {
-                game.take_turn(player, &Action::Stack { card: Card::Flower });
-                game.take_turn(player.other(), &Action::Stack { card: Card::Flower });
+                game.take_turn(player, &Action::Stack { card: Card::Flower }).unwrap();
+                game.take_turn(player.other(), &Action::Stack { card: Card::Flower }).unwrap();
             }
 
-            game.take_turn(player, &Action::Bid { amount: 1 });
-            game.take_turn(player.other(), &Action::Bid { amount: 2 });
-            game.take_turn(player, &Action::Bid { amount: 3 });
-            game.take_turn(player.other(), &Action::Bid { amount: 4 });
-            game.take_turn(player, &Action::Pass);
+            game.take_turn(player, &Action::Bid { amount: 1 }).unwrap();
+            game.take_turn(player.other(), &Action::Bid { amount: 2 }).unwrap();
+            game.take_turn(player, &Action::Bid { amount: 3 }).unwrap();
+            game.take_turn(player.other(), &Action::Bid { amount: 4 }).unwrap();
+            game.take_turn(player, &Action::Pass).unwrap();
 
             //other player just barely flipped over their own skull
-            assert_eq!(player.other().lens(&game.hands).flowers, 2);
+            assert_eq!(game.hands[seat(player.other())].flowers, 2);
+        }
+    }
+
+    #[test]
+    fn rejects_illegal_turns() {
+        let mut game = Skulls::manual_new(Player::P1, 1, 1);
+
+        let err = game.take_turn(Player::P1, &Action::Bid { amount: 1 }).unwrap_err();
+        assert_eq!(err.player, Player::P1);
+        assert_eq!(err.action, Action::Bid { amount: 1 });
+        assert_eq!(err.reason, IllegalMoveReason::InvalidAction("can't bid before every player has stacked"));
+
+        game.take_turn(Player::P1, &Action::Stack { card: Card::Skull }).unwrap();
+        let err = game.take_turn(Player::P1, &Action::Pass).unwrap_err();
+        assert_eq!(err.reason, IllegalMoveReason::WrongPlayer { expected: Player::P2 });
+        game.take_turn(Player::P2, &Action::Stack { card: Card::Flower }).unwrap();
+
+        let err = game.take_turn(Player::P1, &Action::Stack { card: Card::Skull }).unwrap_err();
+        assert_eq!(err.reason, IllegalMoveReason::InvalidAction("no skulls left in hand"));
+        let err = game.take_turn(Player::P1, &Action::Bid { amount: 3 }).unwrap_err();
+        assert_eq!(err.reason, IllegalMoveReason::InvalidAction("bid can't be more than the number of stacked cards"));
+        assert_eq!(game.history.len(), 2);
+    }
+
+    #[test]
+    fn enumerates_lost_cards() {
+        let mut game = Skulls::manual_new(Player::P1, 1, 3);
+        game.take_turn(Player::P1, &Action::Stack { card: Card::Flower }).unwrap();
+        game.take_turn(Player::P2, &Action::Stack { card: Card::Skull }).unwrap();
+        game.take_turn(Player::P1, &Action::Bid { amount: 2 }).unwrap();
+
+        let outcomes = game.take_turn_outcomes(Player::P2, &Action::Pass).unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].1, 0.75);
+        assert_eq!(outcomes[0].0.hands[0].flowers, 2);
+        assert_eq!(outcomes[1].1, 0.25);
+        assert_eq!(outcomes[1].0.hands[0].skulls, 0);
+
+        //no chance when bidding
+        let outcomes = game.take_turn_outcomes(Player::P2, &Action::Bid { amount: 2 });
+        assert!(outcomes.is_err());
+        let mut game = Skulls::manual_new(Player::P1, 1, 3);
+        game.take_turn(Player::P1, &Action::Stack { card: Card::Flower }).unwrap();
+        let outcomes = game.take_turn_outcomes(Player::P2, &Action::Stack { card: Card::Skull }).unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].1, 1.0);
+    }
+
+    #[test]
+    fn opponent_skull_loses_random_card() {
+        //P1 flips their own flower, then P2's skull, which used to count as P1's own skull and lose a flower
+        let mut game = Skulls::manual_new(Player::P1, 1, 3);
+        game.take_turn(Player::P1, &Action::Stack { card: Card::Flower }).unwrap();
+        game.take_turn(Player::P2, &Action::Stack { card: Card::Skull }).unwrap();
+        game.take_turn(Player::P1, &Action::Bid { amount: 2 }).unwrap();
+        let outcomes = game.take_turn_outcomes(Player::P2, &Action::Pass).unwrap();
+        assert_eq!(outcomes.len(), 2);
+        for (outcome, _) in outcomes.iter() {
+            assert!(outcome.history.iter().any(|entry| matches!(entry, HistoryEntry::Flip(0, 1, Card::Skull))));
+        }
+
+        //flipping their own skull still loses a flower first
+        let mut game = Skulls::manual_new(Player::P1, 1, 3);
+        game.take_turn(Player::P1, &Action::Stack { card: Card::Skull }).unwrap();
+        game.take_turn(Player::P2, &Action::Stack { card: Card::Flower }).unwrap();
+        game.take_turn(Player::P1, &Action::Bid { amount: 2 }).unwrap();
+        let outcomes = game.take_turn_outcomes(Player::P2, &Action::Pass).unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].0.history.iter().any(|entry| matches!(entry, HistoryEntry::Flip(0, 0, Card::Skull))));
+        assert_eq!(outcomes[0].0.hands[0].flowers, 2);
+        assert_eq!(outcomes[0].0.hands[0].skulls, 1);
+    }
+
+    #[test]
+    fn enumerates_hand_missing_a_card() {
+        let rules = SkullsRules { num_skulls: 1, num_flowers: 0, self_flip_loses_flowers_first: false, ..SkullsRules::default() };
+        let mut game = Skulls::with_rules(rules, 0);
+        game.take_turn(Player::P1, &Action::Stack { card: Card::Skull }).unwrap();
+        game.take_turn(Player::P2, &Action::Stack { card: Card::Skull }).unwrap();
+        game.take_turn(Player::P1, &Action::Bid { amount: 1 }).unwrap();
+        let outcomes = game.take_turn_outcomes(Player::P2, &Action::Pass).unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].1, 1.0);
+        assert_eq!(outcomes[0].0.hands[0].skulls, 0);
+    }
+
+    #[test]
+    fn opponent_flip_can_lose_flowers_first() {
+        let rules = SkullsRules { opponent_flip_loses_flowers_first: true, ..SkullsRules::default() };
+        let mut game = Skulls::with_rules(rules, 0);
+        game.take_turn(Player::P1, &Action::Stack { card: Card::Flower }).unwrap();
+        game.take_turn(Player::P2, &Action::Stack { card: Card::Skull }).unwrap();
+        game.take_turn(Player::P1, &Action::Bid { amount: 2 }).unwrap();
+        let outcomes = game.take_turn_outcomes(Player::P2, &Action::Pass).unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].0.hands[0].flowers, 2);
+        assert_eq!(outcomes[0].0.hands[0].skulls, 1);
+        assert_eq!(Skulls::from_record(&outcomes[0].0.get_record()).unwrap().rules, rules);
+    }
+
+    #[test]
+    fn records_lost_cards() {
+        let mut game = Skulls::manual_new(Player::P1, 1, 3);
+        game.take_turn(Player::P1, &Action::Stack { card: Card::Flower }).unwrap();
+        game.take_turn(Player::P2, &Action::Stack { card: Card::Skull }).unwrap();
+        game.take_turn(Player::P1, &Action::Bid { amount: 2 }).unwrap();
+        let (mut game, _) = game.take_turn_outcomes(Player::P2, &Action::Pass).unwrap().remove(1);
+
+        let mut record = game.get_record();
+        assert_eq!(record.turns[3].chance, Some(String::from("Skull")));
+        let restored = Skulls::from_record(&record).unwrap();
+        assert_eq!(restored.hands[0].skulls, 0);
+        assert_eq!(restored.hands[0].flowers, 3);
+
+        //P1 has no skull left to lose, so flipping P2's skull again can only cost a flower
+        game.take_turn(Player::P1, &Action::Stack { card: Card::Flower }).unwrap();
+        game.take_turn(Player::P2, &Action::Stack { card: Card::Skull }).unwrap();
+        game.take_turn(Player::P1, &Action::Bid { amount: 2 }).unwrap();
+        game.take_turn(Player::P2, &Action::Pass).unwrap();
+        let mut longer = game.get_record();
+        longer.turns[7].chance = Some(String::from("Skull"));
+        assert!(matches!(Skulls::from_record(&longer), Err(RecordError::IllegalTurn { turn: 7, .. })));
+
+        record.turns[3].chance = None;
+        assert!(matches!(Skulls::from_record(&record), Err(RecordError::IllegalTurn { turn: 3, .. })));
+        record.turns[2].chance = Some(String::from("Flower"));
+        assert!(matches!(Skulls::from_record(&record), Err(RecordError::IllegalTurn { turn: 2, .. })));
+        record.turns[2].chance = Some(String::from("Rose"));
+        assert!(matches!(Skulls::from_record(&record), Err(RecordError::BadTurn { turn: 2, .. })));
+    }
+
+    #[test]
+    fn undo_restores_state() {
+        let mut game = Skulls::manual_new(Player::P1, 1, 3);
+        let start = format!("{:?}", game);
+
+        let turns = [
+            (Player::P1, Action::Stack { card: Card::Skull }),
+            (Player::P2, Action::Stack { card: Card::Flower }),
+            (Player::P1, Action::Bid { amount: 1 }),
+            (Player::P2, Action::Bid { amount: 2 }),
+            (Player::P1, Action::Pass),
+        ];
+        let mut undos = vec![];
+        let mut states = vec![];
+        for (player, action) in turns.iter() {
+            states.push(format!("{:?}", game));
+            undos.push(game.apply_turn(*player, action).unwrap());
+        }
+        assert!(game.stacks[0].is_empty());
+
+        while let Some(undo) = undos.pop() {
+            game.undo_turn(undo);
+            assert_eq!(format!("{:?}", game), states.pop().unwrap());
+        }
+        assert_eq!(format!("{:?}", game), start);
+    }
+
+    #[test]
+    fn stacked_card_is_private() {
+        let mut game1 = Skulls::manual_new(Player::P1, 1, 3);
+        game1.take_turn(Player::P1, &Action::Stack { card: Card::Flower }).unwrap();
+        let mut game2 = Skulls::manual_new(Player::P1, 1, 3);
+        game2.take_turn(Player::P1, &Action::Stack { card: Card::Skull }).unwrap();
+
+        assert_eq!(game1.get_public_observations(), game2.get_public_observations());
+        assert_eq!(game1.get_public_state(), game2.get_public_state());
+        assert_eq!(game1.get_private_observation(Player::P2), game2.get_private_observation(Player::P2));
+        assert_ne!(game1.get_private_observation(Player::P1), game2.get_private_observation(Player::P1));
+        assert_eq!(game1.get_infoset(Player::P2).hash, game2.get_infoset(Player::P2).hash);
+    }
+
+
+    #[test]
+    fn summarizes_rounds() {
+        let play = |bids: &[(Player, u8)]| {
+            let mut game = Skulls::with_rules(SkullsRules::default(), 0);
+            for player in [Player::P1, Player::P2, Player::P1, Player::P2].iter() {
+                game.take_turn(*player, &Action::Stack { card: Card::Flower }).unwrap();
+            }
+            for (player, amount) in bids.iter() {
+                game.take_turn(*player, &Action::Bid { amount: *amount }).unwrap();
+            }
+            game
+        };
+        //both end with P2 leading a bid of 4
+        let game1 = play(&[(Player::P1, 1), (Player::P2, 2), (Player::P1, 3), (Player::P2, 4)]);
+        let game2 = play(&[(Player::P1, 1), (Player::P2, 4)]);
+        assert_ne!(game1.get_infoset(Player::P1).hash, game2.get_infoset(Player::P1).hash);
+        let game1 = Abstracted::new(game1, RoundSummary);
+        let game2 = Abstracted::new(game2, RoundSummary);
+        assert_eq!(game1.get_infoset(Player::P1).hash, game2.get_infoset(Player::P1).hash);
+    }
+
+    #[test]
+    fn abstracts_bids() {
+        let mut game = Skulls::manual_new(Player::P1, 1, 3);
+        for player in [Player::P1, Player::P2, Player::P1, Player::P2].iter() {
+            game.take_turn(*player, &Action::Stack { card: Card::Flower }).unwrap();
         }
+        let game = ActionAbstracted::new(game, BidSizes { amounts: vec![4] });
+        let (_, actions) = game.get_turn();
+        assert!(actions.contains(&Action::Bid { amount: 1 }));
+        assert!(!actions.contains(&Action::Bid { amount: 2 }));
+        assert!(actions.contains(&Action::Bid { amount: 4 }));
+
+        //an off-tree bid is taken in the real game, but P2 sees it as one of the kept bids around it
+        let on_tree = |amount| {
+            let mut game = game.clone();
+            game.take_turn(Player::P1, &Action::Bid { amount }).unwrap();
+            game.get_infoset(Player::P2).hash
+        };
+        let outcomes = game.take_turn_outcomes(Player::P1, &Action::Bid { amount: 2 }).unwrap();
+        assert_eq!(outcomes.len(), 2);
+        let expected = [(on_tree(1), pseudo_harmonic(1.0, 4.0, 2.0)), (on_tree(4), 1.0 - pseudo_harmonic(1.0, 4.0, 2.0))];
+        for ((outcome, prob), (infoset, expected_prob)) in outcomes.iter().zip(expected.iter()) {
+            assert_eq!(outcome.get_infoset(Player::P2).hash, *infoset);
+            assert!((prob - expected_prob).abs() < 1e-6);
+            assert_eq!(outcome.get_turn(), (Player::P2, vec![Action::Pass, Action::Bid { amount: 3 }, Action::Bid { amount: 4 }]));
+        }
+
+        //P2 can still pass or outbid the real bid of 2, even when it was seen as a bid of 4
+        let mut undo = outcomes[1].0.clone();
+        let token = undo.apply_turn(Player::P2, &Action::Bid { amount: 3 }).unwrap();
+        assert_eq!(undo.get_game().get_turn(), (Player::P1, vec![Action::Pass, Action::Bid { amount: 4 }]));
+        undo.undo_turn(token);
+        assert_eq!(undo.get_infoset(Player::P2).hash, on_tree(4));
+    }
+
+    fn multiplayer(num_players: usize, first: Seat, num_skulls: u8, num_flowers: u8) -> Skulls {
+        Skulls::with_rules(SkullsRules { num_players, num_skulls, num_flowers, ..SkullsRules::default() }, first)
+    }
+
+    /// Plays the turns in order, with flipping an opponent's skull costing a skull whenever there's one to lose
+    fn play(game: &mut Skulls, turns: &[(Seat, Action)]) {
+        let mut lose_skull = |hand: &Hand| if hand.skulls > 0 { Card::Skull } else { Card::Flower };
+        for (seat, action) in turns.iter() {
+            game.resolve_turn(seat_player(*seat), action, &mut lose_skull).unwrap();
+        }
+    }
+
+    #[test]
+    fn picks_stacks_to_flip() {
+        let mut game = multiplayer(3, 0, 1, 2);
+        play(&mut game, &[
+            (0, Action::Stack { card: Card::Flower }),
+            (1, Action::Stack { card: Card::Flower }),
+            (2, Action::Stack { card: Card::Skull }),
+            (0, Action::Bid { amount: 2 }),
+            (1, Action::Pass),
+        ]);
+        //every seat after the first plays for P2
+        assert_eq!(game.get_turn(), (Player::P2, vec![Action::Pass, Action::Bid { amount: 3 }]));
+        let err = game.take_turn(Player::P1, &Action::Pass).unwrap_err();
+        assert_eq!(err.reason, IllegalMoveReason::WrongPlayer { expected: Player::P2 });
+
+        //the leader flips their own flower, then has to pick between the other stacks
+        play(&mut game, &[(2, Action::Pass)]);
+        assert_eq!(game.get_turn(), (Player::P1, vec![Action::Flip { seat: 1 }, Action::Flip { seat: 2 }]));
+        let mut flowers = game.clone();
+        play(&mut flowers, &[(0, Action::Flip { seat: 1 })]);
+        assert_eq!(flowers.points[0], 1);
+        play(&mut game, &[(0, Action::Flip { seat: 2 })]);
+        assert_eq!(game.points[0], 0);
+        assert_eq!(game.hands[0].skulls, 0);
+        assert_eq!(game.game_state, GameState::PreStack { player: 0 });
+    }
+
+    #[test]
+    fn skull_owner_leads_after_elimination() {
+        let mut game = multiplayer(3, 1, 1, 1);
+        let round = [
+            (1, Action::Stack { card: Card::Flower }),
+            (2, Action::Stack { card: Card::Skull }),
+            (0, Action::Stack { card: Card::Flower }),
+            (1, Action::Bid { amount: 2 }),
+            (2, Action::Pass),
+            (0, Action::Pass),
+            (1, Action::Flip { seat: 2 }),
+        ];
+        play(&mut game, &round);
+        assert_eq!(game.game_state, GameState::PreStack { player: 1 });
+        play(&mut game, &round);
+
+        //P2 is out, so P3 starts and P1 follows
+        assert_eq!(game.game_state, GameState::PreStack { player: 2 });
+        play(&mut game, &[(2, Action::Stack { card: Card::Flower })]);
+        assert_eq!(game.game_state, GameState::PreStack { player: 0 });
+        play(&mut game, &[(0, Action::Stack { card: Card::Flower })]);
+        assert_eq!(game.game_state, GameState::Stack { player: 2 });
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn ends_when_p1_is_eliminated() {
+        let mut game = multiplayer(3, 0, 1, 1);
+        let round = [
+            (0, Action::Stack { card: Card::Flower }),
+            (1, Action::Stack { card: Card::Skull }),
+            (2, Action::Stack { card: Card::Flower }),
+            (0, Action::Bid { amount: 2 }),
+            (1, Action::Pass),
+            (2, Action::Pass),
+            (0, Action::Flip { seat: 1 }),
+        ];
+        play(&mut game, &round);
+        assert_eq!(game.game_state, GameState::PreStack { player: 0 });
+        play(&mut game, &round);
+
+        //every seat left plays for P2, so there's nothing left to play for
+        assert_eq!(game.game_state, GameState::End { winner: 1 });
+        assert_eq!(game.get_reward(), Some(-1.0));
+        assert_eq!(game.get_turn().1, vec![]);
+    }
+
+    #[test]
+    fn eliminated_challenger_picks_leader() {
+        let mut game = multiplayer(3, 1, 1, 0);
+        play(&mut game, &[
+            (1, Action::Stack { card: Card::Skull }),
+            (2, Action::Stack { card: Card::Skull }),
+            (0, Action::Stack { card: Card::Skull }),
+            (1, Action::Bid { amount: 1 }),
+            (2, Action::Pass),
+            (0, Action::Pass),
+        ]);
+        assert_eq!(game.get_turn(), (Player::P2, vec![Action::Lead { seat: 0 }, Action::Lead { seat: 2 }]));
+        play(&mut game, &[
+            (1, Action::Lead { seat: 2 }),
+            (2, Action::Stack { card: Card::Skull }),
+            (0, Action::Stack { card: Card::Skull }),
+            (2, Action::Bid { amount: 1 }),
+            (0, Action::Pass),
+        ]);
+        //the last player left wins, which is P1
+        assert_eq!(game.game_state, GameState::End { winner: 0 });
+        assert_eq!(game.get_reward(), Some(1.0));
+
+        let restored = Skulls::from_record(&game.get_record()).unwrap();
+        assert_eq!(format!("{:?}", restored), format!("{:?}", game));
+    }
+
+    #[test]
+    fn bids_at_once() {
+        let rules = SkullsRules { num_players: 3, num_flowers: 2, simultaneous_bidding: true, ..SkullsRules::default() };
+        let start = |raise| {
+            let mut game = Skulls::with_rules(rules, 0);
+            play(&mut game, &[
+                (0, Action::Stack { card: Card::Flower }),
+                (1, Action::Stack { card: Card::Flower }),
+                (2, Action::Stack { card: Card::Skull }),
+                (0, Action::Bid { amount: 1 }),
+                (1, Action::Bid { amount: raise }),
+            ]);
+            game
+        };
+
+        //P3 can't see P2's raise until the round is over
+        let (mut game1, mut game2) = (start(2), start(3));
+        assert_eq!(game1.get_infoset(Player::P2).hash, game2.get_infoset(Player::P2).hash);
+        assert_eq!(game1.get_infoset(Player::P1).hash, game2.get_infoset(Player::P1).hash);
+        assert_eq!(game1.get_public_state(), game2.get_public_state());
+        assert_eq!(game1.get_turn(), (Player::P2, vec![Action::Pass, Action::Bid { amount: 2 }, Action::Bid { amount: 3 }]));
+
+        //ties go to whoever is first after the leader
+        play(&mut game1, &[(2, Action::Bid { amount: 2 })]);
+        play(&mut game2, &[(2, Action::Bid { amount: 2 })]);
+        assert_ne!(game1.get_infoset(Player::P1).hash, game2.get_infoset(Player::P1).hash);
+        assert!(matches!(game1.game_state, GameState::Bid { amount: 2, leader: 1, player: 2, .. }));
+        assert!(matches!(game2.game_state, GameState::Bid { amount: 3, leader: 1, player: 2, .. }));
+
+        //the leader flips once nobody raises
+        play(&mut game2, &[(2, Action::Pass), (0, Action::Pass)]);
+        assert_eq!(game2.get_turn(), (Player::P2, vec![Action::Flip { seat: 0 }, Action::Flip { seat: 2 }]));
+        play(&mut game2, &[(1, Action::Flip { seat: 2 })]);
+        assert_eq!(game2.hands[1].skulls, 0);
+    }
+
+    #[test]
+    fn follows_rules() {
+        let rules = SkullsRules { points_to_win: 1, self_flip_loses_flowers_first: false, ..SkullsRules::default() };
+        let mut game = Skulls::with_rules(rules, 0);
+        assert_ne!(game.get_infoset(Player::P1).hash, Skulls::with_rules(SkullsRules::default(), 0).get_infoset(Player::P1).hash);
+        play(&mut game, &[
+            (0, Action::Stack { card: Card::Skull }),
+            (1, Action::Stack { card: Card::Flower }),
+            (0, Action::Bid { amount: 1 }),
+        ]);
+
+        //flipping your own skull costs a random card
+        let outcomes = game.take_turn_outcomes(Player::P2, &Action::Pass).unwrap();
+        assert_eq!(outcomes.len(), 2);
+        let record = outcomes[1].0.get_record();
+        assert_eq!(record.turns[3].chance, Some(String::from("Skull")));
+        assert_eq!(Skulls::from_record(&record).unwrap().rules, rules);
+
+        //one point is enough to win
+        let mut game = Skulls::with_rules(rules, 1);
+        play(&mut game, &[
+            (1, Action::Stack { card: Card::Flower }),
+            (0, Action::Stack { card: Card::Flower }),
+            (1, Action::Bid { amount: 2 }),
+            (0, Action::Pass),
+        ]);
+        assert_eq!(game.get_reward(), Some(-1.0));
+    }
+}
@@ -1,29 +1,104 @@
 use std::fmt::{Display, Formatter};
 
 use crate::game;
-use crate::game::{Player};
+use crate::game::{Player, IllegalMove, IllegalMoveReason, GameRecord, RecordedTurn, RecordGame, RecordError};
 
+/// Steps in (row, col) along a row, a column, and both diagonals
+const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+/// Moves a row and column on a board of the given width and height
+type Transform = fn(usize, usize, usize, usize) -> (usize, usize);
+
+/// TicTacToe on any size of board, where the first to get enough marks in a line wins
 #[derive(Debug, Clone)]
 pub struct TicTacToe {
+    width: usize,
+    height: usize,
+    /// Marks in a row needed to win
+    win_length: usize,
     current_player: Player,
-    /// 3x3 grid as a 1d row-major array
-    board: [Option<Player>; 9],
+    /// Grid as a 1d row-major array
+    board: Vec<Option<Player>>,
     /// Player actions in the order they were made
-    history: Vec<(Player, usize)>
+    history: Vec<(Player, usize)>,
+    /// Set by the move that completes a line, so the whole board never has to be checked
+    winner: Option<Player>,
 }
 
 impl TicTacToe {
+    /// The usual 3x3 board, needing 3 in a row
     pub fn new() -> TicTacToe {
+        TicTacToe::new_mnk(3, 3, 3)
+    }
+
+    pub fn new_mnk(width: usize, height: usize, win_length: usize) -> TicTacToe {
         TicTacToe {
+            width,
+            height,
+            win_length,
             current_player: Player::P1,
-            board: [
-                None, None, None, 
-                None, None, None, 
-                None, None, None
-            ],
+            board: vec![None; width * height],
             history: vec![],
+            winner: None,
         }
     }
+
+    /// Mark in the space, if anyone has played there
+    pub fn get_space(&self, space: usize) -> Option<Player> {
+        self.board[space]
+    }
+
+    /// Number of the same mark in a line through the space, counting both ways along the direction
+    fn line_length(&self, space: usize, (row_step, col_step): (isize, isize)) -> usize {
+        let mark = self.board[space];
+        let (width, height) = (self.width as isize, self.height as isize);
+        let (row, col) = ((space / self.width) as isize, (space % self.width) as isize);
+        let mut length = 1;
+        for sign in [-1, 1].iter() {
+            let (mut r, mut c) = (row + sign * row_step, col + sign * col_step);
+            while r >= 0 && r < height && c >= 0 && c < width && self.board[(r * width + c) as usize] == mark {
+                length += 1;
+                r += sign * row_step;
+                c += sign * col_step;
+            }
+        }
+        length
+    }
+
+    /// Whether the mark in the space is part of a long enough line
+    fn wins_through(&self, space: usize) -> bool {
+        self.board[space].is_some()
+            && DIRECTIONS.iter().any(|&direction| self.line_length(space, direction) >= self.win_length)
+    }
+
+    /// Where each space moves to under each rotation and reflection of the board
+    ///
+    /// Rotating by 90 degrees and reflecting across a diagonal only keep the shape of square boards
+    fn symmetries(&self) -> Vec<Vec<usize>> {
+        let (width, height) = (self.width, self.height);
+        let mut transforms: Vec<Transform> = vec![
+            |r, c, _, _| (r, c),
+            |r, c, w, h| (h - 1 - r, w - 1 - c),
+            |r, c, w, _| (r, w - 1 - c),
+            |r, c, _, h| (h - 1 - r, c),
+        ];
+        if width == height {
+            transforms.extend_from_slice(&[
+                |r, c, _, h| (c, h - 1 - r),
+                |r, c, w, _| (w - 1 - c, r),
+                |r, c, _, _| (c, r),
+                |r, c, w, h| (w - 1 - c, h - 1 - r),
+            ]);
+        }
+        transforms.iter()
+            .map(|transform| (0..width * height)
+                .map(|space| {
+                    let (r, c) = transform(space / width, space % width, width, height);
+                    r * width + c
+                })
+                .collect())
+            .collect()
+    }
 }
 
 impl game::Game for TicTacToe {
@@ -31,11 +106,16 @@ impl game::Game for TicTacToe {
     type Action = usize;
 
     fn get_turn(&self) -> (Player, Vec<usize>) {
+        //nothing to do once someone has won
+        if self.winner.is_some() {
+            return (self.current_player, vec![]);
+        }
+
         //empty spaces
         let spaces = self.board.iter().enumerate()
             .filter_map(|(ind, space)| {
                 match space {
-                    None => Some(ind as usize),
+                    None => Some(ind),
                     _ => None,
                 }
             })
@@ -44,13 +124,20 @@ impl game::Game for TicTacToe {
         (self.current_player, spaces)
     }
 
-    fn take_turn(&mut self, player: Player, action: &usize) {
-        if player != self.current_player {
-            panic!("Given player doesn't match saved player");
-        }
-
-        if self.board[*action].is_some() {
-            panic!("Tried to overwrite non-empty space");
+    fn take_turn(&mut self, player: Player, action: &usize) -> Result<(), IllegalMove<usize>> {
+        let reason = if self.winner.is_some() {
+            Some(IllegalMoveReason::GameOver)
+        } else if player != self.current_player {
+            Some(IllegalMoveReason::WrongPlayer { expected: self.current_player })
+        } else if *action >= self.board.len() {
+            Some(IllegalMoveReason::InvalidAction("space is off the board"))
+        } else if self.board[*action].is_some() {
+            Some(IllegalMoveReason::InvalidAction("space is already taken"))
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(IllegalMove::new(player, action, reason));
         }
         
         self.board[*action] = Some(player);
@@ -59,19 +146,32 @@ impl game::Game for TicTacToe {
             Player::P2 => Player::P1,
         };
         self.history.push((player, *action));
+        //only lines through the new mark can have been completed
+        if self.wins_through(*action) {
+            self.winner = Some(player);
+        }
+        Ok(())
+    }
+
+    fn num_action_ids(&self) -> usize {
+        self.board.len()
+    }
+
+    fn action_id(&self, action: &usize) -> usize {
+        *action
+    }
+
+    fn parse_action(&self, action: &str) -> Option<usize> {
+        action.parse().ok().filter(|&space| space < self.board.len())
     }
 
     fn get_reward(&self) -> Option<f32> {
-        
-        match check_rows(&self)
-            .or_else(|| check_cols(&self))
-            .or_else(|| check_diagonals(&self)) 
-        {
+        match self.winner {
             Some(Player::P1) => Some(1.0),
             Some(Player::P2) => Some(-1.0),
             None => {
                 //empty space means game isn't over
-                if self.board.iter().any(|space| space.is_none()) {
+                if self.history.len() < self.board.len() {
                     None
                 } else {
                     //no empty space and no winner means tie
@@ -82,63 +182,129 @@ impl game::Game for TicTacToe {
     }
 
     fn get_infoset(&self, _player: Player) -> game::Infoset {
-        game::Infoset::new(self.history.clone())
+        game::Infoset::new((self.width, self.height, self.win_length, &self.history))
     }
-}
 
-impl Display for TicTacToe {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "To move: {}", space_to_string(Some(self.current_player)))?;
-        for row in 0..3 {
-            for col in 0..3 {
-                write!(f, "|{}", space_to_string(self.board[3 * row + col]))?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
+    fn get_public_observations(&self) -> Vec<game::Observation> {
+        self.history.iter().map(game::Observation::new).collect()
+    }
+
+    fn get_private_observation(&self, _player: Player) -> game::Observation {
+        //nothing is private
+        game::Observation::new(0)
+    }
+
+    /// Every rotation and reflection of a board is the same position, whatever order the moves came in
+    fn get_canonical_infoset(&self, _player: Player) -> (game::Infoset, Option<Vec<usize>>) {
+        let (board, symmetry) = self.symmetries().into_iter()
+            .map(|symmetry| {
+                let mut board = vec![0u8; self.board.len()];
+                for (space, mark) in self.board.iter().enumerate() {
+                    board[symmetry[space]] = match mark {
+                        None => 0,
+                        Some(Player::P1) => 1,
+                        Some(Player::P2) => 2,
+                    };
+                }
+                (board, symmetry)
+            })
+            .min()
+            .expect("Failed to find a symmetry");
+        (game::Infoset::new((self.width, self.height, self.win_length, board, self.current_player)), Some(symmetry))
     }
 }
 
+impl game::UndoGame for TicTacToe {
+    //the history already has everything we need
+    type Undo = ();
 
+    fn apply_turn(&mut self, player: Player, action: &usize) -> Result<(), IllegalMove<usize>> {
+        game::Game::take_turn(self, player, action)
+    }
 
-pub fn space_to_string(space: Option<Player>) -> String {
-    match space {
-        Some(Player::P1) => String::from("X"),
-        Some(Player::P2) => String::from("O"),
-        None => String::from("_"),
+    fn undo_turn(&mut self, _undo: ()) {
+        let (player, action) = self.history.pop().expect("Tried to undo with no history");
+        self.board[action] = None;
+        self.current_player = player;
+        //nobody could have won before the last move
+        self.winner = None;
     }
 }
 
-fn check_rows(game: &TicTacToe) -> Option<Player> {
-    for row in 0..3 {
-        if check_three(&game.board, 3 * row, 3 * row + 1, 3 * row + 2) {
-            return game.board[3 * row]
+impl game::TensorGame for TicTacToe {
+    fn tensor_size(&self) -> usize {
+        3 * self.board.len()
+    }
+
+    fn get_infoset_tensor(&self, player: Player) -> Vec<f32> {
+        //each space is one-hot as the player's mark, the opponent's mark, or empty
+        let mut tensor = vec![0.0; self.tensor_size()];
+        for (i, space) in self.board.iter().enumerate() {
+            let offset = match space {
+                Some(p) if *p == player => 0,
+                Some(_) => 1,
+                None => 2,
+            };
+            tensor[3 * i + offset] = 1.0;
         }
+        tensor
     }
-    None
 }
 
-fn check_cols(game: &TicTacToe) -> Option<Player> {
-    for col in 0..3 {
-        if check_three(&game.board, col, col + 3, col + 6) {
-            return game.board[col]
+impl RecordGame for TicTacToe {
+    fn get_record(&self) -> GameRecord {
+        let mut record = GameRecord::new("tictactoe");
+        record.add_setup("width", self.width);
+        record.add_setup("height", self.height);
+        record.add_setup("win", self.win_length);
+        record.turns = self.history.iter()
+            .map(|(player, space)| RecordedTurn::new(*player, space))
+            .collect();
+        record
+    }
+
+    fn from_record(record: &GameRecord) -> Result<TicTacToe, RecordError> {
+        record.check_game("tictactoe")?;
+        //records from before boards could change size are all 3x3
+        let width: usize = record.get_setup_or("width", 3)?;
+        let height: usize = record.get_setup_or("height", 3)?;
+        let win_length: usize = record.get_setup_or("win", 3)?;
+        if width == 0 {
+            return Err(RecordError::BadSetup { key: "width", value: width.to_string() });
+        }
+        if height == 0 {
+            return Err(RecordError::BadSetup { key: "height", value: height.to_string() });
         }
+        if win_length == 0 {
+            return Err(RecordError::BadSetup { key: "win", value: win_length.to_string() });
+        }
+        let mut game = TicTacToe::new_mnk(width, height, win_length);
+        record.replay(&mut game)?;
+        Ok(game)
     }
-    None
 }
 
-fn check_diagonals(game: &TicTacToe) -> Option<Player> {
-    if check_three(&game.board, 0, 4, 8) {
-        game.board[0]
-    } else if check_three(&game.board, 2, 4, 6) {
-        game.board[2]
-    } else {
-        None
+impl Display for TicTacToe {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "To move: {}", space_to_string(Some(self.current_player)))?;
+        for row in 0..self.height {
+            for col in 0..self.width {
+                write!(f, "|{}", space_to_string(self.board[self.width * row + col]))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
     }
 }
 
-fn check_three(board: &[Option<Player>; 9], a: usize, b: usize, c: usize) -> bool {
-    board[a] != None && board[a] == board[b] && board[b] == board[c]
+
+
+pub fn space_to_string(space: Option<Player>) -> String {
+    match space {
+        Some(Player::P1) => String::from("X"),
+        Some(Player::P2) => String::from("O"),
+        None => String::from("_"),
+    }
 }
 
 #[cfg(test)]
@@ -146,104 +312,63 @@ mod tests {
     use super::*;
     use crate::game::*;
 
+    /// 3x3 game where the players take turns marking the spaces, starting with P1
+    fn play(spaces: &[usize]) -> TicTacToe {
+        let mut game = TicTacToe::new();
+        for (i, space) in spaces.iter().enumerate() {
+            let player = if i % 2 == 0 { Player::P1 } else { Player::P2 };
+            game.take_turn(player, space).unwrap();
+        }
+        game
+    }
+
     #[test]
     fn has_col() {
-        let board = [
-            Some(Player::P1), Some(Player::P2), None,
-            Some(Player::P1), Some(Player::P1), None,
-            Some(Player::P1), Some(Player::P2), Some(Player::P2),
-        ];
-        let game = TicTacToe {
-            history: vec![],
-            board,
-            current_player: Player::P2,
-        };
-        assert_eq!(check_cols(&game), Some(Player::P1));
+        let game = play(&[0, 1, 3, 7, 6]);
+        assert_eq!(game.get_reward(), Some(1.0));
+        assert_eq!(game.get_turn().1, vec![]);
     }
 
     #[test]
     fn has_no_col() {
-        let board = [
-            Some(Player::P1), Some(Player::P2), None,
-            Some(Player::P2), Some(Player::P1), None,
-            Some(Player::P1), Some(Player::P2), Some(Player::P2),
-        ];
-        let game = TicTacToe {
-            history: vec![],
-            board,
-            current_player: Player::P2,
-        };
-        assert_eq!(check_cols(&game), None);
+        let game = play(&[0, 3, 4, 8, 6]);
+        assert_eq!(game.get_reward(), None);
     }
 
     #[test]
     fn has_row() {
-        let board = [
-            None, Some(Player::P2), None,
-            Some(Player::P2), Some(Player::P2), Some(Player::P1),
-            Some(Player::P1), Some(Player::P1), Some(Player::P1),
-        ];
-        let game = TicTacToe {
-            history: vec![],
-            board,
-            current_player: Player::P2,
-        };
-        assert_eq!(check_rows(&game), Some(Player::P1));
+        let game = play(&[6, 3, 7, 4, 8]);
+        assert_eq!(game.get_reward(), Some(1.0));
     }
 
     #[test]
     fn has_no_row() {
-        let board = [
-            None, Some(Player::P2), None,
-            Some(Player::P1), None, Some(Player::P1),
-            Some(Player::P2), Some(Player::P2), Some(Player::P1),
-        ];
-        let game = TicTacToe {
-            history: vec![],
-            board,
-            current_player: Player::P1,
-        };
-        assert_eq!(check_rows(&game), None);
+        let game = play(&[3, 6, 5, 7, 8]);
+        assert_eq!(game.get_reward(), None);
     }
 
     #[test]
     fn has_diagonals() {
-        let board = [
-            None, Some(Player::P2), Some(Player::P2),
-            Some(Player::P1), Some(Player::P2), Some(Player::P1),
-            Some(Player::P2), Some(Player::P1), Some(Player::P1),
-        ];
-        let game = TicTacToe {
-            history: vec![],
-            board,
-            current_player: Player::P1,
-        };
-        assert_eq!(check_diagonals(&game), Some(Player::P2));
+        let game = play(&[0, 2, 1, 4, 3, 6]);
+        assert_eq!(game.get_reward(), Some(-1.0));
+        let game = play(&[0, 1, 4, 2, 8]);
+        assert_eq!(game.get_reward(), Some(1.0));
     }
- 
+
     #[test]
     fn has_no_diagonals() {
-        let board = [
-            None, Some(Player::P2), Some(Player::P2),
-            Some(Player::P1), Some(Player::P1), Some(Player::P2),
-            Some(Player::P2), Some(Player::P1), Some(Player::P1),
-        ];
-        let game = TicTacToe {
-            history: vec![],
-            board,
-            current_player: Player::P1,
-        };
-        assert_eq!(check_diagonals(&game), None);
+        let game = play(&[0, 2, 4, 8, 6]);
+        assert_eq!(game.get_reward(), None);
     }
 
     #[test]
     fn plays_game() {
         let mut game = TicTacToe::new();
-        game.take_turn(Player::P1, &0);
-        game.take_turn(Player::P2, &1);
-        game.take_turn(Player::P1, &4);
-        game.take_turn(Player::P2, &3);
-        game.take_turn(Player::P1, &8);
+        game.take_turn(Player::P1, &0).unwrap();
+        game.take_turn(Player::P2, &1).unwrap();
+        game.take_turn(Player::P1, &4).unwrap();
+        game.take_turn(Player::P2, &3).unwrap();
+        game.take_turn(Player::P1, &8).unwrap();
         assert_eq!(game.get_reward(), Some(1.0));
         assert_eq!(game.history.len(), 5);
         assert_eq!(game.get_infoset(Player::P1).hash,
@@ -253,18 +378,125 @@ mod tests {
     #[test]
     fn plays_game_tie() {
         let mut game = TicTacToe::new();
-        game.take_turn(Player::P1, &0);
-        game.take_turn(Player::P2, &4);
-        game.take_turn(Player::P1, &1);
-        game.take_turn(Player::P2, &2);
-        game.take_turn(Player::P1, &6);
-        game.take_turn(Player::P2, &3);
-        game.take_turn(Player::P1, &5);
-        game.take_turn(Player::P2, &7);
-        game.take_turn(Player::P1, &8);
+        game.take_turn(Player::P1, &0).unwrap();
+        game.take_turn(Player::P2, &4).unwrap();
+        game.take_turn(Player::P1, &1).unwrap();
+        game.take_turn(Player::P2, &2).unwrap();
+        game.take_turn(Player::P1, &6).unwrap();
+        game.take_turn(Player::P2, &3).unwrap();
+        game.take_turn(Player::P1, &5).unwrap();
+        game.take_turn(Player::P2, &7).unwrap();
+        game.take_turn(Player::P1, &8).unwrap();
         assert_eq!(game.get_reward(), Some(0.0));
         assert_eq!(game.history.len(), 9);
         assert_eq!(game.get_infoset(Player::P1).hash,
             game.get_infoset(Player::P2).hash);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rejects_overwrite() {
+        let mut game = TicTacToe::new();
+        game.take_turn(Player::P1, &4).unwrap();
+        let err = game.take_turn(Player::P2, &4).unwrap_err();
+        assert_eq!(err.player, Player::P2);
+        assert_eq!(err.action, 4);
+        assert_eq!(err.reason, IllegalMoveReason::InvalidAction("space is already taken"));
+        assert_eq!(game.history.len(), 1);
+
+        let err = game.take_turn(Player::P1, &0).unwrap_err();
+        assert_eq!(err.reason, IllegalMoveReason::WrongPlayer { expected: Player::P2 });
+    }
+
+    #[test]
+    fn canonicalizes_symmetries() {
+        //opposite corners with the center, reached in different orders
+        let game1 = play(&[0, 4]);
+        let game2 = play(&[8, 4]);
+        let game3 = play(&[4, 0, 8]);
+        let game4 = play(&[8, 0, 4]);
+        let (infoset1, ids1) = game1.get_canonical_action_ids(Player::P1, &[1, 3, 8]);
+        let (infoset2, ids2) = game2.get_canonical_action_ids(Player::P1, &[7, 5, 0]);
+        assert_eq!(infoset1.hash, infoset2.hash);
+        assert_eq!(ids1[2], ids2[2]);
+        assert!([ids1[0], ids1[1]].contains(&ids2[0]));
+        assert_eq!(game3.get_canonical_infoset(Player::P2).0.hash, game4.get_canonical_infoset(Player::P2).0.hash);
+        assert_ne!(game3.get_infoset(Player::P2).hash, game4.get_infoset(Player::P2).hash);
+        assert_ne!(infoset1.hash, game3.get_canonical_infoset(Player::P2).0.hash);
+    }
+
+    #[test]
+    fn undoes_game() {
+        let mut game = TicTacToe::new();
+        game.apply_turn(Player::P1, &0).unwrap();
+        game.apply_turn(Player::P2, &1).unwrap();
+        game.apply_turn(Player::P1, &4).unwrap();
+        game.undo_turn(());
+        game.undo_turn(());
+        assert_eq!(game.board[0], Some(Player::P1));
+        assert_eq!(game.board[1], None);
+        assert_eq!(game.board[4], None);
+        assert_eq!(game.history, vec![(Player::P1, 0)]);
+        assert_eq!(game.get_turn().0, Player::P2);
+    }
+
+    #[test]
+    fn plays_larger_boards() {
+        //4 in a row on a 5 wide, 4 high board
+        let mut game = TicTacToe::new_mnk(5, 4, 4);
+        assert_eq!(game.get_turn().1.len(), 20);
+        for (p1, p2) in [(5, 0), (6, 1), (7, 2)].iter() {
+            game.take_turn(Player::P1, p1).unwrap();
+            game.take_turn(Player::P2, p2).unwrap();
+        }
+        assert_eq!(game.get_reward(), None);
+        game.take_turn(Player::P1, &9).unwrap();
+        assert_eq!(game.get_reward(), None);
+        game.take_turn(Player::P2, &3).unwrap();
+        assert_eq!(game.get_reward(), Some(-1.0));
+        assert_eq!(game.get_turn().1, vec![]);
+
+        //undoing the winning move reopens the game
+        game.undo_turn(());
+        assert_eq!(game.get_reward(), None);
+        game.take_turn(Player::P2, &19).unwrap();
+        game.take_turn(Player::P1, &8).unwrap();
+        assert_eq!(game.get_reward(), Some(1.0));
+    }
+
+    #[test]
+    fn finds_diagonals_from_any_space() {
+        let mut game = TicTacToe::new_mnk(4, 4, 3);
+        //the last mark is in the middle of the anti-diagonal
+        for (player, space) in [(Player::P1, 3), (Player::P2, 0), (Player::P1, 9), (Player::P2, 1), (Player::P1, 6)].iter() {
+            game.take_turn(*player, space).unwrap();
+        }
+        assert_eq!(game.get_reward(), Some(1.0));
+        assert_eq!(game.symmetries().len(), 8);
+        assert_eq!(TicTacToe::new_mnk(5, 4, 4).symmetries().len(), 4);
+    }
+
+    #[test]
+    fn infosets_include_board() {
+        //the same moves on a board needing a longer line aren't the same position
+        let game = TicTacToe::new();
+        let longer = TicTacToe::new_mnk(3, 3, 4);
+        assert_ne!(game.get_infoset(Player::P1).hash, longer.get_infoset(Player::P1).hash);
+        assert_ne!(game.get_canonical_infoset(Player::P1).0.hash, longer.get_canonical_infoset(Player::P1).0.hash);
+        assert_ne!(game.get_infoset(Player::P1).hash, TicTacToe::new_mnk(9, 1, 3).get_infoset(Player::P1).hash);
+    }
+
+    #[test]
+    fn records_board_size() {
+        let mut game = TicTacToe::new_mnk(4, 3, 3);
+        game.take_turn(Player::P1, &11).unwrap();
+        let restored = TicTacToe::from_record(&game.get_record()).unwrap();
+        assert_eq!(format!("{:?}", restored), format!("{:?}", game));
+
+        //older records have no size and are always 3x3
+        let mut record = GameRecord::new("tictactoe");
+        record.turns.push(RecordedTurn::new(Player::P1, &4));
+        let restored = TicTacToe::from_record(&record).unwrap();
+        assert_eq!(restored.board.len(), 9);
+        assert_eq!(restored.board[4], Some(Player::P1));
+    }
+}
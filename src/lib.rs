@@ -5,12 +5,14 @@ use rand::distributions::Distribution;
 use std::sync::{Barrier, Arc};
 use std::io;
 use std::io::prelude::*;
+use std::env;
 
 mod game;
 mod cfr;
 mod regret;
 mod mcts_exploit;
 mod tree_exploit;
+mod tree_stats;
 
 use game::Game;
 use regret::RegretProvider;
@@ -23,7 +25,43 @@ pub fn run() {
         println!("-----------------");
     }
     */
-    do_cfr();
+
+    //the game is given as a spec like skulls(first=p1,skulls=1,flowers=2)
+    //or as a saved record to start from that position
+    let spec = env::args().nth(1).unwrap_or_else(|| String::from("skulls(first=p1,skulls=1,flowers=2)"));
+    if spec == "list" {
+        print!("{}", game::describe_games());
+        return;
+    }
+    if spec == "stats" {
+        print_game_stats(&env::args().skip(2).collect::<Vec<String>>());
+        return;
+    }
+    if spec.trim_start().starts_with('{') {
+        let restored = game::GameRecord::parse(&spec)
+            .and_then(|record| game::restore_game(&record, CfrRunner));
+        if let Err(err) = restored {
+            println!("{}", err);
+        }
+    } else if let Err(err) = game::build_game(&spec, CfrRunner) {
+        println!("{}", err);
+        println!("Available games:");
+        print!("{}", game::describe_games());
+    }
+}
+
+/// Trains and plays whichever game it's given
+struct CfrRunner;
+
+impl game::GameVisitor for CfrRunner {
+    type Output = ();
+
+    fn visit<G, F>(self, get_game: F)
+        where G: game::UndoGame + Clone + 'static,
+              F: Fn() -> G + Clone + Send + Sync + 'static
+    {
+        do_cfr(get_game);
+    }
 }
 
 enum RegretType {
@@ -48,15 +86,10 @@ fn get_regret_providers(regret_type: RegretType, num: usize, config: &regret::Re
     }).collect()
 }
 
-fn do_cfr() {
-
-    //let get_game = || game::TicTacToe::new();
-    //let get_game = || game::OneCardPoker::new();
-    let get_game = || game::Skulls::manual_new(game::Player::P1, 1, 2);
-    //let get_game = || game::MatrixGame::new(2, vec![1.0, 0.9, -0.7, 1.0]);
-    //let get_game = || game::DoubleMatrixGame::new(2, vec![1.0, 0.9, -0.7, 1.0]);
-    //let get_game = || game::MatrixGame::new_rock_paper_scissors();
-
+fn do_cfr<G, F>(get_game: F)
+    where G: game::UndoGame + Clone + 'static,
+          F: Fn() -> G + Clone + Send + Sync + 'static
+{
     //TODO have a better configuration method
     let num_threads = 16;
     let num_shards = 1;
@@ -118,12 +151,13 @@ fn do_cfr() {
     let children: Vec<thread::JoinHandle<_>> = cfrs.into_iter().enumerate().map(|(tid, mut cfr)| {
         let thread_barrier = barrier.clone();
         let providers = providers.clone();
+        let get_game = get_game.clone();
         thread::spawn(move || {
             for step in 0..num_steps {
                 /*
                 //do this first to get a baseline over the default random strategy
                 //all threads will do the mcts search, but thread 0 will manage everything
-                let mut mcts = mcts_exploit::MonteCarloTreeSearch::new(Box::new(get_game), &cfr, providers.clone());
+                let mut mcts = mcts_exploit::MonteCarloTreeSearch::new(Box::new(get_game.clone()), &cfr, providers.clone());
                 for _ in 0..1 {
                     let (exp1, exp2) = mcts.run(num_exploit_mcts_iterations);
                     if tid == 0 {
@@ -135,7 +169,7 @@ fn do_cfr() {
                 //thread_barrier.wait();
                 /*
                 if tid == 0 {
-                    let mut tree = tree_exploit::TreeExploit::new(Box::new(get_game), &cfr);
+                    let mut tree = tree_exploit::TreeExploit::new(Box::new(get_game.clone()), &cfr);
                     let rsp_vals = tree.run();
                     println!("step, p1 best response, p2 best reponse, exploitability, {}, {}, {}, {}", step, rsp_vals.0, rsp_vals.1, rsp_vals.0 + rsp_vals.1);
                 }
@@ -153,9 +187,9 @@ fn do_cfr() {
                     if tid == 0 {
                         println!("tid-iteration, {}, {}", tid, iteration);
                     }
-                    let game = get_game();
+                    let mut game = get_game();
                     cfr.set_iteration(iteration);
-                    let exp_val = cfr.search(game, 0);
+                    let exp_val = cfr.search_in_place(&mut game, 0);
                     if tid == 0 {
                         if let Some(exp_val) = exp_val {
                             //println!("iteration-exp value {}, {}", iteration, exp_val);
@@ -166,7 +200,7 @@ fn do_cfr() {
                 //thread_barrier.wait();
                 //do this first to get a baseline over the default random strategy
                 //all threads will do the mcts search, but thread 0 will manage everything
-                let mut mcts = mcts_exploit::MonteCarloTreeSearch::new(Box::new(get_game), &cfr, providers.clone());
+                let mut mcts = mcts_exploit::MonteCarloTreeSearch::new(Box::new(get_game.clone()), &cfr, providers.clone());
                 if tid == 0 {
                     mcts.set_verbose(true);
                 }
@@ -194,12 +228,55 @@ fn do_cfr() {
         play_cfr_game(&mut game, &strat_cfr);
     }
     //play_user_game(&mut get_game(), &strat_cfr);
-    //print_ocp_table(&strat_cfr);
+    //print_ocp_table(&strat_cfr, &game::OneCardPokerRules::default());
+}
+
+/// Prints the size of the game tree and how much storage training on it would need
+pub fn print_tree_stats<G: Game + Clone>(roots: Vec<(G, f32)>) {
+    println!("{}", tree_stats::TreeStats::new(roots));
+}
+
+/// Prints the tree stats of a game that's small enough to enumerate from every deal
+///
+/// The game is given by name and then its sizes, like `stats ocp 5`
+fn print_game_stats(args: &[String]) {
+    let sizes: Option<Vec<u32>> = args.iter().skip(1).map(|arg| arg.parse().ok()).collect();
+    match (args.first().map(String::as_str), sizes.as_deref()) {
+        (Some("ocp"), Some([])) => print_tree_stats(game::OneCardPoker::all_deals(&game::OneCardPokerRules::default())),
+        (Some("ocp"), Some([num_cards])) if *num_cards >= 2 => {
+            let rules = game::OneCardPokerRules { num_cards: *num_cards, ..game::OneCardPokerRules::default() };
+            print_tree_stats(game::OneCardPoker::all_deals(&rules));
+        },
+        (Some("kuhn"), Some([])) => print_tree_stats(game::KuhnPoker::all_deals()),
+        (Some("leduc"), Some([])) => print_tree_stats(game::LeducPoker::all_deals()),
+        //there are faces^(2 * dice) rolls, so only small games fit
+        (Some("liars_dice"), Some([num_dice, num_faces])) if (1..=5).contains(num_dice) && (2..=9).contains(num_faces) => {
+            print_tree_stats(game::LiarsDice::all_rolls(*num_dice as u8, *num_faces as u8));
+        },
+        (Some("skulls"), Some([num_skulls, num_flowers])) if (1..=u8::MAX as u32).contains(&(num_skulls + num_flowers)) => {
+            print_tree_stats(vec![(game::Skulls::manual_new(game::Player::P1, *num_skulls as u8, *num_flowers as u8), 1.0)]);
+        },
+        _ => println!("Usage: stats ocp [cards] | kuhn | leduc | liars_dice <dice> <faces> | skulls <skulls> <flowers>"),
+    }
 }
 
 //generate table like http://www.cs.cmu.edu/~ggordon/poker/
-pub fn print_ocp_table<R: regret::RegretHandler>(cfr : &cfr::CounterFactualRegret) {
-    let num_cards = 13;
+//with more than one bet size, bets get a row for each size
+pub fn print_ocp_table<R: regret::RegretHandler>(cfr : &cfr::CounterFactualRegret, rules: &game::OneCardPokerRules) {
+    let num_cards = rules.num_cards;
+    let new_game = |hands| game::OneCardPoker::manual_with_rules(rules.clone(), hands, game::Player::P2);
+    //actions are looked up by value, since where each one is depends on whether there is a bet to call
+    let action_prob = |game: &game::OneCardPoker, player, action: &game::OneCardPokerAction| {
+        let actions = game.get_turn().1;
+        let probs = cfr.get_avg_strategy(game, player, &actions).unwrap();
+        probs[actions.iter().position(|a| a == action).expect("Failed to find the action")]
+    };
+    //without any raises nobody can bet, so there are no bet rows
+    let bets: Vec<game::OneCardPokerAction> = if rules.max_raises == 0 {
+        vec![]
+    } else {
+        rules.bet_sizes.iter().map(|&amount| game::OneCardPokerAction::Bet { amount }).collect()
+    };
 
     print!("label,");
     for hand1 in 0..num_cards {
@@ -207,23 +284,25 @@ pub fn print_ocp_table<R: regret::RegretHandler>(cfr : &cfr::CounterFactualRegre
     }
     println!();
 
-    print!("on pass,");
-    for hand2 in 0..num_cards {
-        let mut game = game::OneCardPoker::manual_new((0, hand2), game::Player::P2);
-        game.take_turn(game::Player::P1, &game::OneCardPokerAction::Call);
-        let probs = cfr.get_avg_strategy(game::Player::P2, &game.get_infoset(game::Player::P2), 2).unwrap();
-        print!("{},", probs[1]);
+    for bet in bets.iter() {
+        print!("on pass {},", bet);
+        for hand2 in 0..num_cards {
+            let mut game = new_game((0, hand2));
+            game.take_turn(game::Player::P1, &game::OneCardPokerAction::Call).expect("Failed to check");
+            print!("{},", action_prob(&game, game::Player::P2, bet));
+        }
+        println!();
     }
-    println!();
 
-    print!("on bet,");
-    for hand2 in 0..num_cards {
-        let mut game = game::OneCardPoker::manual_new((0, hand2), game::Player::P2);
-        game.take_turn(game::Player::P1, &game::OneCardPokerAction::Bet);
-        let probs = cfr.get_avg_strategy(game::Player::P2, &game.get_infoset(game::Player::P2), 2).unwrap();
-        print!("{},", probs[1]);
+    for bet in bets.iter() {
+        print!("on {},", bet);
+        for hand2 in 0..num_cards {
+            let mut game = new_game((0, hand2));
+            game.take_turn(game::Player::P1, bet).expect("Failed to bet");
+            print!("{},", action_prob(&game, game::Player::P2, &game::OneCardPokerAction::Call));
+        }
+        println!();
     }
-    println!();
 
     println!();
 
@@ -233,49 +312,43 @@ pub fn print_ocp_table<R: regret::RegretHandler>(cfr : &cfr::CounterFactualRegre
     }
     println!();
 
-    print!("1st round,");
-    for hand1 in 0..num_cards {
-        let game = game::OneCardPoker::manual_new((hand1, 0), game::Player::P2);
-        let probs = cfr.get_avg_strategy(game::Player::P1, &game.get_infoset(game::Player::P1), 2).unwrap();
-        print!("{},", probs[1]);
+    for bet in bets.iter() {
+        print!("1st round {},", bet);
+        for hand1 in 0..num_cards {
+            let game = new_game((hand1, 0));
+            print!("{},", action_prob(&game, game::Player::P1, bet));
+        }
+        println!();
     }
-    println!();
-    print!("2nd round,");
-    for hand1 in 0..num_cards {
-        let mut game = game::OneCardPoker::manual_new((hand1, 0), game::Player::P2);
-        game.take_turn(game::Player::P1, &game::OneCardPokerAction::Call);
-        game.take_turn(game::Player::P2, &game::OneCardPokerAction::Bet);
-        let probs = cfr.get_avg_strategy(game::Player::P1, &game.get_infoset(game::Player::P1), 2).unwrap();
-        print!("{},", probs[1]);
+    for bet in bets.iter() {
+        print!("2nd round {},", bet);
+        for hand1 in 0..num_cards {
+            let mut game = new_game((hand1, 0));
+            game.take_turn(game::Player::P1, &game::OneCardPokerAction::Call).expect("Failed to check");
+            game.take_turn(game::Player::P2, bet).expect("Failed to bet");
+            print!("{},", action_prob(&game, game::Player::P1, &game::OneCardPokerAction::Call));
+        }
+        println!();
     }
-    println!();
 
 }
 
 pub fn play_cfr_game<G: Game>(game: &mut G, cfr: &cfr::CounterFactualRegret) {
-    let mut rng = rand::thread_rng();
-
     loop {
         println!();
         println!("{}", game);
         match game.get_reward() {
             None => {
-                let (player, actions) = game.get_turn();
-                let infoset = game.get_infoset(player);
-                let probs = cfr.get_avg_strategy(player, &infoset, actions.len())
-                    .expect("Failed to get strategy probabilities");
-
-                println!("player {}", player);
-                for (action, prob) in actions.iter().zip(probs.iter()) {
-                    println!("action {}\tprob {}", action, prob);
+                //at simultaneous turns each player picks without seeing the other's pick
+                if let Some((p1_actions, p2_actions)) = game.get_simultaneous_turn() {
+                    let p1_action = sample_cfr_action(game, cfr, game::Player::P1, &p1_actions, true);
+                    let p2_action = sample_cfr_action(game, cfr, game::Player::P2, &p2_actions, true);
+                    game.take_simultaneous_turn((&p1_action, &p2_action)).expect("Failed to take legal actions");
+                } else {
+                    let (player, actions) = game.get_turn();
+                    let action = sample_cfr_action(game, cfr, player, &actions, true);
+                    game.take_turn(player, &action).expect("Failed to take legal action");
                 }
-
-                let sampler = rand::distributions::WeightedIndex::new(&probs).unwrap();
-                let action_index = sampler.sample(&mut rng);
-                let action = &actions[action_index];
-
-                println!("***Taking action {}", action);
-                game.take_turn(player, action);
             },
             Some(reward) => {
                 println!("Player 1 Reward: {}", reward);
@@ -285,7 +358,62 @@ pub fn play_cfr_game<G: Game>(game: &mut G, cfr: &cfr::CounterFactualRegret) {
     }
 }
 
-pub fn play_user_game(game: &mut impl Game, cfr: &cfr::CounterFactualRegret) {
+/// Samples the player's action from the average strategy, printing the strategy if verbose
+fn sample_cfr_action<G: Game>(game: &G, cfr: &cfr::CounterFactualRegret, player: game::Player, actions: &[G::Action], verbose: bool) -> G::Action {
+    let probs = cfr.get_avg_strategy(game, player, actions)
+        .expect("Failed to get strategy probabilities");
+
+    if verbose {
+        println!("player {}", player);
+        for (action, prob) in actions.iter().zip(probs.iter()) {
+            println!("action {}\tprob {}", action, prob);
+        }
+    }
+
+    let sampler = rand::distributions::WeightedIndex::new(&probs).unwrap();
+    let action = &actions[sampler.sample(&mut rand::thread_rng())];
+    if verbose {
+        println!("***Taking action {}", action);
+    }
+    action.clone()
+}
+
+/// Asks the user to pick one of their actions, printing the record if they type save
+fn read_user_action<G: game::RecordGame>(game: &G, player: game::Player, actions: &[G::Action]) -> G::Action {
+    println!("{}", game.get_summary_string(player));
+    println!();
+    println!("User Player {}", player);
+
+    for (i, action) in actions.iter().enumerate() {
+        println!("action {}: {}", i, action);
+    }
+
+    let action_index = loop {
+        print!("Your action:");
+        io::stdout().flush().ok().expect("Failed to flush stdout");
+        let mut action_index = String::new();
+        io::stdin().read_line(&mut action_index)
+            .expect("Failed to read line");
+
+        if action_index.trim() == "save" {
+            println!("{}", game.get_record());
+            continue;
+        }
+
+        let action_index= action_index.trim().parse::<usize>();
+        if let Ok(i) = action_index {
+            if i < actions.len() {
+                break i;
+            }
+        }
+    };
+    let action = &actions[action_index];
+    println!("{} taking action {}", player, action);
+    action.clone()
+}
+
+/// Plays against the user, who can type save to get a record of the game to resume from later
+pub fn play_user_game(game: &mut impl game::RecordGame, cfr: &cfr::CounterFactualRegret) {
     let mut rng = rand::thread_rng();
     let user_player = if rng.gen::<bool>() {
         game::Player::P1
@@ -298,49 +426,31 @@ pub fn play_user_game(game: &mut impl Game, cfr: &cfr::CounterFactualRegret) {
         println!();
         match game.get_reward() {
             None => {
-                let (player, actions) = game.get_turn();
-                if player == user_player {
-                    println!("{}", game.get_summary_string(player));
-                    println!();
-                    println!("User Player {}", player);
-
-                    for (i, action) in actions.iter().enumerate() {
-                        println!("action {}: {}", i, action);
-                    }
-
-                    let action_index = loop {
-                        print!("Your action:");
-                        io::stdout().flush().ok().expect("Failed to flush stdout");
-                        let mut action_index = String::new();
-                        io::stdin().read_line(&mut action_index)
-                            .expect("Failed to read line");
-
-                        let action_index= action_index.trim().parse::<usize>();
-                        if let Ok(i) = action_index {
-                            if i < actions.len() {
-                                break i;
-                            }
-                        }
-                    };
-                    let action = &actions[action_index];
-                    println!("{} taking action {}", player, action);
-                    game.take_turn(player, action);
-
+                let result = if let Some(actions) = game.get_simultaneous_turn() {
+                    //the user picks first, but the cfr player doesn't get to see it
+                    let user_action = read_user_action(game, user_player, user_player.lens(&actions).as_slice());
+                    println!("CFR Player {}", user_player.other());
+                    let cfr_action = sample_cfr_action(game, cfr, user_player.other(), user_player.other().lens(&actions).as_slice(), false);
+                    println!("CFR Player {} taking action {}", user_player.other(), cfr_action);
+                    game.take_simultaneous_turn(user_player.pair(&user_action, &cfr_action))
                 } else {
-                    println!("CFR Player {}", player);
-                    let infoset = game.get_infoset(player);
-                    let probs = cfr.get_avg_strategy(player, &infoset, actions.len())
-                        .expect("Failed to get strategy probabilities");
+                    let (player, actions) = game.get_turn();
+                    if player == user_player {
+                        let action = read_user_action(game, player, &actions);
+                        game.take_turn(player, &action)
+                    } else {
+                        println!("CFR Player {}", player);
+                        for (i, action) in actions.iter().enumerate() {
+                            println!("action {}: {}", i, action);
+                        }
 
-                    for (i, action) in actions.iter().enumerate() {
-                        println!("action {}: {}", i, action);
+                        let action = sample_cfr_action(game, cfr, player, &actions, false);
+                        game.take_turn(player, &action)
                     }
-
-                    let sampler = rand::distributions::WeightedIndex::new(&probs).unwrap();
-                    let action_index = sampler.sample(&mut rng);
-                    let action = &actions[action_index];
-
-                    game.take_turn(player, action);
+                };
+                //an illegal move just means the user gets asked again
+                if let Err(err) = result {
+                    println!("{}", err);
                 }
             },
             Some(reward) => {
@@ -364,10 +474,17 @@ pub fn play_random_game<G: Game>(game: &mut G) {
         println!("{}", game);
         match game.get_reward() {
             None => {
-                let (player, actions) = game.get_turn();
-                let action = actions.choose(&mut rng).expect("No actions in unfinished game");
-                println!("***Taking action {}", action);
-                game.take_turn(player, action);
+                if let Some((p1_actions, p2_actions)) = game.get_simultaneous_turn() {
+                    let p1_action = p1_actions.choose(&mut rng).expect("No actions in unfinished game");
+                    let p2_action = p2_actions.choose(&mut rng).expect("No actions in unfinished game");
+                    println!("***Taking actions {} and {}", p1_action, p2_action);
+                    game.take_simultaneous_turn((p1_action, p2_action)).expect("Failed to take legal actions");
+                } else {
+                    let (player, actions) = game.get_turn();
+                    let action = actions.choose(&mut rng).expect("No actions in unfinished game");
+                    println!("***Taking action {}", action);
+                    game.take_turn(player, action).expect("Failed to take legal action");
+                }
             },
             Some(reward) => {
                 println!("Player 1 Reward: {}", reward);
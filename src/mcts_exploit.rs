@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use std::cmp::Ordering;
 use std::sync::{RwLock, Mutex, Arc};
 
-use crate::game::{Game, Player};
+use crate::game::{Game, IllegalMove, Player};
 use crate::cfr::CounterFactualRegret;
 
 //constant in UCT
@@ -75,14 +75,16 @@ impl<'a, G: Game> MonteCarloTreeSearch<'a, G> {
         for i in 0..iterations {
             self.on_player = self.on_player.other();
             let mut game = (*self.game_constructor)();
+            let bounds = game.get_utility_bounds();
 
             self.top_actions = None;
             self.search(&mut game, false, 0);
 
+            let on_player = self.on_player;
             if let Some(top_actions) = self.top_actions.as_mut() {
                 let init_value = top_actions
                     .iter()
-                    .map(|(w, n)| (w / (*n as f64)) * 2.0 - 1.0)//x*2-1 maps from [0,1] in mcts to [-1,1] in cfr
+                    .map(|(w, n)| denormalize(w / (*n as f64), on_player, bounds))
                     .filter(|a| a.is_finite())
                     .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
 
@@ -109,67 +111,102 @@ impl<'a, G: Game> MonteCarloTreeSearch<'a, G> {
 
     fn search(&mut self, game: &mut G, rollout: bool, depth: u64) -> f64 {
         if let Some(reward) = game.get_reward() {
-            //scale the reward space from [-1,1] to [0,1]
-            return if self.on_player == Player::P1 {
-                (reward + 1.0) / 2.0
-            } else {
-                (-1.0 * reward + 1.0) / 2.0
-            } as f64;
+            return normalize(reward, self.on_player, game.get_utility_bounds());
+        }
+
+        //at simultaneous turns, the opponent's action is sampled without knowing ours
+        if let Some(actions) = game.get_simultaneous_turn() {
+            let on_player = self.on_player;
+            let other_action = self.sample_action(game, on_player.other(), on_player.other().lens(&actions).as_slice());
+            return self.search_own_turn(game, rollout, depth, on_player, on_player.lens(&actions).as_slice(), |game, action| {
+                game.take_simultaneous_turn(on_player.pair(action, &other_action))
+            });
         }
 
         let (player, actions) = game.get_turn();
-        let infoset = game.get_infoset(player);
         if player == self.on_player {
-            //Simulation
-            if rollout {
-                let action = actions.choose(&mut rand::thread_rng()).unwrap();
-                game.take_turn(player, &action);
-                return self.search(game, rollout, depth + 1);
-            }
-
-            //Selection
-            let provider_index = infoset.hash as usize % self.providers.len();
-            let provider = &self.providers[provider_index];
-            let strategy = player.lens(&provider.strategies)
-                .read().unwrap();
-            let strat_data = strategy.get(&infoset.hash);
-            let (action_index, rollout) = if let Some(strat_data) = strat_data {
-                let strat_data = strat_data.lock().unwrap();
-                (uct(&strat_data), false)
-            } else {
-                (rand::thread_rng().gen_range(0, actions.len()), true)
-            };
-            drop(strategy);
-
-            //Expansion
-            game.take_turn(player, &actions[action_index]);
-            let result = self.search(game, rollout, depth + 1);
-
-            //Backpropagation
-            let provider_index = infoset.hash as usize % self.providers.len();
-            let provider = &self.providers[provider_index];
-            let mut strategy = player.lens(&provider.strategies)
-                .write().unwrap();
-            let mut entry = strategy.entry(infoset.hash).or_insert_with(|| Mutex::new(vec![(0.0, 0); actions.len()]))
-                .lock().unwrap();
-            entry[action_index].0 += result;
-            entry[action_index].1 += 1;
-            if self.top_actions.is_none() && depth == 0 {
-                self.top_actions = Some(entry.clone());
-            }
-
-            result
+            self.search_own_turn(game, rollout, depth, player, &actions, |game, action| game.take_turn(player, action))
         } else {
-            //sample cfr strategy, don't record anything
-            let probs = self.cfr.get_avg_strategy(player, &infoset, actions.len()).unwrap();
-            let sampler = rand::distributions::WeightedIndex::new(&probs).unwrap();
-            let action_index = sampler.sample(&mut rand::thread_rng());
-            let action = &actions[action_index];
-            game.take_turn(player, action);
+            let action = self.sample_action(game, player, &actions);
+            game.take_turn(player, &action).expect("Failed to take legal action");
 
             self.search(game, rollout, depth)
         }
     }
+
+    /// Picks one of our actions with UCT, or at random during rollouts, and records the result
+    ///
+    /// `take` takes the turn for the given action
+    fn search_own_turn<F>(&mut self, game: &mut G, rollout: bool, depth: u64, player: Player, actions: &[G::Action], take: F) -> f64
+        where F: Fn(&mut G, &G::Action) -> Result<(), IllegalMove<G::Action>>
+    {
+        //Simulation
+        if rollout {
+            let action = actions.choose(&mut rand::thread_rng()).unwrap();
+            take(game, action).expect("Failed to take legal action");
+            return self.search(game, rollout, depth + 1);
+        }
+
+        //Selection
+        let infoset = game.get_infoset(player);
+        let provider_index = infoset.hash as usize % self.providers.len();
+        let provider = &self.providers[provider_index];
+        let strategy = player.lens(&provider.strategies)
+            .read().unwrap();
+        let strat_data = strategy.get(&infoset.hash);
+        let (action_index, rollout) = if let Some(strat_data) = strat_data {
+            let strat_data = strat_data.lock().unwrap();
+            (uct(&strat_data), false)
+        } else {
+            (rand::thread_rng().gen_range(0, actions.len()), true)
+        };
+        drop(strategy);
+
+        //Expansion
+        take(game, &actions[action_index]).expect("Failed to take legal action");
+        let result = self.search(game, rollout, depth + 1);
+
+        //Backpropagation
+        let provider_index = infoset.hash as usize % self.providers.len();
+        let provider = &self.providers[provider_index];
+        let mut strategy = player.lens(&provider.strategies)
+            .write().unwrap();
+        let mut entry = strategy.entry(infoset.hash).or_insert_with(|| Mutex::new(vec![(0.0, 0); actions.len()]))
+            .lock().unwrap();
+        entry[action_index].0 += result;
+        entry[action_index].1 += 1;
+        if self.top_actions.is_none() && depth == 0 {
+            self.top_actions = Some(entry.clone());
+        }
+
+        result
+    }
+
+    /// Samples the opponent's action from the cfr strategy, without recording anything
+    fn sample_action(&self, game: &G, player: Player, actions: &[G::Action]) -> G::Action {
+        let probs = self.cfr.get_avg_strategy(game, player, actions).unwrap();
+        let sampler = rand::distributions::WeightedIndex::new(&probs).unwrap();
+        let action_index = sampler.sample(&mut rand::thread_rng());
+        actions[action_index].clone()
+    }
+}
+
+/// Scales P1's reward into [0,1] for the given player, which is what UCT expects
+fn normalize(reward: f32, player: Player, (min, max): (f32, f32)) -> f64 {
+    let value = ((reward - min) / (max - min)) as f64;
+    match player {
+        Player::P1 => value,
+        Player::P2 => 1.0 - value,
+    }
+}
+
+/// Maps a value from `normalize` back to the given player's reward, like cfr uses
+fn denormalize(value: f64, player: Player, (min, max): (f32, f32)) -> f64 {
+    let (min, max) = (min as f64, max as f64);
+    match player {
+        Player::P1 => value * (max - min) + min,
+        Player::P2 => value * (max - min) - max,
+    }
 }
 
 /// Returns index of sampled action according to UCT
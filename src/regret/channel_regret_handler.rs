@@ -1,6 +1,6 @@
 use std::error;
 
-use crate::regret::regret_provider::{Response, Request, RegretHandler, RegretRequest, RegretDelta};
+use crate::regret::regret_provider::{Response, Request, RegretHandler, RegretRequest, RegretDelta, ActionValues};
 use crate::game::{Player};
 
 /// Regret handler for using channels to communicate with a provider
@@ -21,7 +21,7 @@ impl RegretHandler for ChannelRegretHandler {
         Ok(rsp)
     }
 
-    fn send_delta(&self, player: Player, infoset_hash: u64, regret_delta: Vec<f32>, iteration: i32) -> Result<(), Box<dyn error::Error>> {
+    fn send_delta(&self, player: Player, infoset_hash: u64, regret_delta: ActionValues, iteration: i32) -> Result<(), Box<dyn error::Error>> {
         self.requester.try_send(Request::Delta(RegretDelta {
             player,
             infoset_hash,
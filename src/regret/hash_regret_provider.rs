@@ -1,5 +1,6 @@
 
 use std::collections::HashMap;
+use std::mem;
 use crossbeam_channel;
 use crate::game::*;
 use crate::regret::regret_provider::*;
@@ -21,8 +22,8 @@ pub struct HashRegretProvider {
     //which sender's we've sent Response::Closed to
     closed_senders: Vec<bool>,
 
-    p1_regrets: HashMap<u64, Vec<f32>>,
-    p2_regrets: HashMap<u64, Vec<f32>>,
+    p1_regrets: HashMap<u64, ActionValues>,
+    p2_regrets: HashMap<u64, ActionValues>,
 
     config: RegretConfig,
 }
@@ -45,6 +46,17 @@ impl HashRegretProvider {
         }
     }
 
+    /// Rough number of bytes needed to store one value per action for infosets with the given numbers of actions
+    pub fn estimate_bytes(num_actions: impl Iterator<Item = usize>) -> usize {
+        //the table stores a key, the values, and a control byte per entry, and is kept at most 7/8 full
+        let entry_size = (mem::size_of::<u64>() + mem::size_of::<ActionValues>() + 1) * 8 / 7;
+        num_actions
+            //infosets with fewer than 2 actions are never stored
+            .filter(|&n| n >= 2)
+            .map(|n| entry_size + n * (mem::size_of::<usize>() + mem::size_of::<f32>()))
+            .sum()
+    }
+
     fn handle_regret_request(&self, request: &RegretRequest) {
         let regrets = match request.player {
             Player::P1 => &self.p1_regrets,
@@ -62,7 +74,7 @@ impl HashRegretProvider {
 
     fn handle_regret_delta(&mut self, delta: RegretDelta) {
         //0 or 1 actions, nothing to do
-        if delta.regret_delta.len() < 2 {
+        if delta.regret_delta.values.len() < 2 {
             return;
         }
 
@@ -71,11 +83,9 @@ impl HashRegretProvider {
             Player::P2 => &mut self.p2_regrets,
         };
         let regret = regrets.entry(delta.infoset_hash)
-            .or_insert_with(|| vec![0.0; delta.regret_delta.len()]);
+            .or_insert_with(|| ActionValues::new(vec![], vec![]));
 
-        for (r, d) in regret.iter_mut().zip(delta.regret_delta.iter()) {
-            *r = self.config.apply_delta(delta.iteration as f32, *r, *d)
-        }
+        regret.apply_delta(&self.config, delta.iteration as f32, &delta.regret_delta);
     }
     
     fn reject_request(&self, request: &RegretRequest) {
@@ -142,7 +152,7 @@ mod tests {
     fn handles_delta_request_new() {
         let mut provider = HashRegretProvider::new();
         let infoset_hash = 1;
-        let regret = vec![1.0, 2.0, 3.0];
+        let regret = ActionValues::new(vec![0, 1, 2], vec![1.0, 2.0, 3.0]);
         provider.handle_regret_delta(RegretDelta {
             player: Player::P1,
             regret_delta: regret.clone(),
@@ -153,13 +163,34 @@ mod tests {
         assert_eq!(*saved_regret, regret);
     }
 
+    #[test]
+    fn handles_delta_request_reordered() {
+        let mut provider = HashRegretProvider::new();
+        let infoset_hash = 1;
+        provider.p1_regrets.insert(infoset_hash, ActionValues::new(vec![0, 1], vec![1.0, 2.0]));
+
+        //the same actions listed the other way around, plus a new one
+        provider.handle_regret_delta(RegretDelta {
+            player: Player::P1,
+            regret_delta: ActionValues::new(vec![1, 0, 2], vec![3.0, 4.0, 5.0]),
+            infoset_hash,
+            iteration: 1,
+        });
+        let saved_regret = &provider.p1_regrets[&infoset_hash];
+
+        let config = RegretConfig::default();
+        let target_regret = vec![config.apply_delta(1.0, 2.0, 3.0), config.apply_delta(1.0, 1.0, 4.0), 5.0];
+        assert_eq!(saved_regret.get(&[1, 0, 2]), target_regret);
+        assert_eq!(saved_regret.get(&[3]), vec![0.0]);
+    }
+
     //Taken out because the test doesn't account for dcfr
     //#[test]
     fn handles_delta_request_existing() {
         let mut provider = HashRegretProvider::new();
         let infoset_hash = 1;
-        let regret = vec![1.0, 2.0, 3.0];
-        let target_regret = vec![2.0, 4.0, 6.0];
+        let regret = ActionValues::new(vec![0, 1, 2], vec![1.0, 2.0, 3.0]);
+        let target_regret = ActionValues::new(vec![0, 1, 2], vec![2.0, 4.0, 6.0]);
         provider.p1_regrets.insert(infoset_hash, regret.clone());
 
         provider.handle_regret_delta(RegretDelta {
@@ -177,7 +208,7 @@ mod tests {
     fn gets_regret() {
         let mut provider = HashRegretProvider::new();
         let infoset_hash = 1;
-        let regret = vec![1.0, 2.0, 3.0];
+        let regret = ActionValues::new(vec![0, 1, 2], vec![1.0, 2.0, 3.0]);
         provider.p1_regrets.insert(1, regret.clone());
 
         let handler = provider.get_handler();
@@ -200,7 +231,7 @@ mod tests {
     fn sends_delta_new() {
         let mut provider = HashRegretProvider::new();
         let infoset_hash = 1;
-        let regret = vec![1.0, 2.0, 3.0];
+        let regret = ActionValues::new(vec![0, 1, 2], vec![1.0, 2.0, 3.0]);
         let handler = provider.get_handler();
 
         thread::spawn(move || {
@@ -224,7 +255,7 @@ mod tests {
     fn sends_delta_existing() {
         let mut provider = HashRegretProvider::new();
         let infoset_hash = 1;
-        let regret = vec![1.0, 2.0, 3.0];
+        let regret = ActionValues::new(vec![0, 1, 2], vec![1.0, 2.0, 3.0]);
         let handler = provider.get_handler();
 
         thread::spawn(move || {
@@ -241,7 +272,7 @@ mod tests {
             .expect("failed to get regret");
 
         if let Response::Regret(rsp) = rsp {
-            assert_eq!(rsp.regret.unwrap(), vec![2.0, 4.0, 6.0]);
+            assert_eq!(rsp.regret.unwrap().values, vec![2.0, 4.0, 6.0]);
         } else {
             panic!("got closed provider")
         }
@@ -4,7 +4,7 @@ mod regret_sharder;
 mod channel_regret_handler;
 mod rocksdb_regret_provider;
 
-pub use regret_provider::{RegretHandler, RegretProvider, Response, RegretResponse};
+pub use regret_provider::{RegretHandler, RegretProvider, Response, RegretResponse, ActionValues};
 pub use hash_regret_provider::HashRegretProvider;
 pub use regret_sharder::RegretSharder;
 pub use rocksdb_regret_provider::RocksDbRegretProvider;
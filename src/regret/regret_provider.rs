@@ -2,8 +2,48 @@ use std::error;
 
 use crate::game::Player;
 
+/// Values for some of an infoset's actions, along with the stable action ids they belong to
+///
+/// Keeping the ids means stored values still line up if a game changes the order it lists actions in
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionValues {
+    pub action_ids: Vec<usize>,
+    pub values: Vec<f32>,
+}
+
+impl ActionValues {
+    pub fn new(action_ids: Vec<usize>, values: Vec<f32>) -> ActionValues {
+        assert_eq!(action_ids.len(), values.len(), "Every action needs exactly one value");
+        ActionValues {
+            action_ids,
+            values,
+        }
+    }
+
+    /// Returns the value for each of the given action ids, or 0 if an action doesn't have a value
+    pub fn get(&self, action_ids: &[usize]) -> Vec<f32> {
+        action_ids.iter().map(|id| {
+            self.action_ids.iter().position(|i| i == id)
+                .map_or(0.0, |index| self.values[index])
+        }).collect()
+    }
+
+    /// Updates the value of each action in the delta, adding any actions that don't have a value yet
+    pub fn apply_delta(&mut self, config: &RegretConfig, iteration: f32, delta: &ActionValues) {
+        for (id, d) in delta.action_ids.iter().zip(delta.values.iter()) {
+            match self.action_ids.iter().position(|i| i == id) {
+                Some(index) => self.values[index] = config.apply_delta(iteration, self.values[index], *d),
+                None => {
+                    self.action_ids.push(*id);
+                    self.values.push(config.apply_delta(iteration, 0.0, *d));
+                }
+            }
+        }
+    }
+}
+
 pub struct RegretResponse {
-    pub regret: Option<Vec<f32>>,
+    pub regret: Option<ActionValues>,
 }
 
 pub struct RegretRequest {
@@ -15,7 +55,7 @@ pub struct RegretRequest {
 pub struct RegretDelta {
     pub player: Player,
     pub infoset_hash: u64,
-    pub regret_delta: Vec<f32>,
+    pub regret_delta: ActionValues,
     pub iteration: i32,
 }
 
@@ -36,7 +76,7 @@ pub enum Response {
 /// Interface for getting/setting regret in a regret provider
 pub trait RegretHandler : Send {
     fn get_regret(&self, player: Player, infoset_hash: u64) -> Result<Response, Box<dyn error::Error>>;
-    fn send_delta(&self, player: Player, infoset_hash: u64, regret_delta: Vec<f32>, iteration: i32) -> Result<(), Box<dyn error::Error>>;
+    fn send_delta(&self, player: Player, infoset_hash: u64, regret_delta: ActionValues, iteration: i32) -> Result<(), Box<dyn error::Error>>;
 }
 
 ///https://arxiv.org/pdf/1809.04040.pdf
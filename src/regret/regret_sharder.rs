@@ -1,6 +1,6 @@
 use std::error;
 
-use crate::regret::regret_provider::{RegretProvider, RegretHandler, Response};
+use crate::regret::regret_provider::{RegretProvider, RegretHandler, Response, ActionValues};
 use crate::game::Player;
 
 /// Combines several regret providers into a single regret handler
@@ -30,7 +30,7 @@ impl RegretHandler for RegretSharder {
         handler.get_regret(player, infoset_hash)
     }
 
-    fn send_delta(&self, player: Player, infoset_hash: u64, regret_delta: Vec<f32>, iteration: i32) -> Result<(), Box<dyn error::Error>> {
+    fn send_delta(&self, player: Player, infoset_hash: u64, regret_delta: ActionValues, iteration: i32) -> Result<(), Box<dyn error::Error>> {
         let handler_index = infoset_hash as usize % self.regret_handlers.len();
         let handler = &self.regret_handlers[handler_index];
         handler.send_delta(player, infoset_hash, regret_delta, iteration)
@@ -1,6 +1,7 @@
 use rocksdb::{DB, Options};
 use std::error;
 use std::sync::Arc;
+use std::mem;
 use std::mem::transmute;
 use bytevec::{ByteEncodable, ByteDecodable};
 
@@ -23,6 +24,20 @@ impl RocksDbRegretProvider {
             config: RegretConfig::default(),
         } 
     }
+
+    /// Rough number of bytes on disk needed to store one value per action for infosets with the given numbers of actions
+    ///
+    /// This ignores compression and the write buffer
+    pub fn estimate_bytes(num_actions: impl Iterator<Item = usize>) -> usize {
+        //rocksdb adds an 8 byte sequence number to each key, plus about as much again for its index
+        let key_size = mem::size_of::<u64>() + 16;
+        num_actions
+            //infosets with fewer than 2 actions are never stored
+            .filter(|&n| n >= 2)
+            //bytevec prefixes the encoded ids and values with u16 lengths
+            .map(|n| key_size + 3 * mem::size_of::<u16>() + n * (mem::size_of::<u32>() + mem::size_of::<f32>()))
+            .sum()
+    }
 }
 
 fn get_db(path: &str) -> Arc<DB> {
@@ -63,6 +78,19 @@ impl RegretProvider for RocksDbRegretProvider {
     }
 }
 
+/// Values are stored as (action ids, values)
+///
+/// u16 lengths leave room for games with more actions than a u8 length would allow
+fn encode_values(values: &ActionValues) -> Vec<u8> {
+    let action_ids: Vec<u32> = values.action_ids.iter().map(|&id| id as u32).collect();
+    (action_ids, values.values.clone()).encode::<u16>().expect("failed to encode regrets")
+}
+
+fn decode_values(raw: &[u8]) -> ActionValues {
+    let (action_ids, values) = <(Vec<u32>, Vec<f32>)>::decode::<u16>(raw).expect("Failed to decode regrets");
+    ActionValues::new(action_ids.into_iter().map(|id| id as usize).collect(), values)
+}
+
 pub struct RocksDbRegretHandler {
     dbs: (Arc<DB>, Arc<DB>),
     config: RegretConfig,
@@ -79,22 +107,16 @@ impl RegretHandler for RocksDbRegretHandler {
         let hash_bytes: [u8; 8] = unsafe { transmute(infoset_hash) };
         let raw = db.get(hash_bytes).expect("failed to read from db");
 
-        let regret = if let Some(raw) = raw {
-            //try to decode value
-            let regret = <Vec<f32>>::decode::<u8>(&raw).expect("Failed to decode Vec<f32>");
-            Some(regret)
-        } else {
-            None
-        };
+        let regret = raw.map(|raw| decode_values(&raw));
 
         Ok(Response::Regret(RegretResponse { regret }))
 
     }
 
-    fn send_delta(&self, player: Player, infoset_hash: u64, regret_delta: Vec<f32>, iteration: i32) -> Result<(), Box<dyn error::Error>> {
+    fn send_delta(&self, player: Player, infoset_hash: u64, regret_delta: ActionValues, iteration: i32) -> Result<(), Box<dyn error::Error>> {
 
         //nothing to do
-        if regret_delta.len() < 2 {
+        if regret_delta.values.len() < 2 {
             return Ok(());
         }
 
@@ -105,19 +127,16 @@ impl RegretHandler for RocksDbRegretHandler {
         let raw = db.get(hash_bytes).expect("failed to read from db");
 
         let regrets_to_insert = if let Some(raw) = raw {
-            //try to decode value
-            let regret = <Vec<f32>>::decode::<u8>(&raw).expect("Failed to decode Vec<f32>");
             //update regrets
-            let i = iteration as f32;
-            regret.into_iter().zip(regret_delta.into_iter()).map(|(r,d)| {
-                self.config.apply_delta(i, r, d)
-            }).collect()
+            let mut regret = decode_values(&raw);
+            regret.apply_delta(&self.config, iteration as f32, &regret_delta);
+            regret
         } else {
             //just insert regrets we're given
             regret_delta
         };
 
-        let enc_regrets = regrets_to_insert.encode::<u8>().expect("failed to encode regrets");
+        let enc_regrets = encode_values(&regrets_to_insert);
         db.put(hash_bytes, &enc_regrets[..]).expect("failed to save regrets to db");
 
         Ok(())
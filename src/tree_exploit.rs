@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::game::{Game, Player};
+use crate::game::{Game, UndoGame, Player, Sequential};
 use crate::cfr::CounterFactualRegret;
 
 pub struct TreeExploit<'a, G: Game> {
@@ -11,7 +11,7 @@ pub struct TreeExploit<'a, G: Game> {
     seen_infosets: HashSet<u64>,
 }
 
-impl<'a, G: Game + Clone> TreeExploit<'a, G> {
+impl<'a, G: UndoGame + Clone> TreeExploit<'a, G> {
     pub fn new(game_constructor: Box<dyn Fn() -> G>, cfr: &CounterFactualRegret) -> TreeExploit<G> {
         TreeExploit {
             game_constructor,
@@ -24,14 +24,14 @@ impl<'a, G: Game + Clone> TreeExploit<'a, G> {
     pub fn run(&mut self) -> (f32, f32) {
         //TODO consider sampling mutliple times to make up for the fact we're not including chance
         self.on_player = Player::P1;
-        let game = (*self.game_constructor)();
+        let game = Sequential::new((*self.game_constructor)());
         self.seen_infosets.clear();
-        let value1 = self.search(vec![(game, 1.0)]);
+        let value1 = self.search(&mut vec![(game, 1.0)]);
 
         self.on_player = Player::P2;
-        let game = (*self.game_constructor)();
+        let game = Sequential::new((*self.game_constructor)());
         self.seen_infosets.clear();
-        let value2 = self.search(vec![(game, 1.0)]);
+        let value2 = self.search(&mut vec![(game, 1.0)]);
 
         (value1, value2)
     }
@@ -48,11 +48,14 @@ impl<'a, G: Game + Clone> TreeExploit<'a, G> {
     /// Violating this will mean that you will have the same on-player infoset spread across separate search batches, which will mean that the calculation is incorrect.
     /// If a single history is present in a batch, then that entire history's infoset must also be present. 
     ///
-    /// Basically, all histories in an infoset must come from the same opponent state
+    /// Basically, all histories in an infoset must come from the same opponent state.
+    /// Simultaneous turns are split up by `Sequential`, and neither player sees the other's action until both have moved
     /// ***
+    /// The on-player's actions are applied to the games in place and undone afterwards,
+    /// but the off-player's actions need copies, as one game can lead to several infosets
     /// #TODO
     /// * If the results aren't good, make a new game type that makes chance a third player
-    fn search(&mut self, games: Vec<(G, f32)>) -> f32 {
+    fn search(&mut self, games: &mut Vec<(Sequential<G>, f32)>) -> f32 {
         let rewards: Option<Vec<f32>> = games.iter().map(|(g, rp)| {
             match g.get_reward() {
                 Some(r) => Some(r * rp),
@@ -73,17 +76,17 @@ impl<'a, G: Game + Clone> TreeExploit<'a, G> {
         let (player, actions) = games[0].0.get_turn();
 
         return if player == self.on_player {
-            self.check_infosets(&games, player);
+            self.check_infosets(games, player);
             //pick the action that maximizes the reward across infosets with probability 1
-            let mut best_response = -1.0;
+            let mut best_response = f32::NEG_INFINITY;
             for action in actions.iter() {
-                let mut subgames = vec![];
-                for (game, rp) in games.iter() {
-                    let mut subgame = game.clone();
-                    subgame.take_turn(player, action);
-                    subgames.push((subgame, *rp));
+                let undos: Vec<<Sequential<G> as UndoGame>::Undo> = games.iter_mut()
+                    .map(|(game, _)| game.apply_turn(player, action).expect("Failed to take legal action"))
+                    .collect();
+                let reward = self.search(games);
+                for ((game, _), undo) in games.iter_mut().zip(undos) {
+                    game.undo_turn(undo);
                 }
-                let reward = self.search(subgames);
                 if reward > best_response {
                     best_response = reward;
                 }
@@ -94,17 +97,16 @@ impl<'a, G: Game + Clone> TreeExploit<'a, G> {
             //sum of all response values weighted by probability
 
             //maps infoset to list of subgames in infoset and sum of probabilities for eaching each infoset from the current infoset
-            let mut subgame_map: HashMap<u64, Vec<(G, f32)>> = HashMap::new();
+            let mut subgame_map: HashMap<u64, Vec<(Sequential<G>, f32)>> = HashMap::new();
             for (game, rp) in games.iter() {
-                let infoset = game.get_infoset(player);
                 let (_, actions) = game.get_turn();
-                let probs = self.cfr.get_avg_strategy(player, &infoset, actions.len()).unwrap();
+                let probs = self.cfr.get_avg_strategy(game, player, &actions).unwrap();
                 for (action, prob) in actions.iter().zip(probs.iter()) {
                     if *prob == 0.0 {
                         continue;
                     }
                     let mut subgame = game.clone();
-                    subgame.take_turn(player, action);
+                    subgame.take_turn(player, action).expect("Failed to take legal action");
                     let new_infoset = subgame.get_infoset(player.other());
                     let entry = subgame_map.entry(new_infoset.hash).or_insert_with(|| vec![]);
                     //the reach probability will be correctly distributed across all histories in a infoset
@@ -113,8 +115,8 @@ impl<'a, G: Game + Clone> TreeExploit<'a, G> {
                 }
             }
             let mut response_values = vec![];
-            for (_, subgames) in subgame_map.into_iter() {
-                let reward = self.search(subgames);
+            for (_, mut subgames) in subgame_map.into_iter() {
+                let reward = self.search(&mut subgames);
                 response_values.push(reward);
             }
             let sum = response_values.iter().sum();
@@ -125,7 +127,7 @@ impl<'a, G: Game + Clone> TreeExploit<'a, G> {
 
     #[cfg(debug_assertions)]
     /// Verifies that all games belong to the same infoset, and we've never seen this infoset before
-    fn check_infosets(&mut self, games: &Vec<(G, f32)>, player: Player) {
+    fn check_infosets(&mut self, games: &Vec<(Sequential<G>, f32)>, player: Player) {
         let infoset = games[0].0.get_infoset(player);
         assert!(games.iter().all(|g| g.0.get_infoset(player).hash == infoset.hash));
         assert_eq!(self.seen_infosets.get(&infoset.hash), None);
@@ -133,5 +135,5 @@ impl<'a, G: Game + Clone> TreeExploit<'a, G> {
     }
 
     #[cfg(not(debug_assertions))]
-    fn check_infosets(&mut self, games: &Vec<(G, f32)>, player: Player) {}
+    fn check_infosets(&mut self, games: &Vec<(Sequential<G>, f32)>, player: Player) {}
 }
\ No newline at end of file
@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::game::{Game, Player, Sequential};
+use crate::regret::{HashRegretProvider, RocksDbRegretProvider};
+
+/// Size and shape of a game tree, found by visiting every history
+///
+/// Chance is enumerated with `Game::take_turn_outcomes`,
+/// and simultaneous turns count as P1's turn followed by P2's turn
+pub struct TreeStats {
+    pub num_histories: usize,
+    pub num_terminals: usize,
+    pub max_depth: usize,
+    /// Number of non-terminal histories for each number of actions
+    pub branching: BTreeMap<usize, usize>,
+    /// Number of actions at each infoset where each player moves
+    pub infoset_actions: (HashMap<u64, usize>, HashMap<u64, usize>),
+}
+
+impl TreeStats {
+    /// Visits every history reachable from the starting games
+    ///
+    /// The starting games should cover every initial deal, like in `TreeExploit`
+    pub fn new<G: Game + Clone>(roots: Vec<(G, f32)>) -> TreeStats {
+        let mut stats = TreeStats {
+            num_histories: 0,
+            num_terminals: 0,
+            max_depth: 0,
+            branching: BTreeMap::new(),
+            infoset_actions: (HashMap::new(), HashMap::new()),
+        };
+        for (game, _) in roots.into_iter() {
+            stats.visit(&Sequential::new(game), 0);
+        }
+        stats
+    }
+
+    fn visit<G: Game + Clone>(&mut self, game: &G, depth: usize) {
+        self.num_histories += 1;
+        if depth > self.max_depth {
+            self.max_depth = depth;
+        }
+
+        if game.get_reward().is_some() {
+            self.num_terminals += 1;
+            return;
+        }
+
+        let (player, actions) = game.get_turn();
+        let infoset = game.get_infoset(player);
+        player.lens_mut(&mut self.infoset_actions).insert(infoset.hash, actions.len());
+        *self.branching.entry(actions.len()).or_insert(0) += 1;
+
+        for action in actions.iter() {
+            let outcomes = game.take_turn_outcomes(player, action)
+                .expect("Failed to take legal action");
+            for (subgame, _) in outcomes.iter() {
+                self.visit(subgame, depth + 1);
+            }
+        }
+    }
+
+    pub fn num_infosets(&self) -> (usize, usize) {
+        (self.infoset_actions.0.len(), self.infoset_actions.1.len())
+    }
+
+    /// Estimated bytes needed to store either the regrets or the average strategy for the whole game
+    ///
+    /// Training stores both, so it needs twice this
+    pub fn estimated_bytes(&self) -> Vec<(&'static str, usize)> {
+        let num_actions = || self.infoset_actions.0.values().chain(self.infoset_actions.1.values()).copied();
+        vec![
+            ("HashMap", HashRegretProvider::estimate_bytes(num_actions())),
+            ("RocksDb", RocksDbRegretProvider::estimate_bytes(num_actions())),
+        ]
+    }
+}
+
+impl fmt::Display for TreeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        let (p1_infosets, p2_infosets) = self.num_infosets();
+        writeln!(f, "histories: {}", self.num_histories)?;
+        writeln!(f, "terminal histories: {}", self.num_terminals)?;
+        writeln!(f, "{} infosets: {}", Player::P1, p1_infosets)?;
+        writeln!(f, "{} infosets: {}", Player::P2, p2_infosets)?;
+        writeln!(f, "max depth: {}", self.max_depth)?;
+        writeln!(f, "branching factor (actions: histories):")?;
+        for (num_actions, count) in self.branching.iter() {
+            writeln!(f, "\t{}: {}", num_actions, count)?;
+        }
+        writeln!(f, "estimated storage for regrets (strategies need the same again):")?;
+        for (backend, bytes) in self.estimated_bytes() {
+            writeln!(f, "\t{}: {:.1} MiB", backend, bytes as f64 / (1024.0 * 1024.0))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::*;
+
+    #[test]
+    fn matrix_game_stats() {
+        let stats = TreeStats::new(vec![(MatrixGame::new_rock_paper_scissors(), 1.0)]);
+        assert_eq!(stats.num_histories, 13);
+        assert_eq!(stats.num_terminals, 9);
+        assert_eq!(stats.num_infosets(), (1, 1));
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.branching.get(&3), Some(&4));
+    }
+
+    #[test]
+    fn ocp_stats() {
+        let stats = TreeStats::new(OneCardPoker::all_deals(&OneCardPokerRules::default()));
+        //13 * 12 deals with 2 dealers, each with 9 histories
+        assert_eq!(stats.num_histories, 13 * 12 * 2 * 9);
+        //each of the 13 hands at 2 infosets each for the first and second player
+        assert_eq!(stats.num_infosets(), (13 * 4, 13 * 4));
+        assert_eq!(stats.max_depth, 3);
+    }
+}